@@ -0,0 +1,2 @@
+-- Optional IANA timezone override; NULL means use the region's capital city.
+ALTER TABLE users ADD COLUMN timezone TEXT;
@@ -0,0 +1,3 @@
+-- REGIONSUM figures stored alongside each dispatch price (demand_mw already exists)
+ALTER TABLE price_history ADD COLUMN available_gen_mw REAL;
+ALTER TABLE price_history ADD COLUMN net_interchange_mw REAL;
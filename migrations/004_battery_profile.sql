@@ -0,0 +1,4 @@
+-- Battery profile used by the /plan optimiser (battery_kwh already exists)
+ALTER TABLE users ADD COLUMN battery_power_kw REAL;
+ALTER TABLE users ADD COLUMN battery_efficiency REAL;
+ALTER TABLE users ADD COLUMN battery_reserve_pct REAL;
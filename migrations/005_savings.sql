@@ -0,0 +1,13 @@
+-- Estimated daily earnings from following the bot's advice vs a flat-rate baseline
+CREATE TABLE IF NOT EXISTS savings_daily (
+    chat_id        INTEGER NOT NULL,
+    region         TEXT NOT NULL,
+    date           TEXT NOT NULL,
+    charged_kwh    REAL NOT NULL,
+    discharged_kwh REAL NOT NULL,
+    spot_value     REAL NOT NULL,
+    flat_value     REAL NOT NULL,
+    computed_at    TEXT NOT NULL,
+    PRIMARY KEY (chat_id, date),
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
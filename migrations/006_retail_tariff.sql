@@ -0,0 +1,4 @@
+-- Retail tariff selection and optional thresholds in c/kWh (override $/MWh when set)
+ALTER TABLE users ADD COLUMN tariff_id TEXT;
+ALTER TABLE users ADD COLUMN high_alert_retail REAL;
+ALTER TABLE users ADD COLUMN low_alert_retail REAL;
@@ -0,0 +1,8 @@
+-- Periods (days or months) already imported by `nem-price-bot backfill`
+CREATE TABLE IF NOT EXISTS backfill_progress (
+    source        TEXT NOT NULL,
+    period        TEXT NOT NULL,
+    rows          INTEGER NOT NULL,
+    completed_at  TEXT NOT NULL,
+    PRIMARY KEY (source, period)
+);
@@ -0,0 +1,9 @@
+-- Dispatch intervals the scheduler could not recover from NEMweb's Current directory
+CREATE TABLE IF NOT EXISTS dispatch_gaps (
+    region        TEXT NOT NULL,
+    gap_start     TEXT NOT NULL,
+    gap_end       TEXT NOT NULL,
+    intervals     INTEGER NOT NULL,
+    detected_at   TEXT NOT NULL,
+    PRIMARY KEY (region, gap_start)
+);
@@ -0,0 +1,14 @@
+-- Daily pre-dispatch error sums per lead-time bucket; rolling scores add up days
+CREATE TABLE IF NOT EXISTS forecast_accuracy (
+    region        TEXT NOT NULL,
+    date          TEXT NOT NULL,
+    lead_bucket   TEXT NOT NULL,
+    samples       INTEGER NOT NULL,
+    abs_error     REAL NOT NULL,
+    error         REAL NOT NULL,
+    spike_hits    INTEGER NOT NULL,
+    spike_misses  INTEGER NOT NULL,
+    false_alarms  INTEGER NOT NULL,
+    computed_at   TEXT NOT NULL,
+    PRIMARY KEY (region, date, lead_bucket)
+);
@@ -0,0 +1,9 @@
+-- 5-minute pre-dispatch (P5MIN) prices for the next hour, one version per run
+CREATE TABLE IF NOT EXISTS p5min_forecast (
+    region        TEXT NOT NULL,
+    interval_time TEXT NOT NULL,
+    price_mwh     REAL NOT NULL,
+    run_time      TEXT NOT NULL,
+    fetched_at    TEXT NOT NULL,
+    PRIMARY KEY (region, interval_time, run_time)
+);
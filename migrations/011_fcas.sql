@@ -0,0 +1,18 @@
+-- FCAS prices from DISPATCH,PRICE, one row per service per interval
+CREATE TABLE IF NOT EXISTS fcas_price (
+    region        TEXT NOT NULL,
+    interval_time TEXT NOT NULL,
+    service       TEXT NOT NULL,
+    price_mwh     REAL NOT NULL,
+    fetched_at    TEXT NOT NULL,
+    PRIMARY KEY (region, interval_time, service)
+);
+
+-- Per-user FCAS price alerts: notify when a service's price rises above the threshold
+CREATE TABLE IF NOT EXISTS fcas_alert (
+    chat_id       INTEGER NOT NULL,
+    service       TEXT NOT NULL,
+    threshold     REAL NOT NULL,
+    PRIMARY KEY (chat_id, service),
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
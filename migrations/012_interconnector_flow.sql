@@ -0,0 +1,11 @@
+-- Interconnector flows and limits from DISPATCH,INTERCONNECTORRES
+CREATE TABLE IF NOT EXISTS interconnector_flow (
+    interconnector_id TEXT NOT NULL,
+    interval_time     TEXT NOT NULL,
+    mw_flow           REAL NOT NULL,
+    export_limit      REAL NOT NULL,
+    import_limit      REAL NOT NULL,
+    marginal_value    REAL NOT NULL,
+    fetched_at        TEXT NOT NULL,
+    PRIMARY KEY (interconnector_id, interval_time)
+);
@@ -0,0 +1,15 @@
+-- AEMO market notices, one row per notice ID
+CREATE TABLE IF NOT EXISTS market_notice (
+    notice_id     INTEGER PRIMARY KEY,
+    notice_type   TEXT NOT NULL,
+    reference     TEXT NOT NULL,
+    reason        TEXT NOT NULL,
+    issued_at     TEXT NOT NULL,
+    kind          TEXT NOT NULL,
+    severity      TEXT,
+    regions       TEXT NOT NULL,
+    fetched_at    TEXT NOT NULL
+);
+
+-- Minimum severity of market notices a user wants alerts for; NULL means off
+ALTER TABLE users ADD COLUMN notice_severity TEXT;
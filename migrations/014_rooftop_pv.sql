@@ -0,0 +1,10 @@
+-- Regional rooftop PV from AEMO ROOFTOP_PV reports; source is 'actual' or 'forecast'.
+-- Forecasts keep only the latest run's value for each half-hour.
+CREATE TABLE IF NOT EXISTS rooftop_pv (
+    region        TEXT NOT NULL,
+    interval_time TEXT NOT NULL,
+    source        TEXT NOT NULL,
+    power_mw      REAL NOT NULL,
+    fetched_at    TEXT NOT NULL,
+    PRIMARY KEY (region, interval_time, source)
+);
@@ -0,0 +1,10 @@
+-- Per-region generation by fuel type, aggregated from Dispatch_SCADA unit outputs
+CREATE TABLE IF NOT EXISTS generation_mix (
+    region        TEXT NOT NULL,
+    interval_time TEXT NOT NULL,
+    fuel          TEXT NOT NULL,
+    mw            REAL NOT NULL,
+    emissions_tph REAL NOT NULL,
+    fetched_at    TEXT NOT NULL,
+    PRIMARY KEY (region, interval_time, fuel)
+);
@@ -0,0 +1,3 @@
+-- Carbon intensity thresholds in g CO2-e/kWh; NULL means off
+ALTER TABLE users ADD COLUMN carbon_high REAL;
+ALTER TABLE users ADD COLUMN carbon_low REAL;
@@ -0,0 +1,18 @@
+-- Regions each user follows, each with its own thresholds and pause flag. users.region
+-- stays as the home region (tariff, battery, default timezone); the home region's row
+-- here supersedes users.high_alert and users.low_alert.
+CREATE TABLE IF NOT EXISTS user_regions (
+    chat_id       INTEGER NOT NULL,
+    region        TEXT NOT NULL,
+    high_alert    REAL NOT NULL DEFAULT 150.0,
+    low_alert     REAL NOT NULL DEFAULT 0.0,
+    is_active     INTEGER NOT NULL DEFAULT 1,
+    created_at    TEXT NOT NULL,
+    PRIMARY KEY (chat_id, region),
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
+
+INSERT OR IGNORE INTO user_regions (chat_id, region, high_alert, low_alert, created_at)
+SELECT chat_id, region, high_alert, low_alert, created_at FROM users;
+
+CREATE INDEX IF NOT EXISTS idx_user_regions_region ON user_regions(region);
@@ -0,0 +1,25 @@
+-- Quiet hours in the user's local time ("HH:MM"); NULL means off. During them alerts
+-- are dropped, or queued for a digest once they end when quiet_digest is set.
+ALTER TABLE users ADD COLUMN quiet_start TEXT;
+ALTER TABLE users ADD COLUMN quiet_end TEXT;
+ALTER TABLE users ADD COLUMN quiet_digest INTEGER NOT NULL DEFAULT 0;
+
+-- Alert types a user has switched off
+CREATE TABLE IF NOT EXISTS muted_alert (
+    chat_id       INTEGER NOT NULL,
+    alert_type    TEXT NOT NULL,
+    PRIMARY KEY (chat_id, alert_type),
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
+
+-- Alerts held back by quiet hours, latest per region and type
+CREATE TABLE IF NOT EXISTS alert_digest (
+    chat_id       INTEGER NOT NULL,
+    region        TEXT NOT NULL,
+    alert_type    TEXT NOT NULL,
+    price_mwh     REAL NOT NULL,
+    text          TEXT NOT NULL,
+    queued_at     TEXT NOT NULL,
+    PRIMARY KEY (chat_id, region, alert_type),
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
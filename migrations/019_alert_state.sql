@@ -0,0 +1,20 @@
+-- Hysteresis band ($/MWh) a price must move back past before a threshold alert clears,
+-- and how many consecutive intervals it must stay past the threshold before alerting
+ALTER TABLE users ADD COLUMN hysteresis REAL NOT NULL DEFAULT 0.0;
+ALTER TABLE users ADD COLUMN min_intervals INTEGER NOT NULL DEFAULT 1;
+
+-- Explicit level a high-price event clears below; NULL means high_alert minus the band
+ALTER TABLE user_regions ADD COLUMN clear_alert REAL;
+
+-- Threshold alert state per user, region and alert type
+CREATE TABLE IF NOT EXISTS alert_state (
+    chat_id       INTEGER NOT NULL,
+    region        TEXT NOT NULL,
+    alert_type    TEXT NOT NULL,
+    active        INTEGER NOT NULL DEFAULT 0,
+    streak        INTEGER NOT NULL DEFAULT 0,
+    last_interval TEXT,
+    updated_at    TEXT NOT NULL,
+    PRIMARY KEY (chat_id, region, alert_type),
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
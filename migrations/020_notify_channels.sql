@@ -0,0 +1,23 @@
+-- Alert destinations besides the user's Telegram chat (webhook, email, ntfy, gotify).
+-- `target` is the URL or email address, `secret` the HMAC key or access token.
+-- `alert_types` is a comma-separated list of routed types; NULL means every type.
+CREATE TABLE IF NOT EXISTS notify_channel (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id       INTEGER NOT NULL,
+    kind          TEXT NOT NULL,
+    target        TEXT NOT NULL,
+    secret        TEXT,
+    alert_types   TEXT,
+    last_error    TEXT,
+    created_at    TEXT NOT NULL,
+    FOREIGN KEY (chat_id) REFERENCES users(chat_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_notify_channel_chat ON notify_channel(chat_id);
+
+-- Alert types the Telegram chat itself receives, as above
+ALTER TABLE users ADD COLUMN telegram_alerts TEXT;
+
+-- Structured alert fields, kept for alerts held in the digest
+ALTER TABLE alert_digest ADD COLUMN interval_time TEXT NOT NULL DEFAULT '';
+ALTER TABLE alert_digest ADD COLUMN threshold REAL;
@@ -0,0 +1,3 @@
+-- Unit of alert_digest.threshold ("$/MWh", "c/kWh", "g/kWh" or "$"), so retail and
+-- carbon thresholds held for the digest aren't mistaken for wholesale prices
+ALTER TABLE alert_digest ADD COLUMN threshold_unit TEXT;
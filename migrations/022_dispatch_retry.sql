@@ -0,0 +1,8 @@
+-- Dispatch files that were listed on NEMweb but failed to download. Later ticks retry
+-- them, and their intervals only become gaps once the retries run out.
+CREATE TABLE IF NOT EXISTS dispatch_retry (
+    interval_time TEXT PRIMARY KEY,
+    attempts      INTEGER NOT NULL,
+    last_error    TEXT,
+    updated_at    TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Rows imported by `nem-price-bot backfill` are kept for good; live rows age out by
+-- interval time rather than by when they were fetched
+ALTER TABLE price_history ADD COLUMN backfilled INTEGER NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_price_time ON price_history(interval_time);
@@ -0,0 +1,3 @@
+-- Alerts that reached none of the user's destinations (quiet hours without the digest,
+-- rate limited, or failed), so an event's all clear can tell it was never seen
+ALTER TABLE alert_log ADD COLUMN dropped INTEGER NOT NULL DEFAULT 0;
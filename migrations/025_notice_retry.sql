@@ -0,0 +1,8 @@
+-- Market notices that were listed on NEMweb but failed to download. The newest stored
+-- notice marks where fetching resumes, so these are tracked here and retried instead.
+CREATE TABLE IF NOT EXISTS notice_retry (
+    notice_id  INTEGER PRIMARY KEY,
+    attempts   INTEGER NOT NULL,
+    last_error TEXT,
+    updated_at TEXT NOT NULL
+);
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use teloxide::prelude::*;
-use crate::bot::messages;
+use crate::bot::{commands, messages};
 use crate::db::Db;
 
 type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
@@ -13,25 +13,53 @@ pub async fn handle(bot: Bot, q: CallbackQuery, db: Arc<Db>) -> HandlerResult {
 
     if let Some(region) = data.strip_prefix("region:") {
         let chat_id = q.from.id.0 as i64;
-        db.upsert_user(chat_id, region)?;
 
-        let user = db.get_user(chat_id)?;
-        let (high, low) = user
-            .as_ref()
-            .map(|u| (u.high_alert, u.low_alert))
-            .unwrap_or((150.0, 0.0));
-        let text = messages::confirm_region(region, high, low);
+        // Unfollowing the only region would leave nothing to alert on
+        let before = db.get_user_regions(chat_id)?;
+        if before.len() == 1 && before[0].region == region {
+            bot.answer_callback_query(&q.id)
+                .text("You need at least one region. Pick another first to switch.")
+                .await?;
+            return Ok(());
+        }
+
+        let followed = db.toggle_region(chat_id, region)?;
+        let users = db.get_user_regions(chat_id)?;
+        let text = messages::confirm_regions(&users);
 
         // Answer callback to remove loading spinner
         bot.answer_callback_query(&q.id).await?;
 
-        // Edit the original message or send new one
+        // Edit the original message or send new one, keeping the keyboard for more toggles
         if let Some(msg) = q.message {
-            bot.edit_message_text(msg.chat().id, msg.id(), &text).await?;
+            bot.edit_message_text(msg.chat().id, msg.id(), &text)
+                .reply_markup(commands::region_keyboard(&followed))
+                .await?;
         } else {
             bot.send_message(ChatId(chat_id), &text).await?;
         }
+    } else if let Some(alert_type) = data.strip_prefix("mute:") {
+        let chat_id = q.from.id.0 as i64;
+        db.toggle_muted_alert(chat_id, alert_type)?;
+        refresh_quiet(&bot, &q, &db, chat_id).await?;
+    } else if data == "quiet:digest" {
+        let chat_id = q.from.id.0 as i64;
+        if let Some(user) = db.get_user(chat_id)? {
+            db.update_quiet_digest(chat_id, !user.quiet_digest)?;
+        }
+        refresh_quiet(&bot, &q, &db, chat_id).await?;
     }
 
     Ok(())
 }
+
+/// Re-render the /quiet settings message after a toggle.
+async fn refresh_quiet(bot: &Bot, q: &CallbackQuery, db: &Db, chat_id: i64) -> HandlerResult {
+    bot.answer_callback_query(&q.id).await?;
+    let (Some(user), Some(msg)) = (db.get_user(chat_id)?, q.message.as_ref()) else { return Ok(()) };
+    let muted = db.get_muted_alerts(chat_id)?;
+    bot.edit_message_text(msg.chat().id, msg.id(), messages::format_quiet_settings(&user, &muted))
+        .reply_markup(commands::quiet_keyboard(&user, &muted))
+        .await?;
+    Ok(())
+}
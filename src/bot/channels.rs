@@ -0,0 +1,485 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::Sha256;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use crate::bot::notifier::Notifier;
+use crate::engine::analyzer::PendingAlert;
+
+/// Alert types that go out at high priority on push services.
+const URGENT: &[&str] = &["high_price", "spike", "tight_supply", "app_start"];
+
+/// Loopback, private, shared (CGNAT), link-local, unique-local, broadcast or unspecified:
+/// the bot's own host or network. IPv6 forms that embed an IPv4 address (mapped, NAT64,
+/// 6to4) are judged by that address.
+pub fn is_private(ip: IpAddr) -> bool {
+    let v4_private = |ip: Ipv4Addr| {
+        ip.is_loopback()
+            || ip.is_private()
+            || ip.is_link_local()
+            || ip.is_unspecified()
+            || ip.is_broadcast()
+            || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+    };
+    match ip {
+        IpAddr::V4(ip) => v4_private(ip),
+        IpAddr::V6(ip) => {
+            let s = ip.segments();
+            let embedded = |hi: u16, lo: u16| Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo));
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || (s[0] & 0xfe00) == 0xfc00
+                || (s[0] & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(v4_private)
+                // NAT64 well-known prefix 64:ff9b::/96, and the local-use 64:ff9b:1::/48
+                || (s[..6] == [0x64, 0xff9b, 0, 0, 0, 0] && v4_private(embedded(s[6], s[7])))
+                || (s[0] == 0x64 && s[1] == 0xff9b && s[2] == 1)
+                // 6to4 2002::/16 carries the IPv4 address in the next 32 bits
+                || (s[0] == 0x2002 && v4_private(embedded(s[1], s[2])))
+        }
+    }
+}
+
+/// The checks that need no DNS: HTTPS only, and no private IP literal. Returns the host
+/// and port when the host is a name still to be resolved.
+fn precheck(url: &str, allow_http: bool) -> Result<Option<(String, u16)>> {
+    let parsed = reqwest::Url::parse(url).context("That isn't a valid URL")?;
+    match parsed.scheme() {
+        "https" => {}
+        "http" if allow_http => {}
+        _ => bail!("Only https:// URLs are accepted"),
+    }
+    if allow_http {
+        return Ok(None);
+    }
+    let host = parsed.host_str().context("The URL has no host")?;
+    match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) if is_private(ip) => bail!("Local and private network addresses aren't accepted"),
+        Ok(_) => Ok(None),
+        Err(_) => Ok(Some((host.to_string(), parsed.port_or_known_default().unwrap_or(443)))),
+    }
+}
+
+/// Check a stored URL just before delivery. Names aren't resolved here: the channel
+/// client's `PublicResolver` refuses private addresses when it connects.
+pub fn check_literal(url: &str, allow_http: bool) -> Result<()> {
+    precheck(url, allow_http).map(|_| ())
+}
+
+/// Check a user-supplied endpoint before storing it: HTTPS only, and not aimed at the
+/// bot's own host or network, resolving names to check every address they point at.
+/// `allow_http` lifts both, for self-hosters whose ntfy or Gotify server sits on the LAN.
+pub async fn check_url(url: &str, allow_http: bool) -> Result<()> {
+    let Some((host, port)) = precheck(url, allow_http)? else { return Ok(()) };
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .with_context(|| format!("Couldn't look up {host}"))?
+        .collect();
+    if addrs.is_empty() || addrs.iter().any(|a| is_private(a.ip())) {
+        bail!("Local and private network addresses aren't accepted");
+    }
+    Ok(())
+}
+
+/// DNS for the channel client that drops private addresses, so a name re-pointed at the
+/// bot's network after it was registered still can't be reached.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.filter(|a| !is_private(a.ip())).collect();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public address").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A random secret for signing webhook payloads when the user doesn't pick one.
+pub fn new_secret() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect()
+}
+
+/// Hex HMAC-SHA256 of `body`, sent as `X-Signature-256: sha256=<hex>` so receivers
+/// can check a payload came from the bot.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Send a request, treating anything but a 2xx (including redirects, which aren't
+/// followed) as a failed delivery.
+async fn deliver(req: reqwest::RequestBuilder) -> Result<()> {
+    let status = req.send().await?.status();
+    if !status.is_success() {
+        bail!("HTTP {status}");
+    }
+    Ok(())
+}
+
+/// POSTs the alert as JSON, signed with the channel's secret.
+pub struct Webhook {
+    pub client: reqwest::Client,
+    pub url: String,
+    pub secret: String,
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+    async fn send(&self, alert: &PendingAlert) -> Result<()> {
+        let body = serde_json::to_vec(&serde_json::json!({
+            "alert_type": alert.alert_type,
+            "title": alert.title(),
+            "region": alert.region,
+            "price_mwh": alert.price,
+            "interval_time": alert.interval_time,
+            "threshold": alert.threshold.map(|t| t.value),
+            "threshold_unit": alert.threshold.map(|t| t.unit.as_str()),
+            "value": alert.value,
+            "text": alert.text,
+            "sent_at": chrono::Utc::now().to_rfc3339(),
+        }))?;
+        deliver(
+            self.client
+                .post(&self.url)
+                .header("Content-Type", "application/json")
+                .header("X-Alert-Type", &alert.alert_type)
+                .header("X-Signature-256", format!("sha256={}", sign(&self.secret, &body)))
+                .body(body),
+        )
+        .await
+    }
+}
+
+/// Publishes to an ntfy topic URL, e.g. "https://ntfy.sh/my-battery".
+pub struct Ntfy {
+    pub client: reqwest::Client,
+    pub url: String,
+    /// Access token for protected topics.
+    pub token: Option<String>,
+}
+
+#[async_trait]
+impl Notifier for Ntfy {
+    async fn send(&self, alert: &PendingAlert) -> Result<()> {
+        let urgent = URGENT.contains(&alert.route_key());
+        let mut req = self
+            .client
+            .post(&self.url)
+            .header("Title", alert.title())
+            .header("Tags", alert.route_key())
+            .header("Priority", if urgent { "high" } else { "default" })
+            .body(alert.text.clone());
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        deliver(req).await
+    }
+}
+
+/// Posts to a Gotify server's message API with an application token.
+pub struct Gotify {
+    pub client: reqwest::Client,
+    /// Server root, e.g. "https://gotify.example.com".
+    pub url: String,
+    pub token: String,
+}
+
+#[async_trait]
+impl Notifier for Gotify {
+    async fn send(&self, alert: &PendingAlert) -> Result<()> {
+        let urgent = URGENT.contains(&alert.route_key());
+        deliver(
+            self.client
+                .post(format!("{}/message", self.url.trim_end_matches('/')))
+                .header("X-Gotify-Key", &self.token)
+                .json(&serde_json::json!({
+                    "title": alert.title(),
+                    "message": alert.text,
+                    "priority": if urgent { 8 } else { 5 },
+                })),
+        )
+        .await
+    }
+}
+
+/// The server's outgoing mail relay, shared by every email channel.
+pub struct Smtp {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Smtp {
+    pub fn new(url: &str, from: &str) -> Result<Self> {
+        Ok(Self {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(url).context("Invalid SMTP_URL")?.build(),
+            from: from.parse().context("Invalid SMTP_FROM address")?,
+        })
+    }
+}
+
+pub struct Email {
+    smtp: Arc<Smtp>,
+    to: Mailbox,
+}
+
+impl Email {
+    pub fn new(smtp: Arc<Smtp>, to: &str) -> Result<Self> {
+        Ok(Self { smtp, to: to.parse().context("That isn't a valid email address")? })
+    }
+}
+
+#[async_trait]
+impl Notifier for Email {
+    async fn send(&self, alert: &PendingAlert) -> Result<()> {
+        let message = Message::builder()
+            .from(self.smtp.from.clone())
+            .to(self.to.clone())
+            .subject(alert.title())
+            .header(ContentType::TEXT_PLAIN)
+            .body(alert.text.clone())?;
+        self.smtp.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::engine::analyzer::{Threshold, Unit};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn alert(alert_type: &str) -> PendingAlert {
+        PendingAlert {
+            chat_id: 1,
+            alert_type: alert_type.into(),
+            region: "NSW1".into(),
+            price: 312.5,
+            interval_time: "2026/01/15 17:30:00".into(),
+            threshold: Some(Threshold::new(300.0, Unit::Mwh)),
+            value: Some(312.5),
+            text: "Price is high".into(),
+        }
+    }
+
+    /// A request as the stub server saw it.
+    pub(crate) struct Request {
+        pub(crate) head: String,
+        pub(crate) body: Vec<u8>,
+    }
+
+    impl Request {
+        pub(crate) fn header(&self, name: &str) -> Option<&str> {
+            self.head.lines().find_map(|line| {
+                let (k, v) = line.split_once(':')?;
+                k.eq_ignore_ascii_case(name).then(|| v.trim())
+            })
+        }
+    }
+
+    /// Accept one request on a local port, answer it with `status`, and hand it back.
+    async fn stub(status: u16) -> (String, tokio::task::JoinHandle<Request>) {
+        stub_replying(status, "").await
+    }
+
+    /// As `stub`, answering with `body` as JSON.
+    pub(crate) async fn stub_replying(status: u16, body: &'static str) -> (String, tokio::task::JoinHandle<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let split = loop {
+                let n = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break i;
+                }
+            };
+            let head = String::from_utf8(buf[..split].to_vec()).unwrap();
+            let mut request = Request { head, body: buf[split + 4..].to_vec() };
+            let len: usize = request.header("content-length").map_or(0, |l| l.parse().unwrap());
+            while request.body.len() < len {
+                let n = stream.read(&mut chunk).await.unwrap();
+                request.body.extend_from_slice(&chunk[..n]);
+            }
+            let reply = format!(
+                "HTTP/1.1 {status} Stub\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(reply.as_bytes()).await.unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    /// Take one message over SMTP on a local port, and hand back the commands sent
+    /// and the DATA section.
+    async fn smtp_stub() -> (String, tokio::task::JoinHandle<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("smtp://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 stub ESMTP\r\n").await.unwrap();
+            let (mut commands, mut data) = (Vec::new(), String::new());
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = match line.split(' ').next().unwrap_or_default().to_ascii_uppercase().as_str() {
+                    "DATA" => {
+                        write.write_all(b"354 go ahead\r\n").await.unwrap();
+                        while let Some(line) = lines.next_line().await.unwrap().filter(|l| l != ".") {
+                            data.push_str(&line);
+                            data.push('\n');
+                        }
+                        b"250 queued\r\n"
+                    }
+                    "QUIT" => b"221 bye\r\n",
+                    _ => b"250 ok\r\n",
+                };
+                write.write_all(reply).await.unwrap();
+                commands.push(line);
+            }
+            (commands, data)
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn sign_matches_rfc_4231() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn private_addresses() {
+        for ip in ["127.0.0.1", "10.1.2.3", "192.168.0.10", "172.16.5.5", "169.254.169.254", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:10.0.0.1",
+            "100.64.0.1", "100.127.255.254", "255.255.255.255", "64:ff9b::7f00:1", "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::1", "2002:c0a8:1::1", "2002:7f00:1::"] {
+            assert!(is_private(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["1.1.1.1", "203.0.113.9", "2606:4700::1111", "::ffff:8.8.8.8", "100.63.255.255", "100.128.0.1",
+            "64:ff9b::808:808", "2002:808:808::1"] {
+            assert!(!is_private(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn check_url_rejects_plain_http_and_private_hosts() {
+        assert!(check_url("http://example.com/hook", false).await.is_err());
+        assert!(check_url("https://10.0.0.5/hook", false).await.is_err());
+        assert!(check_url("https://[::1]:8443/hook", false).await.is_err());
+        // Names are resolved, so one pointing at the bot's own host is caught too
+        assert!(check_url("https://localhost/hook", false).await.is_err());
+        assert!(check_url("https://1.1.1.1/hook", false).await.is_ok());
+        assert!(check_url("http://192.168.1.20:8080/message", true).await.is_ok());
+        assert!(check_literal("https://127.0.0.1/hook", false).is_err());
+        assert!(check_literal("https://hooks.example.com/x", false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn webhook_is_signed_json() {
+        let (url, server) = stub(200).await;
+        let hook = Webhook { client: reqwest::Client::new(), url: format!("{url}/hook"), secret: "s3cret".into() };
+        hook.send(&alert("high_price")).await.unwrap();
+        let req = server.await.unwrap();
+
+        assert!(req.head.starts_with("POST /hook "));
+        assert_eq!(req.header("x-alert-type"), Some("high_price"));
+        let signature = format!("sha256={}", sign("s3cret", &req.body));
+        assert_eq!(req.header("x-signature-256"), Some(signature.as_str()));
+        let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+        assert_eq!(body["title"], "High price - NSW");
+        assert_eq!(body["price_mwh"], 312.5);
+        assert_eq!(body["threshold"], 300.0);
+        assert_eq!(body["threshold_unit"], "$/MWh");
+        assert_eq!(body["value"], 312.5);
+
+        // Carbon alerts carry the intensity beside a g/kWh threshold, not the spot price
+        let (url, server) = stub(200).await;
+        let hook = Webhook { client: reqwest::Client::new(), url, secret: "s3cret".into() };
+        let carbon = PendingAlert {
+            threshold: Some(Threshold::new(700.0, Unit::GramsKwh)),
+            value: Some(742.0),
+            ..alert("carbon_high")
+        };
+        hook.send(&carbon).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&server.await.unwrap().body).unwrap();
+        assert_eq!(body["alert_type"], "carbon_high");
+        assert_eq!(body["threshold"], 700.0);
+        assert_eq!(body["threshold_unit"], "g/kWh");
+        assert_eq!(body["value"], 742.0);
+        assert_eq!(body["price_mwh"], 312.5);
+    }
+
+    #[tokio::test]
+    async fn ntfy_sets_title_tags_and_priority() {
+        let (url, server) = stub(200).await;
+        let ntfy = Ntfy { client: reqwest::Client::new(), url: format!("{url}/battery"), token: Some("tk_1".into()) };
+        ntfy.send(&alert("fcas_raise6sec")).await.unwrap();
+        let req = server.await.unwrap();
+
+        assert!(req.head.starts_with("POST /battery "));
+        assert_eq!(req.header("tags"), Some("fcas"));
+        assert_eq!(req.header("priority"), Some("default"));
+        assert_eq!(req.header("authorization"), Some("Bearer tk_1"));
+        assert_eq!(req.body, b"Price is high");
+    }
+
+    #[tokio::test]
+    async fn gotify_posts_to_message_api() {
+        let (url, server) = stub(200).await;
+        let gotify = Gotify { client: reqwest::Client::new(), url: format!("{url}/"), token: "app".into() };
+        gotify.send(&alert("spike")).await.unwrap();
+        let req = server.await.unwrap();
+
+        assert!(req.head.starts_with("POST /message "));
+        assert_eq!(req.header("x-gotify-key"), Some("app"));
+        let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+        assert_eq!(body["priority"], 8);
+        assert_eq!(body["message"], "Price is high");
+    }
+
+    #[tokio::test]
+    async fn error_status_fails_delivery() {
+        let (url, server) = stub(500).await;
+        let ntfy = Ntfy { client: reqwest::Client::new(), url, token: None };
+        assert!(ntfy.send(&alert("low_price")).await.is_err());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn email_goes_through_the_relay() {
+        let (url, server) = smtp_stub().await;
+        let smtp = Arc::new(Smtp::new(&url, "NEM Price Bot <bot@example.com>").unwrap());
+        let email = Email::new(smtp, "me@example.com").unwrap();
+        email.send(&alert("high_price")).await.unwrap();
+        let (commands, data) = server.await.unwrap();
+
+        assert!(commands.iter().any(|c| c == "MAIL FROM:<bot@example.com>"));
+        assert!(commands.iter().any(|c| c == "RCPT TO:<me@example.com>"));
+        let (head, body) = data.split_once("\n\n").unwrap();
+        let header = |name: &str| head.lines().find_map(|l| l.strip_prefix(name)?.strip_prefix(": "));
+        assert_eq!(header("From"), Some("\"NEM Price Bot\" <bot@example.com>"));
+        assert_eq!(header("To"), Some("me@example.com"));
+        assert_eq!(header("Subject"), Some("High price - NSW"));
+        assert_eq!(body.trim_end(), "Price is high");
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Timelike};
+use plotters::prelude::*;
+use plotters::style::register_font;
+use std::sync::Once;
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+
+use crate::bot::messages;
+use crate::db::repository::User;
+use crate::timezone;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 450;
+
+/// Telegram's limit on photo captions; longer texts follow the chart as their own message.
+const CAPTION_LIMIT: usize = 1024;
+
+/// Bundled so charts render the same on hosts without system fonts (DejaVu, free licence).
+static FONT: &[u8] = include_bytes!("../../assets/DejaVuSans.ttf");
+static REGISTER_FONT: Once = Once::new();
+
+/// Faded versions of the `price_level` emoji colours, drawn behind the price line.
+fn zone_colour(label: &str) -> RGBColor {
+    match label {
+        "Negative" => RGBColor(214, 236, 255),
+        "Low" => RGBColor(222, 245, 222),
+        "Normal" => RGBColor(255, 250, 214),
+        "Elevated" => RGBColor(255, 234, 208),
+        "High" => RGBColor(255, 218, 218),
+        _ => RGBColor(240, 200, 230),
+    }
+}
+
+/// Render a PNG of `actual` prices (solid) and the `forecast` ahead (dashed) for the
+/// user's region, with their wholesale high/low thresholds and clear bands. Retail
+/// thresholds aren't drawn, as the axis is $/MWh.
+pub fn render(user: &User, actual: &[(String, f64)], forecast: &[(String, f64)]) -> Result<Vec<u8>> {
+    REGISTER_FONT.call_once(|| {
+        if register_font("sans-serif", FontStyle::Normal, FONT).is_err() {
+            tracing::error!("Failed to load chart font");
+        }
+    });
+    let tz = user.tz();
+    let points = |series: &[(String, f64)]| -> Vec<(i64, f64)> {
+        series
+            .iter()
+            .filter_map(|(t, p)| Some((timezone::parse_market(t)?.timestamp(), *p)))
+            .collect()
+    };
+    let actual = points(actual);
+    let mut forecast = points(forecast);
+    // Start the forecast line where the actual line ends so the two join up
+    if let (Some(last), Some(first)) = (actual.last(), forecast.first()) {
+        if first.0 > last.0 {
+            forecast.insert(0, *last);
+        }
+    }
+    let all = || actual.iter().chain(forecast.iter());
+    let (Some(x0), Some(x1)) = (all().map(|p| p.0).min(), all().map(|p| p.0).max()) else {
+        bail!("No prices to chart");
+    };
+    let x1 = x1.max(x0 + 300);
+
+    let high = user.high_alert_retail.is_none().then(|| {
+        let clear = user.clear_alert.filter(|c| *c < user.high_alert).unwrap_or(user.high_alert - user.hysteresis);
+        (clear, user.high_alert)
+    });
+    let low = user.low_alert_retail.is_none().then_some((user.low_alert, user.low_alert + user.hysteresis));
+    let levels = all()
+        .map(|p| p.1)
+        .chain(high.iter().flat_map(|(a, b)| [*a, *b]))
+        .chain(low.iter().flat_map(|(a, b)| [*a, *b]));
+    let (lo, hi) = levels.fold((0.0f64, 100.0f64), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let pad = (hi - lo) * 0.05;
+    let (y0, y1) = (lo - pad, hi + pad);
+
+    // Ticks on whole local hours, spaced so there are roughly eight across the window
+    let span_hours = (x1 - x0) / 3600;
+    let tick_hours = [1, 2, 3, 6, 12, 24].into_iter().find(|h| span_hours / h <= 8).unwrap_or(24);
+    // (stepping by quarter hours, as some timezones are offset by 30 or 45 minutes)
+    let ticks: Vec<i64> = (x0 / 900..=x1 / 900)
+        .map(|q| q * 900)
+        .filter(|t| {
+            (*t >= x0 && *t <= x1)
+                && DateTime::from_timestamp(*t, 0).is_some_and(|d| {
+                    let local = d.with_timezone(&tz);
+                    local.minute() == 0 && local.hour() as i64 % tick_hours == 0
+                })
+        })
+        .collect();
+    let multi_day = x1 - x0 > 86_400;
+    let label = move |t: &i64| {
+        DateTime::from_timestamp(*t, 0)
+            .map(|d| d.with_timezone(&tz).format(if multi_day { "%a %H:%M" } else { "%H:%M" }).to_string())
+            .unwrap_or_default()
+    };
+
+    let mut buf = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buf, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let title = format!("{} spot price ($/MWh)", messages::region_display(&user.region));
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, ("sans-serif", 22))
+            .margin(12)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d((x0..x1).with_key_points(ticks), y0..y1)?;
+
+        // Price-level zones: scan the axis in thin strips and merge runs of the same level
+        const STRIPS: usize = 300;
+        let step = (y1 - y0) / STRIPS as f64;
+        let mut zones: Vec<(f64, f64, &str)> = Vec::new();
+        for i in 0..STRIPS {
+            let from = y0 + step * i as f64;
+            let level = messages::price_level(from + step / 2.0).1;
+            match zones.last_mut() {
+                Some(z) if z.2 == level => z.1 = from + step,
+                _ => zones.push((from, from + step, level)),
+            }
+        }
+        chart.draw_series(
+            zones.iter().map(|(a, b, level)| Rectangle::new([(x0, *a), (x1, *b)], zone_colour(level).filled())),
+        )?;
+
+        chart
+            .configure_mesh()
+            .light_line_style(WHITE.mix(0.0))
+            .bold_line_style(BLACK.mix(0.1))
+            .x_label_formatter(&label)
+            .y_label_formatter(&|v| if *v < 0.0 { format!("-${:.0}", -v) } else { format!("${:.0}", v) })
+            .draw()?;
+
+        // Thresholds as lines, with the hysteresis band (alert to clear level) shaded
+        for (band, edge, colour, name) in [
+            (high, high.map(|h| h.1), RED, "High alert"),
+            (low, low.map(|l| l.0), GREEN, "Low alert"),
+        ] {
+            let (Some((a, b)), Some(edge)) = (band, edge) else { continue };
+            if b > a {
+                chart.draw_series(std::iter::once(Rectangle::new([(x0, a), (x1, b)], colour.mix(0.15).filled())))?;
+            }
+            chart
+                .draw_series(LineSeries::new([(x0, edge), (x1, edge)], colour.stroke_width(1)))?
+                .label(name)
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], colour));
+        }
+
+        let line = RGBColor(30, 80, 160);
+        if !actual.is_empty() {
+            chart
+                .draw_series(LineSeries::new(actual.iter().copied(), line.stroke_width(2)))?
+                .label("Actual")
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], line.stroke_width(2)));
+        }
+        if forecast.len() > 1 {
+            chart
+                .draw_series(DashedLineSeries::new(forecast.iter().copied(), 6, 4, line.mix(0.7).stroke_width(2)))?
+                .label("Forecast")
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], line.mix(0.7)));
+        }
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK.mix(0.3))
+            .label_font(("sans-serif", 13))
+            .draw()?;
+        root.present()?;
+    }
+
+    let mut png = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&buf)?;
+    }
+    Ok(png)
+}
+
+/// Send `text` with a chart attached: as the photo's caption when it fits, otherwise
+/// straight after it. Falls back to the text alone when there is no chart.
+pub async fn send_with_chart(bot: &Bot, chat_id: ChatId, chart: Option<Vec<u8>>, text: &str) -> Result<(), teloxide::RequestError> {
+    let Some(png) = chart else {
+        bot.send_message(chat_id, text).await?;
+        return Ok(());
+    };
+    let photo = bot.send_photo(chat_id, InputFile::memory(png).file_name("chart.png"));
+    if text.chars().count() <= CAPTION_LIMIT {
+        photo.caption(text).await?;
+    } else {
+        photo.await?;
+        bot.send_message(chat_id, text).await?;
+    }
+    Ok(())
+}
+
+/// `render` on the blocking pool, as drawing and PNG encoding take long enough to stall
+/// the runtime. Logs instead of failing so a bad chart never costs the user the text.
+pub async fn try_render(user: &User, actual: &[(String, f64)], forecast: &[(String, f64)]) -> Option<Vec<u8>> {
+    let (user, actual, forecast) = (user.clone(), actual.to_vec(), forecast.to_vec());
+    let region = user.region.clone();
+    match tokio::task::spawn_blocking(move || render(&user, &actual, &forecast)).await {
+        Ok(Ok(png)) => Some(png),
+        Ok(Err(e)) => {
+            tracing::warn!(region = %region, error = %e, "Chart render failed");
+            None
+        }
+        Err(e) => {
+            tracing::warn!(region = %region, error = %e, "Chart render task failed");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository::tests::db_following;
+
+    #[test]
+    fn renders_sparse_and_negative_series() {
+        let user = db_following(&["SA1"]).get_user(1).unwrap().unwrap();
+        let series = |prices: &[f64]| -> Vec<(String, f64)> {
+            prices.iter().enumerate().map(|(i, p)| (format!("2026/01/15 10:{:02}:00", 5 * (i + 1)), *p)).collect()
+        };
+        for (actual, forecast) in [
+            (series(&[85.0]), vec![]),
+            (series(&[-40.0, -250.0, -1000.0]), vec![]),
+            (vec![], series(&[120.0, 95.0, 300.0])),
+        ] {
+            let png = render(&user, &actual, &forecast).unwrap();
+            assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        }
+        assert!(render(&user, &[], &[]).is_err());
+    }
+}
@@ -1,10 +1,18 @@
 use std::sync::Arc;
 use teloxide::prelude::*;
-use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile};
 use teloxide::utils::command::BotCommands;
 
-use crate::bot::messages;
+use crate::bot::notifier::{Delivery, Notifier};
+use crate::bot::{channels, chart, messages};
+use crate::config::Config;
+use crate::data::{fcas, generators, market_params};
+use crate::data::notices::Severity;
+use crate::db::repository::{BatteryProfile, Grouping, User};
 use crate::db::Db;
+use crate::engine::analyzer::{self, PendingAlert, Threshold, Unit, ALERT_TYPES};
+use crate::engine::{accuracy, export, optimiser, savings, tariff};
+use crate::timezone;
 
 type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
@@ -12,78 +20,481 @@ type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 #[command(rename_rule = "lowercase")]
 pub enum Command {
     Start,
-    Price,
-    Forecast,
+    Price(String),
+    Forecast(String),
+    Chart(String),
+    History(String),
+    Export(String),
+    Accuracy(String),
+    Mix,
+    Flows,
+    Notices(String),
     Alert(String),
     Status,
+    Tariff(String),
+    Battery(String),
+    Plan(String),
+    Savings,
     Region,
+    Timezone(String),
+    Quiet(String),
+    Notify(String),
+    Gaps,
     Help,
     About,
 }
 
-fn region_keyboard() -> InlineKeyboardMarkup {
-    InlineKeyboardMarkup::new(vec![vec![
-        InlineKeyboardButton::callback("NSW", "region:NSW1"),
-        InlineKeyboardButton::callback("VIC", "region:VIC1"),
-        InlineKeyboardButton::callback("QLD", "region:QLD1"),
-        InlineKeyboardButton::callback("SA", "region:SA1"),
-        InlineKeyboardButton::callback("TAS", "region:TAS1"),
-    ]])
+const REGIONS: &[&str] = &["NSW1", "VIC1", "QLD1", "SA1", "TAS1"];
+
+/// One button per region; tapping follows or unfollows it. `followed` are ticked.
+pub fn region_keyboard(followed: &[String]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![REGIONS
+        .iter()
+        .map(|r| {
+            let name = messages::region_display(r);
+            let label = if followed.iter().any(|f| f == r) { format!("\u{2705} {}", name) } else { name.to_string() };
+            InlineKeyboardButton::callback(label, format!("region:{}", r))
+        })
+        .collect::<Vec<_>>()])
+}
+
+/// A toggle per mutable alert type, and one for drop vs digest during quiet hours.
+pub fn quiet_keyboard(user: &User, muted: &[String]) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = analyzer::mutable_alerts()
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .map(|pair| {
+            pair.iter()
+                .map(|(key, label)| {
+                    let icon = if muted.iter().any(|m| m == key) { "\u{1f515}" } else { "\u{1f514}" };
+                    InlineKeyboardButton::callback(format!("{} {}", icon, label), format!("mute:{}", key))
+                })
+                .collect()
+        })
+        .collect();
+    let mode = if user.quiet_digest { "Quiet hours: digest" } else { "Quiet hours: drop" };
+    rows.push(vec![InlineKeyboardButton::callback(mode, "quiet:digest")]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// "SA", "sa1" or "SA1" to a region ID.
+fn parse_region(arg: &str) -> Option<&'static str> {
+    let upper = arg.to_uppercase();
+    REGIONS.iter().copied().find(|r| *r == upper || r.trim_end_matches('1') == upper)
 }
 
-pub async fn handle(bot: Bot, msg: Message, cmd: Command, db: Arc<Db>) -> HandlerResult {
+pub async fn handle(
+    bot: Bot,
+    msg: Message,
+    cmd: Command,
+    db: Arc<Db>,
+    cfg: Arc<Config>,
+    delivery: Arc<Delivery>,
+) -> HandlerResult {
     let chat_id = msg.chat.id.0;
     match cmd {
-        Command::Start => cmd_start(&bot, &msg).await?,
-        Command::Price => cmd_price(&bot, &msg, &db, chat_id).await?,
-        Command::Forecast => cmd_forecast(&bot, &msg, &db, chat_id).await?,
+        Command::Start => cmd_start(&bot, &msg, &db, chat_id).await?,
+        Command::Price(args) => cmd_price(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Forecast(args) => cmd_forecast(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Chart(args) => cmd_chart(&bot, &msg, &db, chat_id, &args).await?,
+        Command::History(args) => cmd_history(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Export(args) => cmd_export(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Accuracy(args) => cmd_accuracy(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Mix => cmd_mix(&bot, &msg, &db, chat_id).await?,
+        Command::Flows => cmd_flows(&bot, &msg, &db, chat_id).await?,
+        Command::Notices(args) => cmd_notices(&bot, &msg, &db, chat_id, &args).await?,
         Command::Alert(args) => cmd_alert(&bot, &msg, &db, chat_id, &args).await?,
         Command::Status => cmd_status(&bot, &msg, &db, chat_id).await?,
-        Command::Region => cmd_region(&bot, &msg).await?,
+        Command::Tariff(args) => cmd_tariff(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Battery(args) => cmd_battery(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Plan(args) => cmd_plan(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Savings => cmd_savings(&bot, &msg, &db, chat_id).await?,
+        Command::Region => cmd_region(&bot, &msg, &db, chat_id).await?,
+        Command::Timezone(args) => cmd_timezone(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Quiet(args) => cmd_quiet(&bot, &msg, &db, chat_id, &args).await?,
+        Command::Notify(args) => cmd_notify(&bot, &msg, &db, &cfg, &delivery, chat_id, &args).await?,
+        Command::Gaps => cmd_gaps(&bot, &msg, &db, &cfg, chat_id).await?,
         Command::Help => { bot.send_message(msg.chat.id, messages::help_message()).await?; }
         Command::About => { bot.send_message(msg.chat.id, messages::about_message()).await?; }
     }
     Ok(())
 }
 
-async fn cmd_start(bot: &Bot, msg: &Message) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn cmd_start(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let followed = followed_regions(db, chat_id)?;
     bot.send_message(msg.chat.id, messages::welcome_message())
-        .reply_markup(region_keyboard())
+        .reply_markup(region_keyboard(&followed))
         .await?;
     Ok(())
 }
 
-async fn cmd_region(bot: &Bot, msg: &Message) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    bot.send_message(msg.chat.id, "Select your new region:")
-        .reply_markup(region_keyboard())
+fn followed_regions(db: &Db, chat_id: i64) -> anyhow::Result<Vec<String>> {
+    Ok(db.get_user_regions(chat_id)?.into_iter().map(|u| u.region).collect())
+}
+
+/// Admin only: recently recorded dispatch gaps and how to backfill them.
+async fn cmd_gaps(bot: &Bot, msg: &Message, db: &Db, cfg: &Config, chat_id: i64) -> HandlerResult {
+    if cfg.admin_chat_id != Some(chat_id) {
+        bot.send_message(msg.chat.id, "This command is only available to the bot admin.").await?;
+        return Ok(());
+    }
+    let gaps = db.get_recent_gaps(20)?;
+    bot.send_message(msg.chat.id, messages::format_gaps_response(&gaps)).await?;
+    Ok(())
+}
+
+async fn cmd_region(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let followed = followed_regions(db, chat_id)?;
+    let text = match followed.first() {
+        Some(home) => format!(
+            "Tap a region to follow or unfollow it. Your home region is {}; \
+             unfollow it to make your next region home.",
+            messages::region_display(home)
+        ),
+        None => "Select your region:".to_string(),
+    };
+    bot.send_message(msg.chat.id, text)
+        .reply_markup(region_keyboard(&followed))
         .await?;
     Ok(())
 }
 
-async fn cmd_price(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let user = match db.get_user(chat_id)? {
-        Some(u) => u,
-        None => {
-            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+async fn cmd_price(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let users = db.get_user_regions(chat_id)?;
+    if users.is_empty() {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    }
+    match args.trim() {
+        "" => {}
+        "fcas" => {
+            let mut texts = Vec::new();
+            for user in &users {
+                if let Some((time, prices)) = db.get_latest_fcas(&user.region)? {
+                    texts.push(messages::format_fcas_response(&user.region, &time, &prices, user.tz()));
+                }
+            }
+            let text = if texts.is_empty() {
+                "No FCAS price data available yet. Please try again shortly.".to_string()
+            } else {
+                texts.join("\n\n")
+            };
+            bot.send_message(msg.chat.id, text).await?;
             return Ok(());
         }
-    };
-    let (price, time) = match db.get_latest_price(&user.region)? {
-        Some(p) => p,
-        None => {
-            bot.send_message(msg.chat.id, "No price data available yet. Please try again shortly.").await?;
+        _ => {
+            bot.send_message(msg.chat.id, "Usage: /price or /price fcas").await?;
+            return Ok(());
+        }
+    }
+    let mut sent = false;
+    for user in &users {
+        let Some((price, time)) = db.get_latest_price(&user.region)? else { continue };
+        let tz = user.tz();
+        let (start, end) = timezone::local_day_bounds(tz, timezone::local_today(tz));
+        let range = db.get_daily_range(&user.region, &start, &end)?;
+        let demand = db.get_latest_demand(&user.region)?;
+        let text = messages::format_price_response(&messages::PriceView {
+            region: &user.region,
+            price,
+            interval_time: &time,
+            daily_range: range,
+            age_minutes: interval_age_minutes(&time),
+            demand: demand.as_ref(),
+            retail: user.tariff_for(&user.region).map(|t| t.retail_price(price, &time, tariff::DISPATCH_MINUTES)),
+            market: (db.get_cumulative_price(&user.region, &time)?, market_params::for_interval(&time)),
+            tz,
+        });
+        let png = today_chart(db, user).await?;
+        chart::send_with_chart(bot, msg.chat.id, png, &text).await?;
+        sent = true;
+    }
+    if !sent {
+        bot.send_message(msg.chat.id, "No price data available yet. Please try again shortly.").await?;
+    }
+    Ok(())
+}
+
+/// Hours of forecast drawn after today's prices on `/price` and `/forecast` charts.
+const CHART_AHEAD_HOURS: i64 = 6;
+
+/// Chart of today's prices so far and the next few hours' outlook for `user`'s region.
+async fn today_chart(db: &Db, user: &User) -> anyhow::Result<Option<Vec<u8>>> {
+    let tz = user.tz();
+    let now = timezone::now_market();
+    let (start, _) = timezone::local_day_bounds(tz, timezone::local_today(tz));
+    let (now_str, ahead) = (timezone::market_str(&now), timezone::market_str(&(now + chrono::Duration::hours(CHART_AHEAD_HOURS))));
+    let actual = db.get_interval_prices(&user.region, &start, &now_str)?;
+    let forecast = db.get_outlook(&user.region, &now_str, &ahead)?;
+    Ok(chart::try_render(user, &actual, &forecast).await)
+}
+
+/// Pre-dispatch runs compared by `/forecast changes` (two hours' worth).
+const FORECAST_CHANGE_RUNS: usize = 4;
+
+async fn cmd_forecast(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let users = db.get_user_regions(chat_id)?;
+    if users.is_empty() {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    }
+    let now = timezone::now_market();
+    let changes = match args.trim() {
+        "" => false,
+        "changes" => true,
+        _ => {
+            bot.send_message(msg.chat.id, "Usage: /forecast or /forecast changes").await?;
             return Ok(());
         }
     };
-    let today_prefix = now_aest_date();
-    let range = db.get_daily_range(&user.region, &today_prefix)?;
-    let age = interval_age_minutes(&time);
-    let text = messages::format_price_response(&user.region, price, &time, range, age);
-    bot.send_message(msg.chat.id, text).await?;
+    let later = now + chrono::Duration::hours(6);
+    let (from, to) = (timezone::market_str(&now), timezone::market_str(&later));
+    if changes {
+        let mut texts = Vec::new();
+        for user in &users {
+            let runs = db.get_forecast_runs(&user.region, &from, FORECAST_CHANGE_RUNS)?;
+            texts.push(messages::format_forecast_changes(&user.region, &runs, user.tz()));
+        }
+        bot.send_message(msg.chat.id, texts.join("\n\n")).await?;
+        return Ok(());
+    }
+    for user in &users {
+        let forecasts = db.get_outlook(&user.region, &from, &to)?;
+        let pv_peak = db.get_rooftop_peak(&user.region, "forecast", &from, &to)?;
+        let text = messages::format_forecast_response(
+            &user.region, &forecasts, user.tariff_for(&user.region),
+            pv_peak.as_ref().map(|(t, mw)| (t.as_str(), *mw)), user.tz(),
+        );
+        let png = if forecasts.is_empty() { None } else { today_chart(db, user).await? };
+        chart::send_with_chart(bot, msg.chat.id, png, &text).await?;
+    }
+    Ok(())
+}
+
+/// Longest window `/chart` draws (a week); the forecast ahead is capped at a day.
+const CHART_MAX_HOURS: i64 = 168;
+
+async fn cmd_chart(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> HandlerResult {
+    let users = db.get_user_regions(chat_id)?;
+    if users.is_empty() {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    }
+    let hours = match args.trim() {
+        "" => 24,
+        arg => match arg.trim_end_matches('h').parse::<i64>() {
+            Ok(h) if (1..=CHART_MAX_HOURS).contains(&h) => h,
+            _ => {
+                bot.send_message(msg.chat.id, format!("Usage: /chart [hours] (1\u{2013}{CHART_MAX_HOURS}, default 24)")).await?;
+                return Ok(());
+            }
+        },
+    };
+    let now = timezone::now_market();
+    let from = timezone::market_str(&(now - chrono::Duration::hours(hours)));
+    let now_str = timezone::market_str(&now);
+    let ahead = timezone::market_str(&(now + chrono::Duration::hours(hours.min(24))));
+    for user in &users {
+        let actual = db.get_interval_prices(&user.region, &from, &now_str)?;
+        let forecast = db.get_outlook(&user.region, &now_str, &ahead)?;
+        let text = messages::format_chart_caption(&user.region, hours, &actual, &forecast, user.tz());
+        let png = chart::try_render(user, &actual, &forecast).await;
+        chart::send_with_chart(bot, msg.chat.id, png, &text).await?;
+    }
+    Ok(())
+}
+
+/// Longest `/history` range, and the longest one `by day` lists.
+const HISTORY_MAX_DAYS: i64 = 366;
+const HISTORY_MAX_DAILY: i64 = 62;
+
+/// Intervals listed under "Most expensive intervals".
+const HISTORY_TOP: usize = 5;
+
+const HISTORY_USAGE: &str = "Usage: /history [range] [region] [by day|by hour]\n\
+    Ranges: today, yesterday, 7d (up to 366d), 2026-01-15, or 2026-01-01 2026-01-31";
+
+async fn cmd_history(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> HandlerResult {
+    let users = db.get_user_regions(chat_id)?;
+    if users.is_empty() {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    }
+    let mut words: Vec<String> = args.split_whitespace().map(str::to_lowercase).collect();
+    let grouping = match words.as_slice() {
+        [.., by, g] if by == "by" => {
+            let g = match g.as_str() {
+                "day" | "days" => Grouping::Day,
+                "hour" | "hours" => Grouping::HourOfDay,
+                _ => {
+                    bot.send_message(msg.chat.id, HISTORY_USAGE).await?;
+                    return Ok(());
+                }
+            };
+            words.truncate(words.len() - 2);
+            Some(g)
+        }
+        _ => None,
+    };
+    let Some(users) = take_region(&mut words, &users) else {
+        bot.send_message(msg.chat.id, "You don't follow that region \u{2014} use /region to add it.").await?;
+        return Ok(());
+    };
+    let tz = users[0].tz();
+    let Some((from, to)) = parse_history_range(&words, timezone::local_today(tz)) else {
+        bot.send_message(msg.chat.id, HISTORY_USAGE).await?;
+        return Ok(());
+    };
+    let days = (to - from).num_days() + 1;
+    if days > HISTORY_MAX_DAYS {
+        bot.send_message(msg.chat.id, format!("History covers at most {HISTORY_MAX_DAYS} days at a time.")).await?;
+        return Ok(());
+    }
+    if grouping == Some(Grouping::Day) && days > HISTORY_MAX_DAILY {
+        bot.send_message(msg.chat.id, format!("\"by day\" lists at most {HISTORY_MAX_DAILY} days; try \"by hour\" or a shorter range.")).await?;
+        return Ok(());
+    }
+
+    let (start, _) = timezone::local_day_bounds(tz, from);
+    let (_, end) = timezone::local_day_bounds(tz, to);
+    let period = period_display(from, to);
+    let mut texts = Vec::new();
+    for user in users {
+        let mut stats = db.get_price_stats(&user.region, &start, &end, user.high_alert)?;
+        // A retail high alert is what fires when the user has a tariff here, so count
+        // hours against that, period by period
+        let threshold = match (user.high_alert_retail, user.tariff_for(&user.region), stats.as_mut()) {
+            (Some(cents), Some(tariff), Some(stats)) => {
+                stats.hours_above = tariff.hours_above(&db.get_interval_prices(&user.region, &start, &end)?, cents);
+                Threshold::new(cents, Unit::CentsKwh)
+            }
+            _ => Threshold::new(user.high_alert, Unit::Mwh),
+        };
+        let top = db.get_top_intervals(&user.region, &start, &end, HISTORY_TOP)?;
+        let buckets = match grouping {
+            Some(g) => db.get_price_buckets(&user.region, &start, &end, g, tz)?,
+            None => Vec::new(),
+        };
+        texts.push(messages::format_history(&messages::HistoryView {
+            region: &user.region,
+            period: &period,
+            stats: stats.as_ref(),
+            threshold,
+            top: &top,
+            buckets: grouping.map(|g| (g, buckets.as_slice())),
+            tz,
+        }));
+    }
+    bot.send_message(msg.chat.id, texts.join("\n\n")).await?;
     Ok(())
 }
 
-async fn cmd_forecast(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// The followed regions a trailing region word picks (removing it), or all of them
+/// without one; None if it names a region the user doesn't follow.
+fn take_region<'a>(words: &mut Vec<String>, users: &'a [User]) -> Option<Vec<&'a User>> {
+    let region = words.last().and_then(|w| parse_region(w));
+    if region.is_some() {
+        words.pop();
+    }
+    let picked: Vec<&User> = users.iter().filter(|u| region.is_none_or(|r| u.region == r)).collect();
+    (!picked.is_empty()).then_some(picked)
+}
+
+/// "Thu 01 Jan 2026 – Sat 31 Jan 2026", or one date.
+fn period_display(from: chrono::NaiveDate, to: chrono::NaiveDate) -> String {
+    if from == to {
+        from.format("%a %d %b %Y").to_string()
+    } else {
+        format!("{} \u{2013} {}", from.format("%a %d %b %Y"), to.format("%a %d %b %Y"))
+    }
+}
+
+/// Longest range `/export` sends; self-hosters can export more with the `export` CLI.
+const EXPORT_MAX_DAYS: i64 = 31;
+
+async fn cmd_export(bot: &Bot, msg: &Message, db: &Arc<Db>, chat_id: i64, args: &str) -> HandlerResult {
+    let users = db.get_user_regions(chat_id)?;
+    if users.is_empty() {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    }
+    let mut words: Vec<String> = args.split_whitespace().map(str::to_lowercase).collect();
+    let Some(users) = take_region(&mut words, &users) else {
+        bot.send_message(msg.chat.id, "You don't follow that region \u{2014} use /region to add it.").await?;
+        return Ok(());
+    };
+    let tz = users[0].tz();
+    let Some((from, to)) = parse_history_range(&words, timezone::local_today(tz)) else {
+        bot.send_message(
+            msg.chat.id,
+            "Usage: /export [range] [region]\nRanges: today, yesterday, 7d (up to 31d), 2026-01-15, or 2026-01-01 2026-01-31",
+        )
+        .await?;
+        return Ok(());
+    };
+    if (to - from).num_days() + 1 > EXPORT_MAX_DAYS {
+        bot.send_message(msg.chat.id, format!("Exports cover at most {EXPORT_MAX_DAYS} days at a time.")).await?;
+        return Ok(());
+    }
+
+    let (start, _) = timezone::local_day_bounds(tz, from);
+    let (_, end) = timezone::local_day_bounds(tz, to);
+    let regions: Vec<String> = users.iter().map(|u| u.region.clone()).collect();
+    let region_names = regions.iter().map(|r| messages::region_display(r)).collect::<Vec<_>>().join(", ");
+    // Streamed to a temporary file rather than built in memory, then uploaded from disk.
+    // Up to a month of every followed region is read in pages, so it runs on the blocking
+    // pool to keep the scheduler and other commands going meanwhile.
+    let name = format!("nem-prices-{}-{}.csv", from.format("%Y%m%d"), to.format("%Y%m%d"));
+    let db = Arc::clone(db);
+    let (file, (prices, forecasts)) = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        // A unique name, so two exports of the same range from one chat can't collide
+        let file = tempfile::Builder::new().prefix("nem-price-bot-").suffix(".csv").tempfile()?;
+        let regions: Vec<&str> = regions.iter().map(String::as_str).collect();
+        let counts = export::write_user_csv(&db, &regions, &start, &end, std::io::BufWriter::new(file.as_file()))?;
+        Ok((file, counts))
+    })
+    .await??;
+    if prices + forecasts == 0 {
+        bot.send_message(msg.chat.id, "No prices stored for this range.").await?;
+        return Ok(());
+    }
+    let caption = format!(
+        "\u{1f4e6} {} \u{2014} {}\n{} prices, {} forecast rows (all pre-dispatch runs). Times are market time (AEST), period end.",
+        region_names, period_display(from, to), thousands(prices as f64), thousands(forecasts as f64)
+    );
+    // The file is removed when `file` drops, after the upload has read it
+    bot.send_document(msg.chat.id, InputFile::file(file.path()).file_name(name))
+        .caption(caption)
+        .await?;
+    Ok(())
+}
+
+/// Inclusive local dates for a `/history` or `/export` range: none (last 7 days), "today", "yesterday",
+/// "<n>d", one date, or two dates (also "from..to"). Ranges ending after today are refused.
+fn parse_history_range(words: &[String], today: chrono::NaiveDate) -> Option<(chrono::NaiveDate, chrono::NaiveDate)> {
+    let date = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+    let (from, to) = match words {
+        [] => (today - chrono::Duration::days(6), today),
+        [w] if w == "today" => (today, today),
+        [w] if w == "yesterday" => {
+            let d = today.pred_opt()?;
+            (d, d)
+        }
+        [w] if w.ends_with('d') && w.len() > 1 => {
+            let n: u64 = w[..w.len() - 1].parse().ok().filter(|n| *n >= 1)?;
+            (today.checked_sub_days(chrono::Days::new(n - 1))?, today)
+        }
+        [w] => match w.split_once("..") {
+            Some((a, b)) => (date(a)?, date(b)?),
+            None => (date(w)?, date(w)?),
+        },
+        [a, b] => (date(a)?, date(b)?),
+        _ => return None,
+    };
+    (from <= to && to <= today).then_some((from, to))
+}
+
+async fn cmd_accuracy(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> HandlerResult {
     let user = match db.get_user(chat_id)? {
         Some(u) => u,
         None => {
@@ -91,10 +502,77 @@ async fn cmd_forecast(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result
             return Ok(());
         }
     };
-    let now = now_aest_str();
-    let later = later_aest_str(6);
-    let forecasts = db.get_forecasts(&user.region, &now, &later)?;
-    let text = messages::format_forecast_response(&user.region, &forecasts);
+    let days = match args.trim() {
+        "" => 7,
+        d => match d.parse::<i64>() {
+            Ok(n) if (1..=90).contains(&n) => n,
+            _ => {
+                bot.send_message(msg.chat.id, "Usage: /accuracy [days], e.g. /accuracy 30 (1\u{2013}90)").await?;
+                return Ok(());
+            }
+        },
+    };
+    let scores = accuracy::rolling(db, &user.region, days);
+    bot.send_message(msg.chat.id, messages::format_accuracy_response(&user.region, &scores, days)).await?;
+    Ok(())
+}
+
+async fn cmd_mix(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> HandlerResult {
+    let Some(user) = db.get_user(chat_id)? else {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    };
+    let Some(mix) = db.get_latest_mix(&user.region)? else {
+        bot.send_message(msg.chat.id, "No generation data yet. Try again in 5 minutes.").await?;
+        return Ok(());
+    };
+    // Rooftop estimates are half-hourly; take the latest in the past hour
+    let hour_ago = timezone::market_str(&(timezone::now_market() - chrono::Duration::hours(1)));
+    let rooftop = db.get_rooftop_latest(&user.region, &hour_ago)?;
+    bot.send_message(msg.chat.id, messages::format_mix_response(&user.region, &mix, rooftop, user.tz())).await?;
+    Ok(())
+}
+
+async fn cmd_flows(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> HandlerResult {
+    let Some(time) = db.get_latest_interconnector_interval()? else {
+        bot.send_message(msg.chat.id, "No interconnector data yet. Try again in 5 minutes.").await?;
+        return Ok(());
+    };
+    let tz = db.get_user(chat_id)?.map(|u| u.tz()).unwrap_or(timezone::MARKET_TZ);
+    let flows = db.get_interconnector_flows(&time)?;
+    bot.send_message(msg.chat.id, messages::format_flows_response(&time, &flows, tz)).await?;
+    Ok(())
+}
+
+/// Notices shown by `/notices`.
+const RECENT_NOTICES: usize = 8;
+
+async fn cmd_notices(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> HandlerResult {
+    let Some(user) = db.get_user(chat_id)? else {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    };
+    let text = match args.trim().to_lowercase().as_str() {
+        "" => {
+            let notices = db.get_recent_notices(&user.region, RECENT_NOTICES)?;
+            let setting = user.notice_severity.as_deref().and_then(Severity::from_key);
+            messages::format_notices_response(&user.region, &notices, setting, user.tz())
+        }
+        "off" => {
+            db.update_notice_severity(chat_id, None)?;
+            "\u{2705} Market notice alerts turned off.".to_string()
+        }
+        level => match Severity::from_key(level) {
+            Some(severity) => {
+                db.update_notice_severity(chat_id, Some(severity.key()))?;
+                format!(
+                    "\u{2705} You'll get {}-severity market notices and above for {}.",
+                    severity.key(), messages::region_display(&user.region)
+                )
+            }
+            None => "Usage: /notices [high|medium|low|off]".to_string(),
+        },
+    };
     bot.send_message(msg.chat.id, text).await?;
     Ok(())
 }
@@ -108,12 +586,70 @@ async fn cmd_alert(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str)
         }
     };
 
-    let parts: Vec<&str> = args.split_whitespace().collect();
+    let mut parts: Vec<&str> = args.split_whitespace().collect();
+    // A trailing region ("/alert high 300 SA") targets another region the user follows
+    let target = match parts.as_slice() {
+        ["high" | "low" | "clear" | "on" | "off", .., last] => parse_region(last),
+        _ => None,
+    };
+    let user = match target {
+        Some(region) => {
+            parts.pop();
+            match db.get_user_in(chat_id, region)? {
+                Some(u) => u,
+                None => {
+                    let text = format!("You don't follow {}. Add it with /region.", messages::region_display(region));
+                    bot.send_message(msg.chat.id, text).await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => user,
+    };
+    let has_tariff = user.tariff_for(&user.region).is_some();
     let reply = match parts.as_slice() {
+        ["high" | "low", val] if val.ends_with('c') && !user.is_home() => {
+            "Retail thresholds only apply to your home region, where your tariff is.".to_string()
+        }
+        ["high" | "low", val] if val.ends_with('c') && !has_tariff => {
+            "Retail thresholds need a tariff first \u{2014} see /tariff".to_string()
+        }
+        ["high", val] if val.ends_with('c') => {
+            let v: f64 = val.trim_end_matches('c').parse().map_err(|_| "Invalid number")?;
+            if !(10.0..=500.0).contains(&v) {
+                "Retail high alert must be between 10c and 500c/kWh.".to_string()
+            } else if user.low_alert_retail.is_some_and(|low| v <= low) {
+                "Retail high alert must be greater than your retail low alert.".to_string()
+            } else {
+                db.update_high_alert_retail(chat_id, v)?;
+                format!(
+                    "\u{2705} High price alert updated.\n\n\
+                     You'll be notified when your {} import price exceeds {:.1}c/kWh\n\
+                     (wholesale + network + GST on your tariff).",
+                    messages::region_display(&user.region), v
+                )
+            }
+        }
+        ["low", val] if val.ends_with('c') => {
+            let v: f64 = val.trim_end_matches('c').parse().map_err(|_| "Invalid number")?;
+            if !(-100.0..=100.0).contains(&v) {
+                "Retail low alert must be between -100c and 100c/kWh.".to_string()
+            } else if user.high_alert_retail.is_some_and(|high| v >= high) {
+                "Retail low alert must be less than your retail high alert.".to_string()
+            } else {
+                db.update_low_alert_retail(chat_id, v)?;
+                format!(
+                    "\u{2705} Low price alert updated.\n\n\
+                     You'll be notified when your {} import price drops below {:.1}c/kWh.",
+                    messages::region_display(&user.region), v
+                )
+            }
+        }
         ["high", val] => {
             let v: f64 = val.parse().map_err(|_| "Invalid number")?;
-            if !(50.0..=15000.0).contains(&v) {
-                "High alert must be between $50 and $15,000.".to_string()
+            let cap = market_params::current().price_cap;
+            if !(50.0..=cap).contains(&v) {
+                format!("High alert must be between $50 and ${} (the market price cap).", thousands(cap))
             } else if v <= user.low_alert {
                 format!(
                     "High alert (${:.0}) must be greater than your current low alert (${:.0}).\n\
@@ -122,7 +658,7 @@ async fn cmd_alert(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str)
                 )
             } else {
                 let prev = user.high_alert;
-                db.update_high_alert(chat_id, v)?;
+                db.update_high_alert(chat_id, &user.region, v)?;
                 format!(
                     "\u{2705} High price alert updated.\n\n\
                      You'll be notified when {} spot price exceeds ${:.0}/MWh.\n\
@@ -138,8 +674,9 @@ async fn cmd_alert(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str)
         }
         ["low", val] => {
             let v: f64 = val.parse().map_err(|_| "Invalid number")?;
-            if !(-1000.0..=50.0).contains(&v) {
-                "Low alert must be between -$1,000 and $50.".to_string()
+            let floor = market_params::current().price_floor;
+            if !(floor..=50.0).contains(&v) {
+                format!("Low alert must be between -${} (the market floor) and $50.", thousands(-floor))
             } else if v >= user.high_alert {
                 format!(
                     "Low alert (${:.0}) must be less than your current high alert (${:.0}).\n\
@@ -148,7 +685,7 @@ async fn cmd_alert(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str)
                 )
             } else {
                 let prev = user.low_alert;
-                db.update_low_alert(chat_id, v)?;
+                db.update_low_alert(chat_id, &user.region, v)?;
                 format!(
                     "\u{2705} Low price alert updated.\n\n\
                      You'll be notified when {} spot price drops below ${:.0}/MWh.\n\
@@ -162,6 +699,135 @@ async fn cmd_alert(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str)
                 )
             }
         }
+        ["band", val] => {
+            let v: f64 = val.trim_start_matches('$').parse().map_err(|_| "Invalid number")?;
+            if !(0.0..=500.0).contains(&v) {
+                "Hysteresis band must be between $0 and $500/MWh.".to_string()
+            } else {
+                db.update_hysteresis(chat_id, v)?;
+                format!(
+                    "\u{2705} Hysteresis set to ${:.0}/MWh.\n\n\
+                     A high-price alert now clears once the price falls ${:.0} below your high threshold, \
+                     and a low-price alert once it rises ${:.0} above your low threshold.",
+                    v, v, v
+                )
+            }
+        }
+        ["duration", val] => {
+            let v: i64 = val.parse().map_err(|_| "Invalid number")?;
+            if !(1..=12).contains(&v) {
+                "Duration must be between 1 and 12 intervals (5 minutes each).".to_string()
+            } else {
+                db.update_min_intervals(chat_id, v)?;
+                format!(
+                    "\u{2705} Threshold alerts now need the price past your threshold for {} consecutive \
+                     interval{} ({} minutes).",
+                    v, if v == 1 { "" } else { "s" }, v * 5
+                )
+            }
+        }
+        ["clear", "off"] => {
+            db.update_clear_alert(chat_id, &user.region, None)?;
+            format!(
+                "\u{2705} {} high-price alerts now clear at your high threshold less the hysteresis band.",
+                messages::region_display(&user.region)
+            )
+        }
+        ["clear", val] => {
+            let v: f64 = val.parse().map_err(|_| "Invalid number")?;
+            if v >= user.high_alert {
+                format!("Clear threshold must be below your high alert (${:.0}).", user.high_alert)
+            } else if v < market_params::current().price_floor {
+                "Clear threshold can't be below the market floor.".to_string()
+            } else {
+                db.update_clear_alert(chat_id, &user.region, Some(v))?;
+                format!(
+                    "\u{2705} {} high-price alerts now clear once the price drops below ${:.0}/MWh.",
+                    messages::region_display(&user.region), v
+                )
+            }
+        }
+        ["fcas"] => messages::format_fcas_alerts(&db.get_fcas_alerts(chat_id)?),
+        ["fcas", key, val] => match fcas::find(key) {
+            None => format!("Unknown FCAS service \"{}\".\n\n{}", key, messages::format_fcas_alerts(&db.get_fcas_alerts(chat_id)?)),
+            Some(service) if *val == "off" => {
+                db.update_fcas_alert(chat_id, service.key, None)?;
+                format!("\u{2705} {} alert removed.", service.label)
+            }
+            Some(service) => {
+                let v: f64 = val.parse().map_err(|_| "Invalid number")?;
+                if !(0.0..=20000.0).contains(&v) {
+                    "FCAS alert must be between $0 and $20,000/MWh.".to_string()
+                } else {
+                    db.update_fcas_alert(chat_id, service.key, Some(v))?;
+                    format!(
+                        "\u{2705} FCAS alert set.\n\n\
+                         You'll be notified when {} {} exceeds ${:.0}/MWh.",
+                        messages::region_display(&user.region), service.label, v
+                    )
+                }
+            }
+        },
+        ["carbon"] => format!(
+            "\u{1f33f} Carbon alerts: {}\n\n\
+             Usage:\n\
+             /alert carbon high 700 \u{2014} notify above 700 g CO\u{2082}/kWh\n\
+             /alert carbon low 200 \u{2014} notify below 200 g CO\u{2082}/kWh\n\
+             /alert carbon high off \u{2014} remove one threshold\n\
+             /alert carbon off \u{2014} remove both",
+            carbon_display(user.carbon_high, user.carbon_low)
+        ) + &carbon_coverage_note(db, &user.region),
+        ["carbon", "off"] => {
+            db.update_carbon_alerts(chat_id, None, None)?;
+            "\u{2705} Carbon alerts removed.".to_string()
+        }
+        ["carbon", "high", "off"] => {
+            db.update_carbon_alerts(chat_id, None, user.carbon_low)?;
+            "\u{2705} High carbon alert removed.".to_string()
+        }
+        ["carbon", "low", "off"] => {
+            db.update_carbon_alerts(chat_id, user.carbon_high, None)?;
+            "\u{2705} Low carbon alert removed.".to_string()
+        }
+        ["carbon", "high", val] => {
+            let v: f64 = val.parse().map_err(|_| "Invalid number")?;
+            if !(50.0..=1500.0).contains(&v) {
+                "High carbon alert must be between 50 and 1500 g/kWh.".to_string()
+            } else if user.carbon_low.is_some_and(|low| v <= low) {
+                "High carbon alert must be greater than your low carbon alert.".to_string()
+            } else {
+                db.update_carbon_alerts(chat_id, Some(v), user.carbon_low)?;
+                format!(
+                    "\u{2705} High carbon alert set.\n\n\
+                     You'll be notified when {} generation exceeds {:.0} g CO\u{2082}/kWh.",
+                    messages::region_display(&user.region), v
+                ) + &carbon_coverage_note(db, &user.region)
+            }
+        }
+        ["carbon", "low", val] => {
+            let v: f64 = val.parse().map_err(|_| "Invalid number")?;
+            if !(0.0..=1000.0).contains(&v) {
+                "Low carbon alert must be between 0 and 1000 g/kWh.".to_string()
+            } else if user.carbon_high.is_some_and(|high| v >= high) {
+                "Low carbon alert must be less than your high carbon alert.".to_string()
+            } else {
+                db.update_carbon_alerts(chat_id, user.carbon_high, Some(v))?;
+                format!(
+                    "\u{2705} Low carbon alert set.\n\n\
+                     You'll be notified when {} generation drops below {:.0} g CO\u{2082}/kWh.",
+                    messages::region_display(&user.region), v
+                ) + &carbon_coverage_note(db, &user.region)
+            }
+        }
+        ["off"] if target.is_some() => {
+            db.set_region_active(chat_id, &user.region, false)?;
+            let name = messages::region_display(&user.region);
+            format!("\u{23f8}\u{fe0f} {} alerts paused. Use /alert on {} to resume.", name, name)
+        }
+        ["on"] if target.is_some() => {
+            db.set_region_active(chat_id, &user.region, true)?;
+            format!("\u{25b6}\u{fe0f} {} alerts resumed.", messages::region_display(&user.region))
+        }
         ["off"] => {
             db.set_active(chat_id, false)?;
             "\u{23f8}\u{fe0f} Alerts paused. Use /alert on to resume.".to_string()
@@ -172,16 +838,31 @@ async fn cmd_alert(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str)
         }
         _ => format!(
             "Your current settings:\n\
-             \u{2022} High alert: ${:.0}/MWh\n\
-             \u{2022} Low alert: ${:.0}/MWh\n\
-             \u{2022} Status: {}\n\n\
+             \u{2022} High alert: {}\n\
+             \u{2022} Low alert: {}\n\
+             \u{2022} Status: {}{}\n\
+             \u{2022} Rules: {}\n\n\
              Usage:\n\
              /alert high <value> \u{2014} e.g. /alert high 200\n\
              /alert low <value> \u{2014} e.g. /alert low -20\n\
-             /alert off \u{2014} Pause notifications\n\
+             /alert high 300 SA \u{2014} Threshold for another region you follow\n\
+             /alert high 45c \u{2014} Retail c/kWh threshold (needs /tariff)\n\
+             /alert band 20 \u{2014} Clear only once $20/MWh back past the threshold\n\
+             /alert duration 3 \u{2014} Alert after 3 intervals past the threshold\n\
+             /alert clear 250 \u{2014} Clear high-price events below $250/MWh\n\
+             /alert fcas raise6sec 20 \u{2014} FCAS price threshold\n\
+             /alert carbon high 700 \u{2014} Carbon intensity threshold (g/kWh)\n\
+             /alert off \u{2014} Pause notifications (/alert off SA for one region)\n\
              /alert on \u{2014} Resume notifications",
-            user.high_alert, user.low_alert,
-            if user.is_active { "Active \u{2705}" } else { "Paused \u{23f8}\u{fe0f}" }
+            threshold_display(user.high_alert, user.high_alert_retail),
+            threshold_display(user.low_alert, user.low_alert_retail),
+            match (user.is_active, user.region_active) {
+                (true, true) => "Active \u{2705}",
+                (true, false) => "Paused for this region \u{23f8}\u{fe0f}",
+                (false, _) => "Paused \u{23f8}\u{fe0f}",
+            },
+            other_regions_display(&db.get_user_regions(chat_id)?),
+            rules_display(&user),
         ),
     };
 
@@ -198,20 +879,40 @@ async fn cmd_status(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(
         }
     };
     let weekly_alerts = db.count_alerts_this_week(chat_id).unwrap_or(0);
+    let fcas_alerts = db.get_fcas_alerts(chat_id).unwrap_or_default();
     let member_since = if user.created_at.len() >= 10 { &user.created_at[..10] } else { &user.created_at };
     let text = format!(
         "\u{1f4cb} Your Settings\n\n\
          Region: {}\n\
-         High price alert: ${:.0}/MWh\n\
-         Low price alert: ${:.0}/MWh\n\
+         High price alert: {}\n\
+         Low price alert: {}{}\n\
+         FCAS alerts: {}\n\
+         Carbon alerts: {}\n\
+         Market notices: {}\n\
+         Tariff: {}\n\
+         Timezone: {}{}\n\
          Alerts: {} {}\n\
          Member since: {}\n\
          Alerts received this week: {}",
         messages::region_display(&user.region),
-        user.high_alert,
-        user.low_alert,
-        if user.is_active { "Active" } else { "Paused" },
-        if user.is_active { "\u{2705}" } else { "\u{23f8}\u{fe0f}" },
+        threshold_display(user.high_alert, user.high_alert_retail),
+        threshold_display(user.low_alert, user.low_alert_retail),
+        other_regions_display(&db.get_user_regions(chat_id)?),
+        if fcas_alerts.is_empty() { "None (/alert fcas)".to_string() } else { format!("{} set", fcas_alerts.len()) },
+        carbon_display(user.carbon_high, user.carbon_low),
+        user.notice_severity.as_deref().map_or("Off (/notices)".to_string(), |s| format!("{s} and above")),
+        match user.tariff_for(&user.region) {
+            Some(t) => format!("{} {}", t.network, t.name),
+            None => "Not set (/tariff)".to_string(),
+        },
+        user.tz().name(),
+        if user.timezone.is_some() { "" } else { " (region default)" },
+        match (user.is_active, user.region_active) {
+            (true, true) => "Active",
+            (true, false) => "Paused for home region",
+            (false, _) => "Paused",
+        },
+        if user.is_active && user.region_active { "\u{2705}" } else { "\u{23f8}\u{fe0f}" },
         member_since,
         weekly_alerts,
     );
@@ -219,36 +920,523 @@ async fn cmd_status(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(
     Ok(())
 }
 
-// ── Time helpers (AEST via Brisbane, no DST) ──
+async fn cmd_tariff(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let user = match db.get_user(chat_id)? {
+        Some(u) => u,
+        None => {
+            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+            return Ok(());
+        }
+    };
+    let arg = args.trim().to_lowercase();
+    let reply = if arg == "off" {
+        db.update_tariff(chat_id, None)?;
+        "Tariff removed. Alerts and prices are back to wholesale $/MWh only.".to_string()
+    } else if let Some(t) = tariff::find(&arg).filter(|t| t.region == user.region) {
+        db.update_tariff(chat_id, Some(t.id))?;
+        format!(
+            "\u{2705} Tariff set to {} {}.\n\n\
+             Network charges: {:.1}c peak / {:.1}c shoulder / {:.1}c off-peak per kWh\n\
+             Daily supply: {:.0}c/day | Loss factor: {:.2}\n\n\
+             Prices now show retail c/kWh. Set retail thresholds with\n\
+             /alert high 45c or /alert low 5c",
+            t.network, t.name, t.peak_c, t.shoulder_c, t.offpeak_c, t.daily_supply_c, t.loss_factor
+        )
+    } else {
+        let options: Vec<String> = tariff::for_region(&user.region)
+            .map(|t| format!("/tariff {} \u{2014} {} {}", t.id, t.network, t.name))
+            .collect();
+        let current = match user.tariff_for(&user.region) {
+            Some(t) => format!("{} {}", t.network, t.name),
+            None => "none".to_string(),
+        };
+        format!(
+            "Current tariff: {}\n\n\
+             Pick your distribution network and tariff to see what you\n\
+             actually pay on a wholesale pass-through plan:\n\n{}\n\
+             /tariff off \u{2014} Wholesale prices only",
+            current, options.join("\n")
+        )
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+async fn cmd_battery(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if db.get_user(chat_id)?.is_none() {
+        bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+        return Ok(());
+    }
+    let usage = "Usage:\n\
+                 /battery <kWh> [kW] [efficiency %] [reserve %]\n\
+                 e.g. /battery 13.5 5 90 10\n\
+                 /battery off \u{2014} Remove your battery";
+
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let reply = match parts.as_slice() {
+        [] => match db.get_battery(chat_id)? {
+            Some(b) => format!(
+                "\u{1f50b} Your battery:\n\
+                 \u{2022} Capacity: {:.1} kWh\n\
+                 \u{2022} Power: {:.1} kW\n\
+                 \u{2022} Round-trip efficiency: {:.0}%\n\
+                 \u{2022} Reserve: {:.0}%\n\n{}",
+                b.capacity_kwh, b.power_kw, b.efficiency * 100.0, b.reserve * 100.0, usage
+            ),
+            None => format!("No battery configured.\n\n{usage}"),
+        },
+        ["off"] => {
+            db.update_battery(chat_id, None)?;
+            "Battery removed.".to_string()
+        }
+        [kwh, rest @ ..] if rest.len() <= 3 => {
+            let nums: Result<Vec<f64>, _> = std::iter::once(kwh).chain(rest).map(|v| v.parse::<f64>()).collect();
+            let nums = nums.map_err(|_| "Invalid number")?;
+            let capacity = nums[0];
+            let power = nums.get(1).copied().unwrap_or(5.0);
+            let efficiency = nums.get(2).copied().unwrap_or(90.0);
+            let reserve = nums.get(3).copied().unwrap_or(10.0);
+            if !(0.5..=200.0).contains(&capacity) {
+                "Capacity must be between 0.5 and 200 kWh.".to_string()
+            } else if !(0.5..=100.0).contains(&power) {
+                "Power must be between 0.5 and 100 kW.".to_string()
+            } else if !(50.0..=100.0).contains(&efficiency) {
+                "Round-trip efficiency must be between 50% and 100%.".to_string()
+            } else if !(0.0..=90.0).contains(&reserve) {
+                "Reserve must be between 0% and 90%.".to_string()
+            } else {
+                db.update_battery(chat_id, Some(&BatteryProfile {
+                    capacity_kwh: capacity,
+                    power_kw: power,
+                    efficiency: efficiency / 100.0,
+                    reserve: reserve / 100.0,
+                }))?;
+                format!(
+                    "\u{2705} Battery saved: {:.1} kWh, {:.1} kW, {:.0}% round-trip, {:.0}% reserve.\n\n\
+                     Use /plan to see the best charge/discharge times.",
+                    capacity, power, efficiency, reserve
+                )
+            }
+        }
+        _ => usage.to_string(),
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+async fn cmd_plan(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let user = match db.get_user(chat_id)? {
+        Some(u) => u,
+        None => {
+            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+            return Ok(());
+        }
+    };
+    let battery = match db.get_battery(chat_id)? {
+        Some(b) => b,
+        None => {
+            bot.send_message(msg.chat.id, "Set up your battery first, e.g. /battery 13.5 5 90 10").await?;
+            return Ok(());
+        }
+    };
+    let start_soc = match args.trim().trim_end_matches('%') {
+        "" => optimiser::DEFAULT_START_SOC,
+        v => match v.parse::<f64>() {
+            Ok(pct) if (0.0..=100.0).contains(&pct) => pct / 100.0,
+            _ => {
+                bot.send_message(msg.chat.id, "Usage: /plan [current charge %], e.g. /plan 80").await?;
+                return Ok(());
+            }
+        },
+    };
+    let now = timezone::now_market();
+    let later = now + chrono::Duration::hours(24);
+    let forecasts = db.get_forecasts(&user.region, &timezone::market_str(&now), &timezone::market_str(&later))?;
+    let plan = optimiser::optimise(&battery, &forecasts, start_soc);
+    let text = messages::format_plan_response(&user.region, &battery, &plan, start_soc, user.tz());
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+async fn cmd_savings(bot: &Bot, msg: &Message, db: &Db, chat_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let user = match db.get_user(chat_id)? {
+        Some(u) => u,
+        None => {
+            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+            return Ok(());
+        }
+    };
+    let battery = match db.get_battery(chat_id)? {
+        Some(b) => b,
+        None => {
+            bot.send_message(msg.chat.id, "Set up your battery first, e.g. /battery 13.5 5 90 10").await?;
+            return Ok(());
+        }
+    };
+    let tz = user.tz();
+    // Normally a no-op: the scheduler fills these in each night
+    savings::update_user(db, &user, &battery, tz);
+    let day = savings::total_since(db, chat_id, tz, 1);
+    let week = savings::total_since(db, chat_id, tz, 7);
+    let month = savings::total_since(db, chat_id, tz, 30);
+    let text = messages::format_savings_response(&user.region, day.as_ref(), week.as_ref(), month.as_ref());
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+async fn cmd_timezone(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let user = match db.get_user(chat_id)? {
+        Some(u) => u,
+        None => {
+            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+            return Ok(());
+        }
+    };
+    let arg = args.trim();
+    let reply = if arg.is_empty() {
+        format!(
+            "Times are shown in {} ({}).\n\n\
+             Usage:\n\
+             /timezone Australia/Broken_Hill \u{2014} Use a specific timezone\n\
+             /timezone reset \u{2014} Use your region's default",
+            user.tz().name(), timezone::tz_abbrev(user.tz())
+        )
+    } else if arg.eq_ignore_ascii_case("reset") {
+        db.update_timezone(chat_id, None)?;
+        let tz = timezone::region_tz(&user.region);
+        format!("\u{2705} Timezone reset to {} ({}).", tz.name(), timezone::tz_abbrev(tz))
+    } else {
+        match arg.parse::<chrono_tz::Tz>() {
+            Ok(tz) => {
+                db.update_timezone(chat_id, Some(tz.name()))?;
+                format!("\u{2705} Times will now be shown in {} ({}).", tz.name(), timezone::tz_abbrev(tz))
+            }
+            Err(_) => format!("Unknown timezone \"{arg}\". Use an IANA name like Australia/Adelaide."),
+        }
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+async fn cmd_quiet(bot: &Bot, msg: &Message, db: &Db, chat_id: i64, args: &str) -> HandlerResult {
+    let user = match db.get_user(chat_id)? {
+        Some(u) => u,
+        None => {
+            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+            return Ok(());
+        }
+    };
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let reply = match parts.as_slice() {
+        [] => None,
+        ["off"] => {
+            db.update_quiet_hours(chat_id, None)?;
+            Some("\u{2705} Quiet hours off.".to_string())
+        }
+        [start, end] => match (parse_hhmm(start), parse_hhmm(end)) {
+            (Some(start), Some(end)) if start != end => {
+                db.update_quiet_hours(chat_id, Some((&start, &end)))?;
+                Some(format!(
+                    "\u{2705} Quiet hours set: {}\u{2013}{} ({}).\n\
+                     Alerts in that window are {}.",
+                    start, end, timezone::tz_abbrev(user.tz()),
+                    if user.quiet_digest { "queued for a digest when it ends" } else { "dropped" }
+                ))
+            }
+            _ => Some("Usage: /quiet 22:00 07:00 (start and end must differ)".to_string()),
+        },
+        _ => Some("Usage: /quiet, /quiet 22:00 07:00 or /quiet off".to_string()),
+    };
+    if let Some(text) = reply {
+        bot.send_message(msg.chat.id, text).await?;
+        return Ok(());
+    }
+    let muted = db.get_muted_alerts(chat_id)?;
+    bot.send_message(msg.chat.id, messages::format_quiet_settings(&user, &muted))
+        .reply_markup(quiet_keyboard(&user, &muted))
+        .await?;
+    Ok(())
+}
+
+/// Most destinations a user can add besides their Telegram chat.
+const MAX_CHANNELS: usize = 5;
+
+async fn cmd_notify(
+    bot: &Bot,
+    msg: &Message,
+    db: &Db,
+    cfg: &Config,
+    delivery: &Delivery,
+    chat_id: i64,
+    args: &str,
+) -> HandlerResult {
+    let user = match db.get_user(chat_id)? {
+        Some(u) => u,
+        None => {
+            bot.send_message(msg.chat.id, "Please use /start to set your region first.").await?;
+            return Ok(());
+        }
+    };
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let channels = db.get_notify_channels(chat_id)?;
+    let find = |arg: &str| arg.trim_start_matches('#').parse::<i64>().ok().and_then(|id| channels.iter().find(|c| c.id == id));
+
+    // (kind, target, secret) for a new destination
+    let add = match parts.as_slice() {
+        [] => {
+            let telegram = db.get_telegram_alerts(chat_id)?;
+            let text = messages::format_notify_channels(telegram.as_deref(), &channels, delivery.has_email());
+            bot.send_message(msg.chat.id, text).await?;
+            return Ok(());
+        }
+        ["webhook", url] => Ok(("webhook", *url, Some(channels::new_secret()))),
+        ["webhook", url, secret] => Ok(("webhook", *url, Some(secret.to_string()))),
+        ["ntfy", url] => Ok(("ntfy", *url, None)),
+        ["ntfy", url, token] => Ok(("ntfy", *url, Some(token.to_string()))),
+        ["gotify", url, token] => Ok(("gotify", *url, Some(token.to_string()))),
+        ["email", _] if !delivery.has_email() => Err("Email alerts aren't set up on this server.".to_string()),
+        ["email", address] => Ok(("email", *address, None)),
+        ["remove", id] => {
+            let text = match find(id) {
+                Some(ch) if db.remove_notify_channel(chat_id, ch.id)? => format!("\u{2705} Removed #{} ({}).", ch.id, ch.kind),
+                _ => format!("No destination #{}. Send /notify to list yours.", id.trim_start_matches('#')),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            return Ok(());
+        }
+        ["types", which, types] => {
+            let text = match parse_alert_types(types) {
+                Err(e) => e,
+                Ok(types) if *which == "telegram" => {
+                    db.set_telegram_alerts(chat_id, types.as_deref())?;
+                    "\u{2705} Updated the alerts this chat receives.".to_string()
+                }
+                Ok(types) => match find(which) {
+                    Some(ch) => {
+                        db.set_channel_alert_types(chat_id, ch.id, types.as_deref())?;
+                        format!("\u{2705} Updated the alerts sent to #{}.", ch.id)
+                    }
+                    None => format!("No destination {}. Send /notify to list yours.", which),
+                },
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            return Ok(());
+        }
+        ["test", which] => {
+            let (price, interval_time) = db
+                .get_latest_price(&user.region)?
+                .unwrap_or_else(|| (0.0, timezone::market_str(&chrono::Utc::now())));
+            let alert = PendingAlert {
+                chat_id,
+                alert_type: "test".to_string(),
+                region: user.region.clone(),
+                price,
+                interval_time,
+                threshold: Some(Threshold::price(user.high_alert, user.high_alert_retail)),
+                value: user.high_alert_retail.is_none().then_some(price),
+                text: format!(
+                    "\u{1f514} Test alert from NEM Price Bot\n{} is ${:.2}/MWh. Real alerts will look like this.",
+                    messages::region_display(&user.region), price
+                ),
+            };
+            let result = if *which == "telegram" {
+                delivery.telegram().send(&alert).await
+            } else {
+                match find(which) {
+                    Some(ch) => {
+                        let result = match delivery.channel(ch) {
+                            Ok(notifier) => notifier.send(&alert).await,
+                            Err(e) => Err(e),
+                        };
+                        db.set_channel_error(ch.id, result.as_ref().err().map(|e| e.to_string()).as_deref())?;
+                        result
+                    }
+                    None => {
+                        bot.send_message(msg.chat.id, format!("No destination {}. Send /notify to list yours.", which)).await?;
+                        return Ok(());
+                    }
+                }
+            };
+            let text = match result {
+                Ok(()) => "\u{2705} Test alert sent.".to_string(),
+                Err(e) => format!("\u{26a0}\u{fe0f} Test alert failed: {}", e),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            return Ok(());
+        }
+        _ => Err("Send /notify to see your destinations and usage.".to_string()),
+    };
+
+    let check = match add {
+        Ok(_) if channels.len() >= MAX_CHANNELS => {
+            Err(format!("You can add up to {} destinations. Remove one first.", MAX_CHANNELS))
+        }
+        Ok(("email", target, _)) if target.parse::<lettre::Address>().is_err() => {
+            Err("That isn't a valid email address.".to_string())
+        }
+        Ok((kind, target, secret)) if kind != "email" => match channels::check_url(target, cfg.notify_allow_http).await {
+            Ok(()) => Ok((kind, target, secret)),
+            Err(e) => Err(format!("{}.", e)),
+        },
+        other => other,
+    };
+    let text = match check {
+        Err(e) => e,
+        Ok((kind, target, secret)) => {
+            let id = db.add_notify_channel(chat_id, kind, target, secret.as_deref())?;
+            let mut text = format!(
+                "\u{2705} Added #{} ({}). It gets all alerts; use /notify types {} to choose, and /notify test {} to try it.",
+                id, kind, id, id
+            );
+            if let (Some(secret), "webhook") = (&secret, kind) {
+                text.push_str(&format!(
+                    "\n\nSigning secret: {}\nEach request carries X-Signature-256: sha256=<hex HMAC-SHA256 of the body>.",
+                    secret
+                ));
+            }
+            text
+        }
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+/// "all", "none" or "high_price,spike" to a routing list (None for all).
+fn parse_alert_types(arg: &str) -> Result<Option<Vec<String>>, String> {
+    match arg {
+        "all" => return Ok(None),
+        "none" => return Ok(Some(Vec::new())),
+        _ => {}
+    }
+    let mut types = Vec::new();
+    for t in arg.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !ALERT_TYPES.iter().any(|(key, _, _)| *key == t) {
+            return Err(format!("Unknown alert type {}. Send /notify for the list.", t));
+        }
+        types.push(t.to_string());
+    }
+    Ok(Some(types))
+}
+
+/// "22:00", "7:30" or "22" to "HH:MM".
+fn parse_hhmm(arg: &str) -> Option<String> {
+    let arg = if arg.contains(':') { arg.to_string() } else { format!("{}:00", arg) };
+    chrono::NaiveTime::parse_from_str(&arg, "%H:%M").ok().map(|t| t.format("%H:%M").to_string())
+}
 
-fn now_aest_str() -> String {
-    chrono::Utc::now()
-        .with_timezone(&chrono_tz::Australia::Brisbane)
-        .format("%Y/%m/%d %H:%M:%S")
-        .to_string()
+/// "20,300" for whole-dollar amounts.
+fn thousands(v: f64) -> String {
+    let digits = format!("{:.0}", v);
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
 }
 
-fn later_aest_str(hours: i64) -> String {
-    (chrono::Utc::now() + chrono::Duration::hours(hours))
-        .with_timezone(&chrono_tz::Australia::Brisbane)
-        .format("%Y/%m/%d %H:%M:%S")
-        .to_string()
+/// "45.0c/kWh retail" when a retail threshold is set, else "$200/MWh".
+fn threshold_display(wholesale: f64, retail: Option<f64>) -> String {
+    match retail {
+        Some(c) => format!("{:.1}c/kWh retail", c),
+        None => format!("${:.0}/MWh", wholesale),
+    }
 }
 
-fn now_aest_date() -> String {
-    chrono::Utc::now()
-        .with_timezone(&chrono_tz::Australia::Brisbane)
-        .format("%Y/%m/%d")
-        .to_string()
+/// Duration, hysteresis band and clear level, e.g. "after 3 intervals, $20/MWh band, clears below $250".
+fn rules_display(user: &User) -> String {
+    let mut parts = vec![match user.min_intervals {
+        1 => "alert on the first interval".to_string(),
+        n => format!("alert after {} intervals ({} min)", n, n * 5),
+    }];
+    if user.hysteresis > 0.0 {
+        parts.push(format!("${:.0}/MWh band", user.hysteresis));
+    }
+    if let Some(clear) = user.clear_alert {
+        parts.push(format!("clears below ${:.0}", clear));
+    }
+    parts.join(", ")
+}
+
+/// "\n• Also following SA: above $300, below $0 (paused)" per non-home region.
+fn other_regions_display(users: &[User]) -> String {
+    users
+        .iter()
+        .filter(|u| !u.is_home())
+        .map(|u| format!(
+            "\n\u{2022} Also following {}: above ${:.0}, below ${:.0}{}",
+            messages::region_display(&u.region), u.high_alert, u.low_alert,
+            if u.region_active { "" } else { " (paused)" }
+        ))
+        .collect()
+}
+
+/// The coverage warning for `/alert carbon`, when `region`'s latest mix is too incomplete
+/// for carbon alerts to be checked.
+fn carbon_coverage_note(db: &Db, region: &str) -> String {
+    match db.get_latest_mix(region) {
+        Ok(Some(mix)) if mix.coverage() < generators::MIN_COVERAGE => {
+            messages::format_carbon_coverage_note(region, mix.coverage())
+        }
+        _ => String::new(),
+    }
+}
+
+fn carbon_display(high: Option<f64>, low: Option<f64>) -> String {
+    match (high, low) {
+        (None, None) => "None (/alert carbon)".to_string(),
+        (Some(h), None) => format!("above {:.0} g/kWh", h),
+        (None, Some(l)) => format!("below {:.0} g/kWh", l),
+        (Some(h), Some(l)) => format!("above {:.0} / below {:.0} g/kWh", h, l),
+    }
 }
 
 /// Calculate how many minutes ago an AEMO interval_time was.
 /// Returns -1 if the timestamp cannot be parsed.
 fn interval_age_minutes(interval_time: &str) -> i64 {
-    let now = chrono::Utc::now().with_timezone(&chrono_tz::Australia::Brisbane);
-    chrono::NaiveDateTime::parse_from_str(interval_time, "%Y/%m/%d %H:%M:%S")
-        .ok()
-        .and_then(|naive| naive.and_local_timezone(chrono_tz::Australia::Brisbane).single())
+    let now = timezone::now_market();
+    timezone::parse_market(interval_time)
         .map(|dt| now.signed_duration_since(dt).num_minutes().max(0))
         .unwrap_or(-1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn range(args: &str) -> Option<(NaiveDate, NaiveDate)> {
+        let words: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_history_range(&words, NaiveDate::from_ymd_opt(2026, 1, 15).unwrap())
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, d).unwrap()
+    }
+
+    #[test]
+    fn history_ranges() {
+        assert_eq!(range(""), Some((day(9), day(15))));
+        assert_eq!(range("today"), Some((day(15), day(15))));
+        assert_eq!(range("yesterday"), Some((day(14), day(14))));
+        assert_eq!(range("30d"), Some((NaiveDate::from_ymd_opt(2025, 12, 17).unwrap(), day(15))));
+        assert_eq!(range("2026-01-03"), Some((day(3), day(3))));
+        assert_eq!(range("2026-01-03 2026-01-10"), Some((day(3), day(10))));
+        assert_eq!(range("2026-01-03..2026-01-10"), Some((day(3), day(10))));
+        // Past the earliest date chrono can represent
+        assert_eq!(range("100000000d"), None);
+        assert_eq!(range("18446744073709551615d"), None);
+    }
+
+    #[test]
+    fn bad_history_ranges() {
+        assert_eq!(range("0d"), None);
+        assert_eq!(range("d"), None);
+        assert_eq!(range("2026-01-10 2026-01-03"), None);
+        assert_eq!(range("2026-01-10 2026-01-16"), None);
+        assert_eq!(range("last week"), None);
+        assert_eq!(range("2026-01-01 2026-01-02 2026-01-03"), None);
+    }
+}
@@ -1,3 +1,23 @@
+use chrono_tz::Tz;
+use std::collections::HashMap;
+
+use crate::data::fcas;
+use crate::data::generators::{self, GenerationMix};
+use crate::data::interconnectors::{self, Interconnector};
+use crate::data::market_params::{AppStatus, MarketParams};
+use crate::data::notices::{MarketNotice, NoticeKind, Severity};
+use crate::data::parser::{InterconnectorRecord, RegionDemand};
+use crate::data::weather::SolarPotential;
+use crate::db::repository::{
+    BatteryProfile, DispatchGap, ForecastRuns, ForecastScore, Grouping, NotifyChannel, PriceBucket, PriceStats,
+    Savings, User,
+};
+use crate::engine::accuracy;
+use crate::engine::analyzer::{self, PendingAlert, Revision, Threshold, Unit, ALERT_TYPES};
+use crate::engine::optimiser::{self, Action, Plan};
+use crate::engine::tariff::{self, RetailPrice, Tariff};
+use crate::timezone;
+
 /// Returns (emoji, label, suggestion) for a price level.
 pub fn price_level(price: f64) -> (&'static str, &'static str, &'static str) {
     if price < 0.0 {
@@ -26,24 +46,35 @@ pub fn region_display(region: &str) -> &str {
     }
 }
 
-fn format_time_short(interval_time: &str) -> &str {
-    // "2026/02/27 14:35:00" -> "14:35"
-    if interval_time.len() >= 16 {
-        &interval_time[11..16]
-    } else {
-        interval_time
+/// "Retail (peak): 38.2c/kWh import | 9.5c/kWh export", or empty without a tariff.
+fn retail_str(retail: Option<RetailPrice>) -> String {
+    match retail {
+        Some(r) => format!(
+            "\nRetail ({}): {:.1}c/kWh import | {:.1}c/kWh export",
+            r.period.label(), r.import_c, r.export_c
+        ),
+        None => String::new(),
     }
 }
 
-pub fn format_price_response(
-    region: &str,
-    price: f64,
-    interval_time: &str,
-    daily_range: Option<(f64, f64)>,
-    age_minutes: i64,
-) -> String {
+/// Everything shown in a /price reply for one region.
+pub struct PriceView<'a> {
+    pub region: &'a str,
+    pub price: f64,
+    pub interval_time: &'a str,
+    pub daily_range: Option<(f64, f64)>,
+    pub age_minutes: i64,
+    pub demand: Option<&'a RegionDemand>,
+    pub retail: Option<RetailPrice>,
+    /// 7-day cumulative price and the settings in force for the interval.
+    pub market: (f64, &'a MarketParams),
+    pub tz: Tz,
+}
+
+pub fn format_price_response(view: &PriceView) -> String {
+    let PriceView { region, price, interval_time, daily_range, age_minutes, demand, retail, market, tz } = *view;
     let (emoji, label, suggestion) = price_level(price);
-    let time_str = format_time_short(interval_time);
+    let time_str = timezone::format_local_time(interval_time, tz);
     let range_str = match daily_range {
         Some((min, max)) => format!("Today's range: ${:.0} ~ ${:.0}", min, max),
         None => "No data for today yet.".into(),
@@ -56,22 +87,332 @@ pub fn format_price_response(
         format!(" ({} min ago)", age_minutes)
     };
     let stale = if age_minutes > 5 { " \u{26a0}\u{fe0f}" } else { "" };
+    let demand_str = match demand {
+        Some(d) => format!(
+            "\n\nDemand: {:.0} MW | Supply margin: {:.0} MW ({:.0}%)",
+            d.demand_mw, d.supply_margin_mw(), d.margin_pct()
+        ),
+        None => String::new(),
+    };
+    let (cumulative, params) = market;
+    let limit_str = if price >= params.price_cap {
+        format!("\n\u{26d4} At the market price cap (${:.0})", params.price_cap)
+    } else if price <= params.price_floor {
+        format!("\n\u{26d4} At the market floor (-${:.0})", -params.price_floor)
+    } else {
+        String::new()
+    };
+    let app_str = match params.app_status(cumulative) {
+        AppStatus::Active => format!(
+            "\n\n\u{1f6d1} Administered pricing: prices capped at ${:.0}/MWh\n{}",
+            params.administered_cap, cumulative_str(cumulative, params)
+        ),
+        AppStatus::Likely => format!("\n\n\u{1f7e0} Administered pricing likely\n{}", cumulative_str(cumulative, params)),
+        AppStatus::Normal => String::new(),
+    };
     format!(
-        "\u{26a1} {} Spot Price\n\n${:.2}/MWh {} {}\n\n{}\n\nUpdated: {} AEST{}{} | {}",
-        region_display(region), price, emoji, label, suggestion, time_str, age_str, stale, range_str
+        "\u{26a1} {} Spot Price\n\n${:.2}/MWh {} {}{}{}\n\n{}{}{}\n\nUpdated: {} {}{}{} | {}",
+        region_display(region), price, emoji, label, limit_str, retail_str(retail), suggestion, demand_str, app_str,
+        time_str, timezone::tz_abbrev_at(interval_time, tz), age_str, stale, range_str
     )
 }
 
-pub fn format_forecast_response(region: &str, forecasts: &[(String, f64)]) -> String {
+pub fn format_fcas_response(region: &str, interval_time: &str, prices: &HashMap<String, f64>, tz: Tz) -> String {
+    let line = |s: &fcas::Service| {
+        prices
+            .get(s.key)
+            .map(|p| format!("{:<11} ${:.2}/MWh", s.label, p))
+    };
+    let contingency: Vec<String> = fcas::SERVICES.iter().filter(|s| s.contingency).filter_map(line).collect();
+    let regulation: Vec<String> = fcas::SERVICES.iter().filter(|s| !s.contingency).filter_map(line).collect();
+    let mut lines = vec![format!("\u{1f39b}\u{fe0f} {} FCAS Prices\n", region_display(region))];
+    if !contingency.is_empty() {
+        lines.push("Contingency:".into());
+        lines.extend(contingency);
+    }
+    if !regulation.is_empty() {
+        lines.push("\nRegulation:".into());
+        lines.extend(regulation);
+    }
+    lines.push(format!(
+        "\nUpdated: {} {}\n\n\u{1f4a1} VPP batteries are paid these for standing ready to raise or lower output.\n\
+         Set alerts with /alert fcas <service> <$/MWh>.",
+        timezone::format_local_time(interval_time, tz), timezone::tz_abbrev_at(interval_time, tz)
+    ));
+    lines.join("\n")
+}
+
+/// A region's current generation by fuel, with renewable share and emissions intensity.
+/// `rooftop_mw` is the latest rooftop PV estimate, shown separately as it sits behind the meter.
+pub fn format_mix_response(region: &str, mix: &GenerationMix, rooftop_mw: Option<f64>, tz: Tz) -> String {
+    let total = mix.total_mw();
+    let share = |mw: f64| if total > 0.0 && mw > 0.0 { format!(" ({:.0}%)", mw / total * 100.0) } else { String::new() };
+    let mut lines = vec![format!("\u{1f3ed} {} Generation Mix\n", region_display(region))];
+    for &(fuel, mw, _) in &mix.by_fuel {
+        if mw.abs() < 1.0 {
+            continue;
+        }
+        let note = if mw < 0.0 { " charging" } else { "" };
+        lines.push(format!("{} {:<8} {:>6.0} MW{}{}", fuel.emoji(), fuel.label(), mw, share(mw), note));
+    }
+    let unclassified = mix.unclassified_mw();
+    if unclassified >= 1.0 {
+        lines.push(format!("\u{2754} {:<8} {:>6.0} MW{}", "Unclassified", unclassified, share(unclassified)));
+    }
+    lines.push(format!("\nTotal: {:.0} MW", total));
+    if let Some(mw) = rooftop_mw.filter(|mw| *mw >= 1.0) {
+        lines.push(format!("Rooftop solar (behind the meter): ~{:.0} MW", mw));
+    }
+    let intensity = mix.intensity();
+    let (emoji, hint) = if intensity < 300.0 {
+        ("\u{1f7e2}", "Low-carbon interval \u{2014} a good time to charge.")
+    } else if intensity < 700.0 {
+        ("\u{1f7e1}", "Moderate emissions.")
+    } else {
+        ("\u{1f534}", "Fossil-heavy interval \u{2014} defer flexible load if you can.")
+    };
+    lines.push(format!(
+        "\nRenewables: {:.0}%\nEmissions: {} {:.0} g CO\u{2082}/kWh\n{}",
+        mix.renewable_pct(), emoji, intensity, hint
+    ));
+    if mix.coverage() < generators::MIN_COVERAGE {
+        lines.push(format!(
+            "Only {:.0}% of output is from known units, so emissions may be higher than shown.",
+            mix.coverage() * 100.0
+        ));
+    }
+    lines.push(format!(
+        "\nUpdated: {} {} \u{b7} Units in this region only; imports not included.",
+        timezone::format_local_time(&mix.interval_time, tz), timezone::tz_abbrev_at(&mix.interval_time, tz)
+    ));
+    lines.join("\n")
+}
+
+/// Every interconnector's flow for one interval, binding ones flagged.
+pub fn format_flows_response(interval_time: &str, flows: &[InterconnectorRecord], tz: Tz) -> String {
+    let mut lines = vec!["\u{1f50c} Interconnector Flows\n".to_string()];
+    let mut binding = Vec::new();
+    for f in flows {
+        let (name, from, to) = match interconnectors::find(&f.interconnector_id) {
+            Some(ic) => (ic.name, region_display(ic.from), region_display(ic.to)),
+            None => (f.interconnector_id.as_str(), "", ""),
+        };
+        // Show the direction power is actually moving
+        let dir = match (from.is_empty(), f.mw_flow >= 0.0) {
+            (true, _) => String::new(),
+            (false, true) => format!(" {from} \u{2192} {to}"),
+            (false, false) => format!(" {to} \u{2192} {from}"),
+        };
+        let (emoji, note) = if f.is_binding() {
+            binding.push(name);
+            ("\u{1f534}", " \u{2014} at limit")
+        } else {
+            ("\u{1f7e2}", "")
+        };
+        lines.push(format!(
+            "{} {}{}: {:.0} MW (limit {:.0}){}",
+            emoji, name, dir, f.mw_flow.abs(), f.limit_mw().abs(), note
+        ));
+    }
+    lines.push(String::new());
+    if binding.is_empty() {
+        lines.push("No interconnectors are at their limit.".into());
+    } else {
+        lines.push(format!(
+            "\u{26a0}\u{fe0f} Binding: {}\nThe importing region can't draw more from its neighbours, so its price can rise on its own.",
+            binding.join(", ")
+        ));
+    }
+    lines.push(format!(
+        "\nUpdated: {} {}",
+        timezone::format_local_time(interval_time, tz), timezone::tz_abbrev_at(interval_time, tz)
+    ));
+    lines.join("\n")
+}
+
+fn severity_emoji(severity: Option<Severity>) -> &'static str {
+    match severity {
+        Some(Severity::High) => "\u{1f6a8}",
+        Some(Severity::Medium) => "\u{26a0}\u{fe0f}",
+        _ => "\u{2139}\u{fe0f}",
+    }
+}
+
+fn notice_regions(notice: &MarketNotice) -> String {
+    if notice.regions.is_empty() {
+        "NEM-wide".to_string()
+    } else {
+        notice.regions.iter().map(|r| region_display(r)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// AEMO's reason text cut to a readable length, without the repeated reference line.
+fn notice_excerpt(notice: &MarketNotice) -> String {
+    const MAX_CHARS: usize = 400;
+    let text = notice
+        .reason
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty() && !p.eq_ignore_ascii_case(notice.reference.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if text.chars().count() <= MAX_CHARS {
+        text
+    } else {
+        format!("{}\u{2026}", text.chars().take(MAX_CHARS).collect::<String>().trim_end())
+    }
+}
+
+pub fn format_notice_alert(notice: &MarketNotice, tz: Tz) -> String {
+    let hint = if notice.cancelled {
+        "The earlier notice no longer applies."
+    } else {
+        match notice.kind {
+            NoticeKind::Lor(3) => "Load shedding is possible. Expect extreme prices \u{2014} discharge your battery and minimise grid usage.",
+            NoticeKind::Lor(_) => "Reserves are tight and prices often spike. Keep your battery charged.",
+            NoticeKind::Suspension => "The market is suspended; prices follow AEMO's schedule until it resumes.",
+            _ => "AEMO has stepped in to keep the system secure; prices may not reflect supply and demand.",
+        }
+    };
+    format!(
+        "{} MARKET NOTICE \u{2014} {}{} ({})\n\n\
+         {}\n\
+         Issued {} {} \u{b7} Notice {}\n\n\
+         {}\n\n\
+         \u{1f4a1} {}\n\
+         /notices shows recent notices.",
+        severity_emoji(notice.severity()),
+        notice.kind.label(),
+        if notice.cancelled { " cancelled" } else { "" },
+        notice_regions(notice),
+        notice.reference,
+        timezone::format_local_time(&notice.issued_at, tz), timezone::tz_abbrev_at(&notice.issued_at, tz),
+        notice.id,
+        notice_excerpt(notice),
+        hint
+    )
+}
+
+/// Recent notices for the user's region and their opt-in setting.
+pub fn format_notices_response(
+    region: &str, notices: &[MarketNotice], setting: Option<Severity>, tz: Tz,
+) -> String {
+    let mut lines = vec![format!("\u{1f4e2} Market Notices \u{2014} {}\n", region_display(region))];
+    if notices.is_empty() {
+        lines.push("No LOR, direction or suspension notices recently.".into());
+    }
+    for n in notices {
+        let when = timezone::to_local(&n.issued_at, tz)
+            .map(|t| t.format("%d %b %H:%M").to_string())
+            .unwrap_or_else(|| n.issued_at.clone());
+        lines.push(format!(
+            "{} {} \u{2014} {}{} ({})\n   {}",
+            severity_emoji(n.severity()), when, n.kind.label(),
+            if n.cancelled { " cancelled" } else { "" }, notice_regions(n), n.reference
+        ));
+    }
+    lines.push(String::new());
+    lines.push(match setting {
+        Some(Severity::High) => "Alerts: LOR3 and market suspension only.".into(),
+        Some(Severity::Medium) => "Alerts: LOR2 and above, directions and interventions.".into(),
+        Some(Severity::Low) => "Alerts: all LOR, direction, intervention and suspension notices.".into(),
+        None => "Alerts: off.".into(),
+    });
+    lines.push(
+        "\n/notices high \u{2014} LOR3 and suspensions\n\
+         /notices medium \u{2014} plus LOR2, directions, interventions\n\
+         /notices low \u{2014} everything incl. LOR1 and cancellations\n\
+         /notices off \u{2014} stop notice alerts"
+            .into(),
+    );
+    lines.join("\n")
+}
+
+pub fn format_fcas_alert(region: &str, service: &fcas::Service, price: f64, threshold: f64) -> String {
+    format!(
+        "\u{1f39b}\u{fe0f} FCAS PRICE \u{2014} {}\n\n\
+         {} is at ${:.2}/MWh\n\
+         Your threshold: ${:.0}/MWh\n\n\
+         \u{1f4a1} If you're in a VPP, keep your battery available for frequency control.\n\
+         /price fcas shows all services.",
+        region_display(region), service.label, price, threshold
+    )
+}
+
+/// Intensity has risen above the user's `/alert carbon high` threshold.
+pub fn format_carbon_high_alert(region: &str, mix: &GenerationMix, threshold: f64) -> String {
+    format!(
+        "\u{1f3ed} HIGH EMISSIONS \u{2014} {}\n\n\
+         Grid carbon intensity: {:.0} g CO\u{2082}/kWh \u{1f534}\n\
+         Your threshold: {:.0} g/kWh\n\
+         Renewables: {:.0}%\n\n\
+         \u{1f4a1} What to do:\n\
+         \u{2192} Run the house from your battery\n\
+         \u{2192} Hold off on EV charging and other flexible loads\n\n\
+         /mix shows the full breakdown.",
+        region_display(region), mix.intensity(), threshold, mix.renewable_pct()
+    )
+}
+
+/// Added to `/alert carbon` replies while too little of the region's output comes from
+/// known units for carbon alerts to be checked.
+pub fn format_carbon_coverage_note(region: &str, coverage: f64) -> String {
+    format!(
+        "\n\n\u{26a0}\u{fe0f} Only {:.0}% of {} generation is from units this bot knows, under the {:.0}% \
+         carbon alerts need, so they won't fire until that improves. /mix shows the current share.",
+        coverage * 100.0,
+        region_display(region),
+        generators::MIN_COVERAGE * 100.0
+    )
+}
+
+/// Intensity has fallen below the user's `/alert carbon low` threshold.
+pub fn format_carbon_low_alert(region: &str, mix: &GenerationMix, threshold: f64) -> String {
+    format!(
+        "\u{1f33f} LOW EMISSIONS \u{2014} {}\n\n\
+         Grid carbon intensity: {:.0} g CO\u{2082}/kWh \u{1f7e2}\n\
+         Your threshold: {:.0} g/kWh\n\
+         Renewables: {:.0}%\n\n\
+         \u{1f4a1} What to do:\n\
+         \u{2192} Charge your battery or EV from the grid\n\
+         \u{2192} Run washing machine, dryer, dishwasher\n\n\
+         /mix shows the full breakdown.",
+        region_display(region), mix.intensity(), threshold, mix.renewable_pct()
+    )
+}
+
+/// A user's FCAS thresholds, or how to set one.
+pub fn format_fcas_alerts(alerts: &[(String, f64)]) -> String {
+    let keys: Vec<&str> = fcas::SERVICES.iter().map(|s| s.key).collect();
+    let mut lines = vec!["\u{1f39b}\u{fe0f} FCAS alerts\n".to_string()];
+    if alerts.is_empty() {
+        lines.push("None set.".into());
+    }
+    for (key, threshold) in alerts {
+        let label = fcas::find(key).map(|s| s.label).unwrap_or(key.as_str());
+        lines.push(format!("\u{2022} {} above ${:.0}/MWh", label, threshold));
+    }
+    lines.push(format!(
+        "\nUsage:\n/alert fcas raise6sec 20 \u{2014} notify above $20/MWh\n/alert fcas raise6sec off \u{2014} remove\n\n\
+         Services: {}",
+        keys.join(", ")
+    ));
+    lines.join("\n")
+}
+
+/// `pv_peak` is the forecast rooftop PV peak in the window, used to explain negative prices.
+pub fn format_forecast_response(
+    region: &str, forecasts: &[(String, f64)], tariff: Option<&Tariff>, pv_peak: Option<(&str, f64)>, tz: Tz,
+) -> String {
     if forecasts.is_empty() {
         return format!("\u{1f4c8} {} Price Forecast\n\nNo forecast data available.", region_display(region));
     }
     let mut lines = vec![format!("\u{1f4c8} {} Price Forecast\n", region_display(region))];
     let mut peak_price = f64::MIN;
     let mut peak_time = "";
-    for (time, price) in forecasts {
+    for ((time, price), minutes) in forecasts.iter().zip(tariff::outlook_minutes(forecasts)) {
         let (emoji, _, _) = price_level(*price);
-        let ts = format_time_short(time);
+        let ts = timezone::format_local_time(time, tz);
         let marker = if *price > peak_price {
             peak_price = *price;
             peak_time = time;
@@ -79,40 +420,164 @@ pub fn format_forecast_response(region: &str, forecasts: &[(String, f64)]) -> St
         } else {
             ""
         };
-        lines.push(format!("{}  ${:.0}/MWh   {}{}", ts, price, emoji, marker));
+        let retail = match tariff {
+            Some(t) => format!("  {:.0}c", t.retail_price(*price, time, minutes).import_c),
+            None => String::new(),
+        };
+        lines.push(format!("{}  ${:.0}/MWh{}   {}{}", ts, price, retail, emoji, marker));
     }
     // Re-mark the actual peak (remove intermediate markers)
-    let peak_ts = format_time_short(peak_time);
+    let peak_ts = timezone::format_local_time(peak_time, tz);
+    if let Some(t) = tariff {
+        lines.push(format!("\nRetail import prices (c/kWh) on {} {}.", t.network, t.name));
+    }
+    // P5MIN points come first, 5 minutes apart
+    let five_minute = match forecasts {
+        [(a, _), (b, _), ..] => timezone::parse_market(a)
+            .zip(timezone::parse_market(b))
+            .is_some_and(|(a, b)| (b - a).num_minutes() == 5),
+        _ => false,
+    };
+    if five_minute {
+        lines.push("\nNext hour in 5-minute steps, then half-hourly.".into());
+    }
+    if let Some((time, mw)) = pv_peak.filter(|_| forecasts.iter().any(|(_, p)| *p < 0.0)) {
+        lines.push(format!(
+            "\n\u{2600}\u{fe0f} Rooftop solar is forecast to peak at {:.0} MW around {} {} \u{2014} \
+             that surplus is why prices are expected to go negative.",
+            mw, timezone::format_local_time(time, tz), timezone::tz_abbrev_at(time, tz)
+        ));
+    }
     lines.push(format!(
-        "\n\u{1f4a1} Peak expected around {}.\n\n\u{26a0}\u{fe0f} Forecasts are estimates and may change.",
-        peak_ts
+        "\n\u{1f4a1} Peak expected around {} {}.\n\n\u{26a0}\u{fe0f} Forecasts are estimates and may change.",
+        peak_ts, timezone::tz_abbrev_at(peak_time, tz)
     ));
     lines.join("\n")
 }
 
-pub fn format_high_alert(region: &str, price: f64, threshold: f64, daily_range: Option<(f64, f64)>) -> String {
+/// Collapse a plan into runs of the same action, skipping idle stretches.
+fn plan_lines(plan: &Plan, tz: Tz) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut i = 0;
+    while i < plan.steps.len() {
+        let action = plan.steps[i].action;
+        let mut j = i;
+        while j + 1 < plan.steps.len() && plan.steps[j + 1].action == action {
+            j += 1;
+        }
+        if action != Action::Idle {
+            let run = &plan.steps[i..=j];
+            let kwh: f64 = run.iter().map(|s| s.grid_kwh.abs()).sum();
+            let avg = run.iter().map(|s| s.price).sum::<f64>() / run.len() as f64;
+            // Step times are period ends; the run starts one period before the first
+            let start = timezone::parse_market(&run[0].time)
+                .map(|t| t - chrono::Duration::minutes((optimiser::PERIOD_HOURS * 60.0) as i64))
+                .map(|t| timezone::market_str(&t))
+                .unwrap_or_else(|| run[0].time.clone());
+            let label = match action {
+                Action::Charge => "\u{1f50c} Charge",
+                _ => "\u{26a1} Discharge",
+            };
+            lines.push(format!(
+                "{}\u{2013}{}  {} {:.1} kWh (avg ${:.0}) \u{2192} {:.1} kWh stored",
+                timezone::format_local_time(&start, tz),
+                timezone::format_local_time(&run[run.len() - 1].time, tz),
+                label, kwh, avg, run[run.len() - 1].soc_kwh
+            ));
+        }
+        i = j + 1;
+    }
+    lines
+}
+
+pub fn format_plan_response(
+    region: &str, battery: &BatteryProfile, plan: &Plan, start_soc: f64, tz: Tz,
+) -> String {
+    if plan.steps.is_empty() {
+        return format!("\u{1f50b} {} Battery Plan\n\nNo forecast data available.", region_display(region));
+    }
+    let mut lines = vec![
+        format!("\u{1f50b} {} Battery Plan\n", region_display(region)),
+        format!(
+            "{:.1} kWh | {:.1} kW | {:.0}% round-trip | {:.0}% reserve",
+            battery.capacity_kwh, battery.power_kw, battery.efficiency * 100.0, battery.reserve * 100.0
+        ),
+        format!("Starting charge: {:.0}%\n", start_soc * 100.0),
+    ];
+    let steps = plan_lines(plan, tz);
+    if steps.is_empty() {
+        lines.push("No profitable charge/discharge cycle in the forecast \u{2014} keep the battery idle.".into());
+    } else {
+        lines.extend(steps);
+        lines.push(format!("\nEstimated value: ${:.2} vs leaving the battery idle", plan.value));
+    }
+    // Abbreviations at the plan's own times, so a plan made across a daylight-saving change names both
+    let first = timezone::tz_abbrev_at(&plan.steps[0].time, tz);
+    let last = timezone::tz_abbrev_at(&plan.steps[plan.steps.len() - 1].time, tz);
+    let zone = if first == last { first } else { format!("{}, then {}", first, last) };
+    lines.push(format!(
+        "\nTimes in {}. \u{26a0}\u{fe0f} Based on pre-dispatch forecasts, which may change.",
+        zone
+    ));
+    lines.join("\n")
+}
+
+pub fn format_savings_response(
+    region: &str, day: Option<&Savings>, week: Option<&Savings>, month: Option<&Savings>,
+) -> String {
+    let line = |label: &str, s: Option<&Savings>| match s {
+        Some(s) => format!(
+            "{}: ${:+.2} vs flat rate ({} day{}, {:.1} kWh in / {:.1} kWh out)",
+            label, s.saved(), s.days, if s.days == 1 { "" } else { "s" }, s.charged_kwh, s.discharged_kwh
+        ),
+        None => format!("{}: no data yet", label),
+    };
+    format!(
+        "\u{1f4b0} {} Battery Savings\n\n{}\n{}\n{}\n\n\
+         Estimated for a household that charged when prices were low and\n\
+         discharged above its high alert, compared with paying a flat rate\n\
+         equal to each day's average spot price.",
+        region_display(region),
+        line("Yesterday", day),
+        line("Last 7 days", week),
+        line("Last 30 days", month),
+    )
+}
+
+pub fn format_high_alert(
+    region: &str,
+    price: f64,
+    threshold: f64,
+    daily_range: Option<(f64, f64)>,
+    retail: Option<RetailPrice>,
+    retail_threshold: Option<f64>,
+) -> String {
+    let threshold_str = match retail_threshold {
+        Some(c) => format!("{:.1}c/kWh", c),
+        None => format!("${:.0}/MWh", threshold),
+    };
     let range_str = match daily_range {
         Some((min, max)) => format!("Today's range: ${:.0} ~ ${:.0}", min, max),
         None => String::new(),
     };
     format!(
         "\u{26a1} HIGH PRICE \u{2014} {}\n\n\
-         Current price: ${:.2}/MWh \u{1f534}\n\
-         Your threshold: ${:.0}/MWh\n\n\
+         Current price: ${:.2}/MWh \u{1f534}{}\n\
+         Your threshold: {}\n\n\
          \u{1f4a1} What to do:\n\
          \u{2192} Switch battery to discharge / export mode\n\
          \u{2192} Avoid running dishwasher, dryer, pool pump\n\
          \u{2192} If on a VPP, ensure export is enabled\n\n\
          {}",
-        region_display(region), price, threshold, range_str
+        region_display(region), price, retail_str(retail), threshold_str, range_str
     )
 }
 
-pub fn format_low_alert(region: &str, price: f64) -> String {
+pub fn format_low_alert(region: &str, price: f64, retail: Option<RetailPrice>) -> String {
     let label = if price < 0.0 { "NEGATIVE PRICE" } else { "LOW PRICE" };
     format!(
         "\u{1f50b} {} \u{2014} {}\n\n\
-         Current price: ${:.2}/MWh \u{1f7e2}\u{1f4b0}\n\n\
+         Current price: ${:.2}/MWh \u{1f7e2}\u{1f4b0}{}\n\n\
          \u{1f4a1} What to do:\n\
          \u{2192} Switch battery to charge from grid\n\
          \u{2192} Run washing machine, dryer, dishwasher\n\
@@ -120,35 +585,200 @@ pub fn format_low_alert(region: &str, price: f64) -> String {
         label,
         region_display(region),
         price,
+        retail_str(retail),
         if price < 0.0 { "\u{2192} You're being PAID to use electricity!" } else { "" }
     )
 }
 
-pub fn format_spike_alert(region: &str, prev: f64, current: f64) -> String {
+/// `limited` are interconnectors into the region at their limit, named as the likely cause.
+pub fn format_spike_alert(
+    region: &str, prev: f64, current: f64, retail: Option<RetailPrice>, limited: &[&Interconnector],
+) -> String {
+    let cause = if limited.is_empty() {
+        "This is unusual and may indicate a supply event.".to_string()
+    } else {
+        let names: Vec<String> = limited
+            .iter()
+            .map(|ic| format!("{} ({} \u{2192} {})", ic.name, region_display(ic.from), region_display(ic.to)))
+            .collect();
+        format!(
+            "Likely cause: {} {} at its limit, so {} can't import more power. See /flows.",
+            names.join(" and "), if names.len() > 1 { "are" } else { "is" }, region_display(region)
+        )
+    };
     format!(
         "\u{26a0}\u{fe0f} PRICE SPIKE \u{2014} {}\n\n\
-         Price jumped from ${:.0} \u{2192} ${:.0}/MWh in 5 minutes!\n\
-         This is unusual and may indicate a supply event.\n\n\
+         Price jumped from ${:.0} \u{2192} ${:.0}/MWh in 5 minutes!{}\n\
+         {}\n\n\
          \u{1f4a1} Switch to battery power immediately if you haven't already.",
-        region_display(region), prev, current
+        region_display(region), prev, current, retail_str(retail), cause
+    )
+}
+
+/// "$1.52m" for cumulative prices.
+fn millions(v: f64) -> String {
+    format!("${:.2}m", v / 1_000_000.0)
+}
+
+/// "7-day cumulative price: $1.52m of the $1.82m threshold (83%)"
+fn cumulative_str(cumulative: f64, params: &MarketParams) -> String {
+    format!(
+        "7-day cumulative price: {} of the {} threshold ({:.0}%)",
+        millions(cumulative), millions(params.cumulative_threshold),
+        cumulative / params.cumulative_threshold * 100.0
+    )
+}
+
+pub fn format_app_alert(region: &str, status: AppStatus, cumulative: f64, params: &MarketParams) -> String {
+    if status == AppStatus::Active {
+        format!(
+            "\u{1f6d1} ADMINISTERED PRICING \u{2014} {}\n\n\
+             {}\n\n\
+             AEMO caps {} prices at ${:.0}/MWh until the end of the trading day on which the \
+             cumulative price falls back below the threshold.\n\n\
+             \u{1f4a1} Spot prices can't spike past the cap for now, so exporting at peaks earns less.",
+            region_display(region), cumulative_str(cumulative, params),
+            region_display(region), params.administered_cap
+        )
+    } else {
+        format!(
+            "\u{1f7e0} ADMINISTERED PRICING LIKELY \u{2014} {}\n\n\
+             {}\n\n\
+             Prices have been very high this week. If the threshold is reached, AEMO caps prices \
+             at ${:.0}/MWh until the cumulative price falls back.\n\n\
+             \u{1f4a1} Expect continued volatility; keep your battery charged for evening peaks.",
+            region_display(region), cumulative_str(cumulative, params), params.administered_cap
+        )
+    }
+}
+
+pub fn format_tight_supply_alert(
+    region: &str, demand: &RegionDemand, price: f64, retail: Option<RetailPrice>,
+) -> String {
+    format!(
+        "\u{1f7e0} TIGHT SUPPLY \u{2014} {}\n\n\
+         Supply margin: {:.0} MW ({:.0}% of demand)\n\
+         Demand: {:.0} MW | Available generation: {:.0} MW\n\
+         Current price: ${:.0}/MWh{}\n\n\
+         \u{1f4a1} Tight margins often come before price spikes:\n\
+         \u{2192} Make sure your battery is charged\n\
+         \u{2192} Be ready to switch to battery power",
+        region_display(region), demand.supply_margin_mw(), demand.margin_pct(),
+        demand.demand_mw, demand.available_generation_mw, price, retail_str(retail)
     )
 }
 
-pub fn format_forecast_alert(region: &str, forecast_price: f64, forecast_time: &str, current_price: f64) -> String {
-    let ts = format_time_short(forecast_time);
+pub fn format_forecast_alert(
+    region: &str,
+    forecast_price: f64,
+    forecast_time: &str,
+    current_price: f64,
+    retail: Option<RetailPrice>,
+    tz: Tz,
+) -> String {
+    let ts = timezone::format_local_time(forecast_time, tz);
     format!(
         "\u{1f4e2} HEADS UP \u{2014} {}\n\n\
-         Prices forecast to reach ${:.0}+/MWh around {}.\n\
+         Prices forecast to reach ${:.0}+/MWh around {} {}.{}\n\
          Current price: ${:.0}/MWh \u{1f7e1}\n\n\
          \u{1f4a1} Prepare now:\n\
          \u{2192} Ensure battery is fully charged\n\
          \u{2192} Set battery to discharge when peak begins\n\
          \u{2192} Delay any heavy appliance usage",
-        region_display(region), forecast_price, ts, current_price
+        region_display(region), forecast_price, ts, timezone::tz_abbrev_at(forecast_time, tz), retail_str(retail), current_price
     )
 }
 
-pub fn format_all_clear(region: &str, price: f64, peak: Option<f64>) -> String {
+pub fn format_revision_alert(region: &str, revisions: &[Revision], retail: Option<RetailPrice>, tz: Tz) -> String {
+    let mut lines = vec![format!("\u{1f504} FORECAST REVISED \u{2014} {}\n", region_display(region))];
+    for r in revisions.iter().take(3) {
+        let arrow = if r.new > r.old { "\u{1f53a}" } else { "\u{1f53b}" };
+        lines.push(format!(
+            "{} {}: ${:.0} \u{2192} ${:.0}/MWh {}",
+            timezone::format_local_time(&r.forecast_time, tz), timezone::tz_abbrev_at(&r.forecast_time, tz), r.old, r.new, arrow
+        ));
+    }
+    if revisions.len() > 3 {
+        lines.push(format!("\u{2026}and {} more", revisions.len() - 3));
+    }
+    lines.push(retail_str(retail).trim_start().to_string());
+    let advice = if revisions.first().map(|r| r.new > r.old).unwrap_or(false) {
+        "\u{1f4a1} The latest pre-dispatch run expects higher prices:\n\
+         \u{2192} Top up your battery before then\n\
+         \u{2192} Move heavy appliance use earlier"
+    } else {
+        "\u{1f4a1} The latest pre-dispatch run has eased \u{2014} you may not need to hold charge for this."
+    };
+    lines.push(format!("\n{}\n\n/forecast changes shows the recent runs.", advice));
+    lines.retain(|l| !l.is_empty());
+    lines.join("\n")
+}
+
+/// How the outlook has moved across the last few pre-dispatch runs.
+pub fn format_forecast_changes(region: &str, runs: &ForecastRuns, tz: Tz) -> String {
+    let title = format!("\u{1f504} {} Forecast Changes", region_display(region));
+    if runs.published.len() < 2 {
+        return format!("{}\n\nNot enough pre-dispatch runs stored yet \u{2014} check back in an hour.", title);
+    }
+    let run_times: Vec<String> = runs.published.iter().map(|p| timezone::format_local_time(p, tz)).collect();
+    let mut lines = vec![
+        format!("{}\n", title),
+        format!(
+            "Runs published at {} {}",
+            run_times.join(", "),
+            timezone::tz_abbrev_at(&runs.published[runs.published.len() - 1], tz)
+        ),
+        String::new(),
+    ];
+
+    // Periods whose forecast moved most between the first and last run that covered them
+    let mut moved: Vec<(&String, Vec<f64>)> = runs
+        .periods
+        .iter()
+        .map(|(t, prices)| (t, prices.iter().flatten().copied().collect::<Vec<_>>()))
+        .filter(|(_, p)| p.len() >= 2)
+        .collect();
+    let drift = |p: &[f64]| (p[p.len() - 1] - p[0]).abs();
+    moved.sort_by(|a, b| drift(&b.1).total_cmp(&drift(&a.1)));
+    moved.truncate(10);
+    moved.retain(|(_, p)| drift(p) >= 10.0);
+    moved.sort_by(|a, b| a.0.cmp(b.0));
+
+    if moved.is_empty() {
+        lines.push("The outlook has held steady \u{2014} no period moved by $10/MWh or more.".into());
+    } else {
+        for (time, prices) in &moved {
+            let path: Vec<String> = prices.iter().map(|p| format!("${:.0}", p)).collect();
+            let (emoji, _, _) = price_level(prices[prices.len() - 1]);
+            lines.push(format!(
+                "{}  {}  {}",
+                timezone::format_local_time(time, tz), path.join(" \u{2192} "), emoji
+            ));
+        }
+        lines.push("\nPrices in $/MWh, oldest run first. Largest moves shown.".into());
+    }
+    lines.join("\n")
+}
+
+/// Caption for a `/chart` window: the span covered and the low/average/high of actual prices.
+pub fn format_chart_caption(region: &str, hours: i64, actual: &[(String, f64)], forecast: &[(String, f64)], tz: Tz) -> String {
+    let mut text = format!("\u{1f4c8} {} \u{2014} last {}h", region_display(region), hours);
+    if let Some((t, _)) = forecast.last() {
+        text.push_str(&format!(" + forecast to {} {}", timezone::format_local_time(t, tz), timezone::tz_abbrev_at(t, tz)));
+    }
+    let Some((_, now)) = actual.last() else {
+        return format!("{}\n\nNo prices stored for this window yet.", text);
+    };
+    let (low, high) = actual.iter().fold((f64::MAX, f64::MIN), |(lo, hi), (_, p)| (lo.min(*p), hi.max(*p)));
+    let avg = actual.iter().map(|(_, p)| p).sum::<f64>() / actual.len() as f64;
+    let (emoji, label, _) = price_level(*now);
+    format!(
+        "{}\n\nNow: ${:.2}/MWh {} {}\nLow ${:.0} | Avg ${:.0} | High ${:.0}",
+        text, now, emoji, label, low, avg, high
+    )
+}
+
+pub fn format_all_clear(region: &str, price: f64, peak: Option<f64>, retail: Option<RetailPrice>) -> String {
     let peak_str = match peak {
         Some(p) => format!("\nPeak reached: ${:.0}/MWh", p),
         None => String::new(),
@@ -156,20 +786,141 @@ pub fn format_all_clear(region: &str, price: f64, peak: Option<f64>) -> String {
     let (emoji, _, _) = price_level(price);
     format!(
         "\u{2705} PRICES NORMAL \u{2014} {}\n\n\
-         Price has dropped back to ${:.2}/MWh {}\n\
+         Price has dropped back to ${:.2}/MWh {}{}\n\
          {}",
-        region_display(region), price, emoji, peak_str
+        region_display(region), price, emoji, retail_str(retail), peak_str
     )
 }
 
-pub fn format_daily_summary(
-    region: &str,
-    date_display: &str,
-    stats: Option<&crate::db::repository::DailyStats>,
-    peak_time: Option<&str>,
-    weather: Option<&crate::data::weather::WeatherForecast>,
-    alerts_today: i64,
-) -> String {
+fn bias_str(bias: f64) -> String {
+    if bias.abs() < 0.5 {
+        "no bias".into()
+    } else {
+        format!("ran ${:.0} {}", bias.abs(), if bias > 0.0 { "high" } else { "low" })
+    }
+}
+
+pub fn format_accuracy_response(region: &str, scores: &HashMap<String, ForecastScore>, days: i64) -> String {
+    let mut lines = vec![format!(
+        "\u{1f3af} {} Forecast Accuracy \u{2014} last {} days\n",
+        region_display(region), days
+    )];
+    let mut any = false;
+    for (_, bucket) in accuracy::LEAD_BUCKETS {
+        let Some(s) = scores.get(*bucket).filter(|s| s.samples > 0) else { continue };
+        any = true;
+        let spikes = match s.hit_rate() {
+            Some(rate) => format!(
+                "{:.0}% of spikes forecast ({} of {})",
+                rate * 100.0, s.spike_hits, s.spike_hits + s.spike_misses
+            ),
+            None => "no spikes".into(),
+        };
+        lines.push(format!(
+            "{} ahead: off by ${:.0}/MWh, {}\n  {} | {} false alarm{}",
+            bucket, s.mae(), bias_str(s.bias()), spikes, s.false_alarms, if s.false_alarms == 1 { "" } else { "s" }
+        ));
+    }
+    if !any {
+        lines.push("Not enough settled forecasts scored yet \u{2014} check back tomorrow.".into());
+        return lines.join("\n");
+    }
+    lines.push(format!(
+        "\nPre-dispatch forecasts vs the average of each half-hour's dispatch prices. \
+         A spike is over ${:.0}/MWh; bias shows whether forecasts tend to run high or low.",
+        accuracy::SPIKE_MWH
+    ));
+    lines.join("\n")
+}
+
+/// Everything `/history` shows for one region and date range.
+pub struct HistoryView<'a> {
+    pub region: &'a str,
+    /// Human-readable range, e.g. "Thu 01 Jan 2026 \u{2013} Sat 31 Jan 2026".
+    pub period: &'a str,
+    pub stats: Option<&'a PriceStats>,
+    /// The user's high alert, used for the hours-above line: retail when that's what
+    /// their alerts go by.
+    pub threshold: Threshold,
+    pub top: &'a [(String, f64)],
+    pub buckets: Option<(Grouping, &'a [PriceBucket])>,
+    pub tz: Tz,
+}
+
+pub fn format_history(view: &HistoryView) -> String {
+    let HistoryView { region, period, stats, threshold, top, buckets, tz } = *view;
+    let mut lines = vec![format!("\u{1f4dc} {} Price History\n{}\n", region_display(region), period)];
+    let Some(s) = stats else {
+        lines.push("No prices stored for this range. Self-hosters can import older prices with `backfill`.".into());
+        return lines.join("\n");
+    };
+    lines.push(format!("Range: ${:.0} ~ ${:.0}/MWh", s.min_price, s.max_price));
+    lines.push(format!("Mean: ${:.2} | Median: ${:.2}", s.mean_price, s.median_price));
+    lines.push(format!("Time-weighted average: ${:.2}/MWh", s.time_weighted_price));
+    lines.push(format!("Negative price hours: {:.1}h", s.negative_hours));
+    let above = match threshold.unit {
+        Unit::CentsKwh => format!("{:.1}c/kWh retail", threshold.value),
+        _ => format!("${:.0}", threshold.value),
+    };
+    lines.push(format!("Hours above {} (your high alert): {:.1}h", above, s.hours_above));
+    lines.push(format!("Intervals stored: {}", s.intervals));
+
+    if !top.is_empty() {
+        lines.push("\nMost expensive intervals:".into());
+        for (time, price) in top {
+            let when = timezone::to_local(time, tz).map(|t| t.format("%a %d %b %H:%M").to_string()).unwrap_or_else(|| time.clone());
+            lines.push(format!("  {}  ${:.2}", when, price));
+        }
+    }
+
+    if let Some((grouping, buckets)) = buckets {
+        lines.push(match grouping {
+            Grouping::Day => "\nBy day (min ~ max, mean):".to_string(),
+            Grouping::HourOfDay => format!("\nBy hour of day, {} (min ~ max, mean):", timezone::tz_abbrev(tz)),
+        });
+        for b in buckets {
+            let label = match grouping {
+                Grouping::Day => chrono::NaiveDate::parse_from_str(&b.key, "%Y-%m-%d")
+                    .map(|d| d.format("%a %d %b").to_string())
+                    .unwrap_or_else(|_| b.key.clone()),
+                Grouping::HourOfDay => format!("{}:00", b.key),
+            };
+            let negative = if b.negative_intervals > 0 {
+                format!("  \u{1f7e2} {:.0}% negative", b.negative_intervals as f64 * 100.0 / b.intervals as f64)
+            } else {
+                String::new()
+            };
+            lines.push(format!("  {}  ${:.0} ~ ${:.0}, ${:.0}{}", label, b.min_price, b.max_price, b.mean_price, negative));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Everything that goes into one user's daily summary for a region.
+pub struct DailySummary<'a> {
+    pub region: &'a str,
+    pub date_display: &'a str,
+    pub stats: Option<&'a crate::db::repository::DailyStats>,
+    pub peak_time: Option<&'a str>,
+    pub weather: Option<&'a crate::data::weather::WeatherForecast>,
+    /// Tomorrow's solar potential: from the rooftop PV forecast, else the weather icon.
+    pub solar: Option<SolarPotential>,
+    /// Peak rooftop PV half-hour today (actual) and tomorrow (forecast), as (time, MW).
+    pub pv_today: Option<(&'a str, f64)>,
+    pub pv_tomorrow: Option<(&'a str, f64)>,
+    pub alerts_today: i64,
+    pub plan: Option<&'a Plan>,
+    pub savings: Option<&'a Savings>,
+    /// Rolling 7-day score for forecasts made 1–4 hours ahead.
+    pub accuracy: Option<&'a ForecastScore>,
+    pub tz: Tz,
+}
+
+pub fn format_daily_summary(summary: &DailySummary) -> String {
+    let DailySummary {
+        region, date_display, stats, peak_time, weather, solar, pv_today, pv_tomorrow, alerts_today, plan, savings,
+        accuracy, tz,
+    } = *summary;
     let mut lines = vec![format!(
         "\u{1f4ca} Daily Summary \u{2014} {} \u{2014} {}\n",
         region_display(region), date_display
@@ -181,8 +932,24 @@ pub fn format_daily_summary(
         if s.negative_hours > 0.0 {
             lines.push(format!("Negative price hours: {:.1}h", s.negative_hours));
         }
+        if let Some((time, mw)) = pv_today {
+            let why = if s.negative_hours > 0.0 { " \u{2014} surplus midday solar is what pushed prices negative" } else { "" };
+            lines.push(format!(
+                "Rooftop solar peak: {:.0} MW at {} {}{}",
+                mw, timezone::format_local_time(time, tz), timezone::tz_abbrev_at(time, tz), why
+            ));
+        }
         if let Some(pt) = peak_time {
-            lines.push(format!("Peak: ${:.0}/MWh at {} AEST", s.max_price, format_time_short(pt)));
+            lines.push(format!(
+                "Peak: ${:.0}/MWh at {} {}",
+                s.max_price, timezone::format_local_time(pt, tz), timezone::tz_abbrev_at(pt, tz)
+            ));
+        }
+        if let Some(d) = s.peak_demand {
+            lines.push(format!("Peak demand: {:.0} MW", d));
+        }
+        if let Some(m) = s.min_margin {
+            lines.push(format!("Tightest supply margin: {:.0} MW", m));
         }
     } else {
         lines.push("No price data recorded today.".into());
@@ -190,37 +957,43 @@ pub fn format_daily_summary(
 
     lines.push(format!("\nAlerts sent today: {}", alerts_today));
 
-    // Tomorrow's weather outlook
-    if let Some(w) = weather {
-        let temp_str = match w.temp_max {
-            Some(t) => format!(", {:.0}\u{00b0}C", t),
+    // Tomorrow's outlook: weather plus solar potential
+    if let Some(solar) = solar {
+        let weather_str = match weather {
+            Some(w) => match w.temp_max {
+                Some(t) => format!("{}, {:.0}\u{00b0}C \u{2014} ", w.description, t),
+                None => format!("{} \u{2014} ", w.description),
+            },
             None => String::new(),
         };
-        lines.push(format!(
-            "\nTomorrow's outlook:\n{} {}{} \u{2014} {}",
-            w.solar.emoji(), w.description, temp_str, w.solar.label()
-        ));
+        lines.push(format!("\nTomorrow's outlook:\n{} {}{}", solar.emoji(), weather_str, solar.label()));
+        if let Some((time, mw)) = pv_tomorrow {
+            lines.push(format!(
+                "Rooftop solar forecast: {:.0} MW peak around {} {}",
+                mw, timezone::format_local_time(time, tz), timezone::tz_abbrev_at(time, tz)
+            ));
+        }
         // Strategy based on solar potential
-        lines.push(match &w.solar {
-            crate::data::weather::SolarPotential::Excellent | crate::data::weather::SolarPotential::Good => {
+        lines.push(match solar {
+            SolarPotential::Excellent | SolarPotential::Good => {
                 "\u{1f50b} Likely negative prices midday\n\
                  \u{2022} Morning: Let solar charge battery\n\
                  \u{2022} Midday: Charge from grid (negative prices)\n\
                  \u{2022} Evening: Discharge during peak".into()
             }
-            crate::data::weather::SolarPotential::Moderate => {
+            SolarPotential::Moderate => {
                 "\u{26c5} Some solar generation expected\n\
                  \u{2022} Midday prices may dip but unlikely negative\n\
                  \u{2022} Evening: Discharge during peak if prices rise".into()
             }
-            crate::data::weather::SolarPotential::Poor => {
+            SolarPotential::Poor => {
                 "\u{1f327}\u{fe0f} Low solar generation expected\n\
                  \u{2022} Prices unlikely to go negative\n\
                  \u{2022} Conserve battery for evening peak".into()
             }
         });
         // Heat warning
-        if let Some(t) = w.temp_max {
+        if let Some(t) = weather.and_then(|w| w.temp_max) {
             if t >= 35.0 {
                 lines.push("\u{26a1} Extreme heat \u{2014} expect high evening demand and prices".into());
             } else if t >= 30.0 {
@@ -229,30 +1002,202 @@ pub fn format_daily_summary(
         }
     }
 
+    if let Some(p) = plan {
+        let steps = plan_lines(p, tz);
+        if !steps.is_empty() {
+            lines.push("\n\u{1f50b} Tomorrow's battery plan:".into());
+            lines.extend(steps);
+            lines.push(format!("Estimated value: ${:.2}", p.value));
+        }
+    }
+
+    if let Some(s) = savings {
+        lines.push(format!("\n\u{1f4b0} Savings (last {} days): ${:+.2} vs flat rate", s.days, s.saved()));
+    }
+
+    if let Some(a) = accuracy.filter(|a| a.samples > 0) {
+        lines.push(format!(
+            "\n\u{1f3af} Forecast accuracy (7 days, 1\u{2013}4h ahead): off by ${:.0}/MWh on average, {}",
+            a.mae(), bias_str(a.bias())
+        ));
+    }
+
     lines.push("\nPowered by AEMO + BOM data | /help for commands".into());
     lines.join("\n")
 }
 
+/// Market-time span of a gap, e.g. "01 Jan 10:05 – 10:40 AEST".
+fn gap_range(gap: &DispatchGap) -> String {
+    match (timezone::parse_market(&gap.gap_start), timezone::parse_market(&gap.gap_end)) {
+        (Some(start), Some(end)) if start.date_naive() == end.date_naive() => {
+            format!("{} \u{2013} {} AEST", start.format("%d %b %H:%M"), end.format("%H:%M"))
+        }
+        (Some(start), Some(end)) => {
+            format!("{} \u{2013} {} AEST", start.format("%d %b %H:%M"), end.format("%d %b %H:%M"))
+        }
+        _ => format!("{} \u{2013} {}", gap.gap_start, gap.gap_end),
+    }
+}
+
+/// The backfill command that recovers a gap. Archive days run from 04:05 to
+/// 04:00, so the day before the gap is included.
+fn backfill_hint(gap: &DispatchGap) -> String {
+    let day = |t: &str| timezone::parse_market(t).map(|d| d.date_naive()).unwrap_or_default();
+    let from = day(&gap.gap_start).pred_opt().unwrap_or_default();
+    format!("nem-price-bot backfill {} {}", from, day(&gap.gap_end))
+}
+
+pub fn format_gap_alert(gaps: &[DispatchGap]) -> String {
+    let mut lines = vec!["\u{26a0}\u{fe0f} Missed dispatch intervals\n".to_string()];
+    for g in gaps {
+        lines.push(format!(
+            "{}: {} ({} interval{})",
+            g.region, gap_range(g), g.intervals, if g.intervals == 1 { "" } else { "s" }
+        ));
+    }
+    if let Some(first) = gaps.first() {
+        lines.push(format!(
+            "\nThey are no longer on NEMweb's Current listing. Once archived (next day), recover with:\n{}",
+            backfill_hint(first)
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_gaps_response(gaps: &[DispatchGap]) -> String {
+    if gaps.is_empty() {
+        return "\u{2705} No dispatch gaps recorded in the last 90 days.".into();
+    }
+    let mut lines = vec!["\u{1f4c9} Recent dispatch gaps\n".to_string()];
+    for g in gaps {
+        let status = match g.still_missing {
+            n if n <= 0 => "recovered".to_string(),
+            n if n == g.intervals => format!("{} missing", n),
+            n => format!("{} of {} missing", n, g.intervals),
+        };
+        lines.push(format!("{}: {} \u{2014} {}", g.region, gap_range(g), status));
+        if g.still_missing > 0 {
+            lines.push(format!("  \u{2192} {}", backfill_hint(g)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Alerts held back during quiet hours, oldest first.
+pub fn format_digest(alerts: &[PendingAlert]) -> String {
+    let mut parts = vec![format!(
+        "\u{1f305} While you were in quiet hours ({} alert{}, latest of each kind):",
+        alerts.len(), if alerts.len() == 1 { "" } else { "s" }
+    )];
+    parts.extend(alerts.iter().map(|a| a.text.clone()));
+    parts.join("\n\n\u{2014}\u{2014}\u{2014}\n\n")
+}
+
+pub fn format_quiet_settings(user: &User, muted: &[String]) -> String {
+    let hours = match (&user.quiet_start, &user.quiet_end) {
+        (Some(start), Some(end)) => format!("{}\u{2013}{} ({})", start, end, timezone::tz_abbrev(user.tz())),
+        _ => "Off".to_string(),
+    };
+    let muted_labels: Vec<&str> = analyzer::mutable_alerts()
+        .filter(|(key, _)| muted.iter().any(|m| m == key))
+        .map(|(_, label)| label)
+        .collect();
+    format!(
+        "\u{1f319} Quiet hours: {}\n\
+         During quiet hours: {}\n\
+         Muted alerts: {}\n\n\
+         Usage:\n\
+         /quiet 22:00 07:00 \u{2014} Set quiet hours (your local time)\n\
+         /quiet off \u{2014} Turn quiet hours off\n\n\
+         Tap below to mute an alert type or switch between dropping alerts and a morning digest.",
+        hours,
+        if user.quiet_digest { "queue for a digest when they end" } else { "drop alerts" },
+        if muted_labels.is_empty() { "None".to_string() } else { muted_labels.join(", ") },
+    )
+}
+
+/// "all alerts", "none" or "High price, Spike" for a routing list.
+fn alert_types_display(types: Option<&[String]>) -> String {
+    match types {
+        None => "all alerts".to_string(),
+        Some([]) => "none".to_string(),
+        Some(types) => ALERT_TYPES
+            .iter()
+            .filter(|(key, _, _)| types.iter().any(|t| t == key))
+            .map(|(_, label, _)| *label)
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+pub fn format_notify_channels(telegram: Option<&[String]>, channels: &[NotifyChannel], email: bool) -> String {
+    let mut lines = vec![
+        "\u{1f4e8} Alert destinations:".to_string(),
+        format!("\u{2022} telegram \u{2014} this chat: {}", alert_types_display(telegram)),
+    ];
+    for ch in channels {
+        lines.push(format!(
+            "\u{2022} #{} {} \u{2014} {}: {}",
+            ch.id, ch.kind, ch.target, alert_types_display(ch.alert_types.as_deref())
+        ));
+        if let Some(err) = &ch.last_error {
+            lines.push(format!("   \u{26a0}\u{fe0f} Last delivery failed: {}", err));
+        }
+    }
+    let keys: Vec<&str> = ALERT_TYPES.iter().map(|(key, _, _)| *key).collect();
+    format!(
+        "{}\n\n\
+         Usage:\n\
+         /notify webhook <url> [secret] \u{2014} Signed JSON POST\n\
+         {}\
+         /notify ntfy <topic url> [token] \u{2014} ntfy push\n\
+         /notify gotify <server url> <app token> \u{2014} Gotify push\n\
+         /notify types <id|telegram> high_price,spike \u{2014} Choose alerts (all, none)\n\
+         /notify test <id|telegram> \u{2014} Send a test alert\n\
+         /notify remove <id>\n\n\
+         Alert types: {}\n\
+         Quiet hours only apply to this chat.",
+        lines.join("\n"),
+        if email { "/notify email <address> \u{2014} Email\n" } else { "" },
+        keys.join(", "),
+    )
+}
+
 pub fn welcome_message() -> &'static str {
     "Welcome to NEM Price Bot! \u{26a1}\n\n\
      I'll send you real-time electricity price alerts so you know\n\
      when to charge and discharge your home battery.\n\n\
-     Select your NEM region:"
+     Select your NEM region (you can follow more than one):"
 }
 
-pub fn confirm_region(region: &str, high_alert: f64, low_alert: f64) -> String {
+/// `users` are the regions followed, home first.
+pub fn confirm_regions(users: &[User]) -> String {
+    let names: Vec<String> = users
+        .iter()
+        .map(|u| {
+            let name = region_display(&u.region);
+            if u.is_home() && users.len() > 1 { format!("{} (home)", name) } else { name.to_string() }
+        })
+        .collect();
+    let thresholds: Vec<String> = users
+        .iter()
+        .map(|u| format!(
+            "\u{2022} {}: above ${:.0}/MWh, below ${:.0}/MWh",
+            region_display(&u.region), u.high_alert, u.low_alert
+        ))
+        .collect();
     format!(
-        "\u{2705} You're set up for {}.\n\n\
-         Current alerts:\n\
-         \u{2022} High price: ${:.0}/MWh (notify when price goes above)\n\
-         \u{2022} Low price: ${:.0}/MWh (notify when price drops below)\n\n\
+        "\u{2705} You're following {}.\n\n\
+         Price alerts (notify when price goes above / drops below):\n{}\n\n\
+         Tap a region to follow or unfollow it. Your home region sets your tariff, \
+         battery plan and timezone.\n\n\
          Commands:\n\
          /price \u{2014} Current spot price\n\
          /forecast \u{2014} Next few hours outlook\n\
          /alert \u{2014} Customise alert thresholds\n\
          /status \u{2014} View your settings\n\
          /help \u{2014} All commands",
-        region_display(region), high_alert, low_alert
+        names.join(", "), thresholds.join("\n")
     )
 }
 
@@ -260,15 +1205,38 @@ pub fn help_message() -> &'static str {
     "NEM Price Bot \u{2014} Help \u{26a1}\n\n\
      \u{1f4ca} Check prices:\n\
      /price \u{2014} Current spot price for your region\n\
-     /forecast \u{2014} Price forecast for next 4\u{2013}6 hours\n\n\
+     /price fcas \u{2014} Current FCAS prices (for VPP members)\n\
+     /mix \u{2014} Generation by fuel, renewable share and emissions\n\
+     /flows \u{2014} Interconnector flows and which are at their limit\n\
+     /notices \u{2014} AEMO market notices (LOR, directions) and alert opt-in\n\
+     /forecast \u{2014} Price forecast for next 4\u{2013}6 hours\n\
+     /forecast changes \u{2014} How the forecast has shifted recently\n\
+     /chart 48 \u{2014} Price chart for the last 48 hours and the forecast ahead\n\
+     /accuracy \u{2014} How well forecasts matched actual prices\n\
+     /history 7d \u{2014} Price statistics for a range (or yesterday, 2026-01-01 2026-01-31)\n\
+     /export 7d \u{2014} Download prices and forecasts as CSV\n\n\
      \u{1f514} Manage alerts:\n\
      /alert high 200 \u{2014} Notify above $200/MWh\n\
      /alert low -20 \u{2014} Notify below -$20/MWh\n\
+     /alert high 300 SA \u{2014} Threshold for another region you follow\n\
+     /alert high 45c \u{2014} Notify above 45c/kWh retail (needs /tariff)\n\
+     /alert fcas raise6sec 20 \u{2014} Notify when an FCAS price exceeds $20/MWh\n\
+     /alert carbon low 200 \u{2014} Notify when the grid is below 200 g CO\u{2082}/kWh\n\
+     /alert band 20 \u{2014} Hysteresis so alerts don't flap at the threshold\n\
      /alert off \u{2014} Pause notifications\n\
-     /alert on \u{2014} Resume notifications\n\n\
+     /alert on \u{2014} Resume notifications\n\
+     /quiet \u{2014} Quiet hours and muting alert types\n\
+     /notify \u{2014} Also send alerts to webhooks, email, ntfy or Gotify\n\n\
+     \u{1f50b} Battery:\n\
+     /battery 13.5 5 90 10 \u{2014} Set kWh, kW, efficiency %, reserve %\n\
+     /plan \u{2014} Charge/discharge plan for the next 24 hours\n\
+     /plan 80 \u{2014} Plan starting from 80% charge\n\
+     /savings \u{2014} Estimated earnings from following alerts\n\n\
      \u{2699}\u{fe0f} Settings:\n\
      /status \u{2014} View current settings\n\
-     /region \u{2014} Change your NEM region\n\n\
+     /region \u{2014} Follow or unfollow NEM regions\n\
+     /tariff \u{2014} Pick your network tariff for retail c/kWh prices\n\
+     /timezone \u{2014} Show times in a different timezone\n\n\
      \u{2139}\u{fe0f} About:\n\
      /about \u{2014} What is this bot and where does the data come from\n\n\
      Data source: AEMO (aemo.com.au)\n\
@@ -285,7 +1253,8 @@ pub fn about_message() -> &'static str {
      Wholesale spot prices from AEMO's NEM dispatch system\n\
      (nemweb.com.au). Updated every 5 minutes.\n\n\
      \u{1f512} Privacy:\n\
-     We only store your Telegram chat ID and region selection.\n\
+     We only store your Telegram chat ID and region selection,\n\
+     plus any alert destinations you add with /notify.\n\
      No personal information is collected.\n\n\
      \u{26a0}\u{fe0f} Disclaimer:\n\
      This service provides wholesale market data for\n\
@@ -295,3 +1264,25 @@ pub fn about_message() -> &'static str {
      at your own discretion and risk.\n\n\
      Built with \u{1f980} Rust"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::optimiser::PlanStep;
+
+    #[test]
+    fn plan_names_the_zone_in_force_at_its_times() {
+        let battery = BatteryProfile { capacity_kwh: 10.0, power_kw: 5.0, efficiency: 0.9, reserve: 0.1 };
+        let plan = |times: &[&str]| Plan {
+            steps: times
+                .iter()
+                .map(|t| PlanStep { time: t.to_string(), price: 80.0, action: Action::Idle, grid_kwh: 0.0, soc_kwh: 5.0 })
+                .collect(),
+            value: 0.0,
+        };
+        let text = |times: &[&str]| format_plan_response("NSW1", &battery, &plan(times), 0.5, chrono_tz::Australia::Sydney);
+        assert!(text(&["2026/01/15 10:00:00", "2026/01/15 10:30:00"]).contains("Times in AEDT."));
+        // Daylight saving ends at 02:00 AEST on 5 April 2026
+        assert!(text(&["2026/04/05 01:30:00", "2026/04/05 02:30:00"]).contains("Times in AEDT, then AEST."));
+    }
+}
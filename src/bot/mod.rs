@@ -1,4 +1,7 @@
 pub mod callbacks;
+pub mod chart;
+pub mod channels;
 pub mod commands;
 pub mod messages;
+pub mod mqtt;
 pub mod notifier;
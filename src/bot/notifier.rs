@@ -1,27 +1,317 @@
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use std::collections::HashMap;
 use std::sync::Arc;
 use teloxide::prelude::*;
+use tokio::task::JoinSet;
+use crate::bot::mqtt::Mqtt;
+use crate::bot::{channels, messages};
+use crate::config::Config;
+use crate::db::repository::{NotifyChannel, User};
 use crate::db::Db;
 use crate::engine::analyzer::PendingAlert;
 
-pub async fn send_alerts(bot: &Bot, db: &Arc<Db>, alerts: Vec<PendingAlert>) {
+/// Somewhere an alert can be delivered: the user's Telegram chat, or one of the
+/// channels in `bot::channels`.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, alert: &PendingAlert) -> anyhow::Result<()>;
+}
+
+pub struct Telegram {
+    bot: Bot,
+}
+
+#[async_trait]
+impl Notifier for Telegram {
+    async fn send(&self, alert: &PendingAlert) -> anyhow::Result<()> {
+        self.bot.send_message(ChatId(alert.chat_id), &alert.text).await?;
+        Ok(())
+    }
+}
+
+/// The bot plus the shared clients the other channels are built from, and the MQTT
+/// publisher when one is configured.
+pub struct Delivery {
+    pub bot: Bot,
+    pub mqtt: Option<Mqtt>,
+    client: reqwest::Client,
+    smtp: Option<Arc<channels::Smtp>>,
+    allow_http: bool,
+}
+
+impl Delivery {
+    pub fn new(bot: Bot, cfg: &Config) -> anyhow::Result<Self> {
+        // No redirects, so a checked URL can't bounce requests on to a private address
+        let mut client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none());
+        if !cfg.notify_allow_http {
+            client = client.dns_resolver(Arc::new(channels::PublicResolver));
+        }
+        let client = client.build()?;
+        let smtp = match (&cfg.smtp_url, &cfg.smtp_from) {
+            (Some(url), Some(from)) => Some(Arc::new(channels::Smtp::new(url, from)?)),
+            (Some(_), None) => bail!("SMTP_FROM must be set along with SMTP_URL"),
+            _ => None,
+        };
+        Ok(Self { bot, mqtt: Mqtt::start(cfg)?, client, smtp, allow_http: cfg.notify_allow_http })
+    }
+
+    pub fn has_email(&self) -> bool {
+        self.smtp.is_some()
+    }
+
+    pub fn telegram(&self) -> Telegram {
+        Telegram { bot: self.bot.clone() }
+    }
+
+    /// The notifier for one of a user's stored channels.
+    pub fn channel(&self, ch: &NotifyChannel) -> anyhow::Result<Box<dyn Notifier>> {
+        let client = self.client.clone();
+        let (url, token) = (ch.target.clone(), ch.secret.clone());
+        if ch.kind != "email" {
+            channels::check_literal(&url, self.allow_http)?;
+        }
+        Ok(match ch.kind.as_str() {
+            "webhook" => Box::new(channels::Webhook { client, url, secret: token.unwrap_or_default() }),
+            "email" => {
+                let smtp = self.smtp.clone().context("Email alerts aren't set up on this server")?;
+                Box::new(channels::Email::new(smtp, &ch.target)?)
+            }
+            "ntfy" => Box::new(channels::Ntfy { client, url, token }),
+            "gotify" => Box::new(channels::Gotify { client, url, token: token.unwrap_or_default() }),
+            kind => bail!("Unknown channel kind {kind}"),
+        })
+    }
+}
+
+/// Whether an alert with routing key `key` goes to a destination taking `types` (None: all).
+pub fn routes_to(types: Option<&[String]>, key: &str) -> bool {
+    types.is_none_or(|t| t.iter().any(|k| k == key))
+}
+
+/// Where one user's alerts go, looked up once per batch.
+struct Routes {
+    telegram: Option<Vec<String>>,
+    channels: Vec<NotifyChannel>,
+}
+
+pub async fn send_alerts(delivery: &Delivery, db: &Arc<Db>, alerts: Vec<PendingAlert>) {
+    let now = chrono::Utc::now();
+    let telegram = delivery.telegram();
+    let mut users: HashMap<i64, Option<User>> = HashMap::new();
+    let mut routes: HashMap<i64, Routes> = HashMap::new();
     for alert in alerts {
         // Rate limit: max 10/hour per user
         if db.count_alerts_this_hour(alert.chat_id).unwrap_or(10) >= 10 {
+            let _ = db.log_dropped_alert(alert.chat_id, &alert.alert_type, alert.price, &alert.region);
             continue;
         }
+        let routes = routes.entry(alert.chat_id).or_insert_with(|| Routes {
+            telegram: db.get_telegram_alerts(alert.chat_id).unwrap_or_default(),
+            channels: db.get_notify_channels(alert.chat_id).unwrap_or_default(),
+        });
+        // Sent, or queued for the digest: either way it's logged below, for dedup and the rate limit
+        let mut handled = false;
 
-        match bot.send_message(ChatId(alert.chat_id), &alert.text).await {
-            Ok(_) => {
-                let _ = db.log_alert(alert.chat_id, &alert.alert_type, alert.price, &alert.region);
+        if routes_to(routes.telegram.as_deref(), alert.route_key()) {
+            // Quiet hours: hold for the digest or drop, as the user chose
+            let user = users.entry(alert.chat_id).or_insert_with(|| db.get_user(alert.chat_id).ok().flatten());
+            if let Some(user) = user.as_ref().filter(|u| u.in_quiet_hours(now)) {
+                if user.quiet_digest {
+                    match db.queue_digest(&alert) {
+                        Ok(()) => handled = true,
+                        Err(e) => tracing::error!(chat_id = alert.chat_id, error = %e, "Failed to queue alert for digest"),
+                    }
+                }
+            } else {
+                match telegram.send(&alert).await {
+                    Ok(()) => handled = true,
+                    Err(e) => {
+                        tracing::error!(chat_id = alert.chat_id, error = %e, "Failed to send alert");
+                        if e.to_string().contains("Forbidden") {
+                            let _ = db.set_active(alert.chat_id, false);
+                        }
+                    }
+                }
+                // Basic throttle: avoid hitting Telegram rate limits
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
             }
-            Err(e) => {
-                tracing::error!(chat_id = alert.chat_id, error = %e, "Failed to send alert");
-                if e.to_string().contains("Forbidden") {
-                    let _ = db.set_active(alert.chat_id, false);
+        }
+        // Other channels ignore quiet hours: they're often automations, and push apps
+        // have their own do-not-disturb
+        let mut targets = Vec::new();
+        for ch in routes.channels.iter().filter(|c| routes_to(c.alert_types.as_deref(), alert.route_key())) {
+            match delivery.channel(ch) {
+                Ok(notifier) => targets.push((ch.id, ch.kind.clone(), ch.last_error.is_some(), notifier)),
+                Err(e) => {
+                    tracing::warn!(chat_id = alert.chat_id, channel = ch.id, kind = %ch.kind, error = %e, "Channel delivery failed");
+                    let _ = db.set_channel_error(ch.id, Some(&e.to_string()));
+                }
+            }
+        }
+        if !handled && targets.is_empty() {
+            let _ = db.log_dropped_alert(alert.chat_id, &alert.alert_type, alert.price, &alert.region);
+            continue;
+        }
+        // Logged before any channel answers, so the rest of this batch and the next tick
+        // count it for dedup and the rate limit however slow delivery is
+        let logged = db.log_alert(alert.chat_id, &alert.alert_type, alert.price, &alert.region);
+        if !targets.is_empty() {
+            // Only an alert no other destination took is marked dropped if the channels fail
+            let fallback = if handled { None } else { logged.ok() };
+            tokio::spawn(deliver_to_channels(db.clone(), Arc::new(alert), targets, fallback));
+        }
+    }
+}
+
+/// Send one alert to a user's other destinations at once, off the Telegram send path so
+/// a slow or dead endpoint only delays itself. `log_id` is the alert's log entry when
+/// only these channels carry it, marked dropped if none of them takes it.
+async fn deliver_to_channels(
+    db: Arc<Db>,
+    alert: Arc<PendingAlert>,
+    targets: Vec<(i64, String, bool, Box<dyn Notifier>)>,
+    log_id: Option<i64>,
+) {
+    let mut sends = JoinSet::new();
+    for (id, kind, had_error, notifier) in targets {
+        let alert = alert.clone();
+        sends.spawn(async move { (id, kind, had_error, notifier.send(&alert).await) });
+    }
+    let mut delivered = false;
+    while let Some(joined) = sends.join_next().await {
+        let Ok((id, kind, had_error, result)) = joined else { continue };
+        match result {
+            Ok(()) => {
+                delivered = true;
+                if had_error {
+                    let _ = db.set_channel_error(id, None);
                 }
             }
+            Err(e) => {
+                tracing::warn!(chat_id = alert.chat_id, channel = id, kind = %kind, error = %e, "Channel delivery failed");
+                let _ = db.set_channel_error(id, Some(&e.to_string()));
+            }
+        }
+    }
+    if let Some(id) = log_id.filter(|_| !delivered) {
+        let _ = db.mark_alert_dropped(id);
+    }
+}
+
+/// Send queued alerts to users whose quiet hours have ended, as one message each. They
+/// were logged when queued, so they aren't logged again here.
+pub async fn send_digests(bot: &Bot, db: &Arc<Db>) {
+    let now = chrono::Utc::now();
+    for chat_id in db.get_digest_chat_ids().unwrap_or_default() {
+        let Ok(Some(user)) = db.get_user(chat_id) else { continue };
+        if user.in_quiet_hours(now) {
+            continue;
+        }
+        let alerts = match db.take_digest(chat_id) {
+            Ok(a) if !a.is_empty() => a,
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::error!(chat_id, error = %e, "Failed to read alert digest");
+                continue;
+            }
+        };
+        if let Err(e) = bot.send_message(ChatId(chat_id), messages::format_digest(&alerts)).await {
+            tracing::error!(chat_id, error = %e, "Failed to send alert digest");
         }
-        // Basic throttle: avoid hitting Telegram rate limits
         tokio::time::sleep(std::time::Duration::from_millis(50)).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository::tests::db_following;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A local endpoint answering every request with 200, and how many it has had.
+    async fn counting_stub() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let count = Arc::new(AtomicUsize::new(0));
+        let seen = count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                seen.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await;
+            }
+        });
+        (url, count)
+    }
+
+    fn spike(interval_time: &str, price: f64) -> PendingAlert {
+        PendingAlert {
+            chat_id: 1,
+            alert_type: "spike".into(),
+            region: "NSW1".into(),
+            price,
+            interval_time: interval_time.into(),
+            threshold: None,
+            value: None,
+            text: format!("Spike to ${price}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn channel_only_alerts_count_towards_the_limit_at_once() {
+        let db = Arc::new(db_following(&["NSW1"]));
+        db.set_telegram_alerts(1, Some(&[])).unwrap();
+        let (url, requests) = counting_stub().await;
+        db.add_notify_channel(1, "webhook", &url, Some("s3cret")).unwrap();
+        for _ in 0..9 {
+            db.log_alert(1, "high_price", 400.0, "NSW1").unwrap();
+        }
+        let delivery =
+            Delivery { bot: Bot::new("0:test"), mqtt: None, client: reqwest::Client::new(), smtp: None, allow_http: true };
+
+        send_alerts(&delivery, &db, vec![spike("2026/01/15 17:00:00", 900.0), spike("2026/01/15 17:05:00", 1500.0)]).await;
+        // The first is logged before its delivery finishes, so the second is over the limit
+        assert_eq!(db.count_alerts_this_hour(1).unwrap(), 10);
+
+        for _ in 0..50 {
+            if requests.load(Ordering::SeqCst) > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(db.count_alerts_this_hour(1).unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn telegram_calls_send_message() {
+        let reply = r#"{"ok":true,"result":{"message_id":7,"date":1768460400,"chat":{"id":1,"type":"private","first_name":"A"},"text":"Spike to $900"}}"#;
+        let (url, server) = channels::tests::stub_replying(200, reply).await;
+        let telegram = Telegram { bot: Bot::new("0:test").set_api_url(url.parse().unwrap()) };
+        telegram.send(&spike("2026/01/15 17:00:00", 900.0)).await.unwrap();
+        let req = server.await.unwrap();
+
+        // Bot API method names are case-insensitive; teloxide capitalises them
+        assert!(req.head.starts_with("POST /bot0:test/SendMessage "));
+        let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+        assert_eq!(body["chat_id"], 1);
+        assert_eq!(body["text"], "Spike to $900");
+    }
+
+    #[test]
+    fn routing_by_alert_type() {
+        let types = vec!["high_price".to_string(), "fcas".to_string()];
+        assert!(routes_to(None, "spike"));
+        assert!(routes_to(Some(&types), "high_price"));
+        assert!(routes_to(Some(&types), "fcas"));
+        assert!(!routes_to(Some(&types), "low_price"));
+        assert!(!routes_to(Some(&[]), "high_price"));
+    }
+}
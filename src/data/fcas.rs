@@ -0,0 +1,27 @@
+/// A frequency control ancillary service priced alongside energy in `DISPATCH,PRICE`.
+pub struct Service {
+    /// Short name used in commands and the `fcas_price` table.
+    pub key: &'static str,
+    /// RRP column in the dispatch file.
+    pub column: &'static str,
+    pub label: &'static str,
+    /// Contingency services respond to sudden events; regulation tracks minute-to-minute imbalance.
+    pub contingency: bool,
+}
+
+pub const SERVICES: &[Service] = &[
+    Service { key: "raise1sec", column: "RAISE1SECRRP", label: "Raise 1s", contingency: true },
+    Service { key: "raise6sec", column: "RAISE6SECRRP", label: "Raise 6s", contingency: true },
+    Service { key: "raise60sec", column: "RAISE60SECRRP", label: "Raise 60s", contingency: true },
+    Service { key: "raise5min", column: "RAISE5MINRRP", label: "Raise 5min", contingency: true },
+    Service { key: "raisereg", column: "RAISEREGRRP", label: "Raise reg", contingency: false },
+    Service { key: "lower1sec", column: "LOWER1SECRRP", label: "Lower 1s", contingency: true },
+    Service { key: "lower6sec", column: "LOWER6SECRRP", label: "Lower 6s", contingency: true },
+    Service { key: "lower60sec", column: "LOWER60SECRRP", label: "Lower 60s", contingency: true },
+    Service { key: "lower5min", column: "LOWER5MINRRP", label: "Lower 5min", contingency: true },
+    Service { key: "lowerreg", column: "LOWERREGRRP", label: "Lower reg", contingency: false },
+];
+
+pub fn find(key: &str) -> Option<&'static Service> {
+    SERVICES.iter().find(|s| s.key == key)
+}
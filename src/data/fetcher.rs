@@ -1,61 +1,143 @@
 use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
 use regex::Regex;
 use std::io::{Cursor, Read};
 
-use crate::data::parser::{self, ForecastRecord, PriceRecord};
+use crate::data::parser::{self, Dispatch, ForecastRun, GeneratorRecord, PriceRecord, RooftopPvRecord, ScadaRecord};
+use crate::timezone;
 
-const DISPATCH_URL: &str = "https://nemweb.com.au/Reports/Current/DispatchIS_Reports/";
-const PREDISPATCH_URL: &str = "https://nemweb.com.au/Reports/Current/PredispatchIS_Reports/";
+const DISPATCH_PATH: &str = "/Reports/Current/DispatchIS_Reports/";
+const PREDISPATCH_PATH: &str = "/Reports/Current/PredispatchIS_Reports/";
+const P5MIN_PATH: &str = "/Reports/Current/P5_Reports/";
+const DISPATCH_ARCHIVE_PATH: &str = "/Reports/Archive/DispatchIS_Reports/";
+const MMSDM_PATH: &str = "/Data_Archive/Wholesale_Electricity/MMSDM/";
+const SCADA_PATH: &str = "/Reports/Current/Dispatch_SCADA/";
+const CDEII_PATH: &str = "/Reports/Current/CDEII/";
+const MARKET_NOTICE_PATH: &str = "/Reports/Current/Market_Notice/";
+const ROOFTOP_PV_ACTUAL_PATH: &str = "/Reports/Current/ROOFTOP_PV/ACTUAL/";
+const ROOFTOP_PV_FORECAST_PATH: &str = "/Reports/Current/ROOFTOP_PV/FORECAST/";
 
-/// Download and extract the latest CSV from an AEMO directory listing.
-async fn fetch_latest_zip(client: &reqwest::Client, base_url: &str, pattern: &str) -> Result<String> {
-    let html = client.get(base_url).send().await?.text().await?;
+/// List zip files matching `pattern` in an AEMO directory, as full URLs in name order.
+async fn list_zips(client: &reqwest::Client, base: &str, path: &str, pattern: &str) -> Result<Vec<String>> {
+    let re = Regex::new(&format!(r#"(?i)href="([^"]*{pattern}[^"]*\.zip)""#))?;
+    list_files(client, base, path, &re).await
+}
+
+/// List files in an AEMO directory whose link matches `re` (group 1 is the HREF).
+async fn list_files(client: &reqwest::Client, base: &str, path: &str, re: &Regex) -> Result<Vec<String>> {
+    let dir_url = format!("{base}{path}");
+    let html = client.get(&dir_url).send().await?.error_for_status()?.text().await?;
 
     // AEMO uses uppercase HREF with full paths, e.g. HREF="/Reports/.../PUBLIC_DISPATCHIS_xxx.zip"
-    let re = Regex::new(&format!(r#"(?i)href="([^"]*{pattern}[^"]*\.zip)""#))?;
     let mut files: Vec<&str> = re
         .captures_iter(&html)
         .filter_map(|c| c.get(1).map(|m| m.as_str()))
         .collect();
     files.sort();
-    let latest = files.last().context("No files found in AEMO listing")?;
 
     // HREF may be absolute path or relative — build full URL from base domain
-    let zip_url = if latest.starts_with('/') {
-        format!("https://nemweb.com.au{latest}")
-    } else {
-        format!("{base_url}{latest}")
-    };
-    let bytes = client.get(&zip_url).send().await?.bytes().await?;
+    Ok(files
+        .into_iter()
+        .map(|f| if f.starts_with('/') { format!("{base}{f}") } else { format!("{dir_url}{f}") })
+        .collect())
+}
+
+/// Download a zip and return the text of every CSV inside it, including
+/// CSVs in nested zips (AEMO's daily archives are zips of 5-minute zips).
+async fn fetch_zip_csvs(client: &reqwest::Client, url: &str) -> Result<Vec<String>> {
+    let bytes = client.get(url).send().await?.error_for_status()?.bytes().await?;
+    let mut csvs = Vec::new();
+    extract_csvs(&bytes, &mut csvs)?;
+    Ok(csvs)
+}
+
+fn extract_csvs(bytes: &[u8], out: &mut Vec<String>) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            let mut inner = Vec::new();
+            file.read_to_end(&mut inner)?;
+            extract_csvs(&inner, out)?;
+        } else if name.ends_with(".csv") {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+            out.push(text);
+        }
+    }
+    Ok(())
+}
 
-    let cursor = Cursor::new(bytes);
-    let mut archive = zip::ZipArchive::new(cursor)?;
-    let mut file = archive.by_index(0)?;
-    let mut csv_text = String::new();
-    file.read_to_string(&mut csv_text)?;
-    Ok(csv_text)
+/// Download and extract the latest CSV from an AEMO directory listing.
+async fn fetch_latest_zip(client: &reqwest::Client, base: &str, path: &str, pattern: &str) -> Result<String> {
+    let files = list_zips(client, base, path, pattern).await?;
+    let latest = files.last().context("No files found in AEMO listing")?;
+    fetch_zip_csvs(client, latest)
+        .await?
+        .into_iter()
+        .next()
+        .context("Zip contained no CSV")
 }
 
-/// Fetch latest dispatch prices with retries.
-pub async fn fetch_dispatch(client: &reqwest::Client) -> Result<Vec<PriceRecord>> {
+/// A dispatch file in the Current directory and the interval it covers.
+pub struct DispatchFile {
+    /// Interval end in market time, taken from the file name.
+    pub interval_time: String,
+    pub url: String,
+}
+
+/// List the dispatch files currently on NEMweb, oldest first, with retries.
+/// Names look like `PUBLIC_DISPATCHIS_202501011005_0000000451234567.zip`.
+pub async fn list_dispatch(client: &reqwest::Client, base: &str) -> Result<Vec<DispatchFile>> {
+    let re = Regex::new(r"PUBLIC_DISPATCHIS_(\d{12})_")?;
     for attempt in 0..3 {
-        match fetch_latest_zip(client, DISPATCH_URL, "PUBLIC_DISPATCHIS_").await {
-            Ok(csv) => return Ok(parser::parse_dispatch(&csv)),
+        match list_zips(client, base, DISPATCH_PATH, "PUBLIC_DISPATCHIS_").await {
+            Ok(urls) => {
+                // Keyed by interval so a reissued file (later name) replaces the original
+                let mut by_interval = std::collections::BTreeMap::new();
+                for url in urls {
+                    let stamp = re.captures(&url).and_then(|c| c.get(1)).map(|m| m.as_str().to_string());
+                    if let Some(time) = stamp.and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y%m%d%H%M").ok()) {
+                        by_interval.insert(time.format(timezone::MARKET_FMT).to_string(), url);
+                    }
+                }
+                let files: Vec<DispatchFile> = by_interval
+                    .into_iter()
+                    .map(|(interval_time, url)| DispatchFile { interval_time, url })
+                    .collect();
+                if files.is_empty() {
+                    anyhow::bail!("No dispatch files found in AEMO listing");
+                }
+                return Ok(files);
+            }
             Err(e) => {
-                tracing::warn!(attempt, error=%e, "Dispatch fetch failed");
+                tracing::warn!(attempt, error=%e, "Dispatch listing failed");
                 if attempt < 2 {
                     tokio::time::sleep(std::time::Duration::from_secs(30)).await;
                 }
             }
         }
     }
-    anyhow::bail!("Failed to fetch dispatch data after 3 attempts")
+    anyhow::bail!("Failed to list dispatch data after 3 attempts")
+}
+
+/// Download and parse one dispatch file.
+pub async fn fetch_dispatch_file(client: &reqwest::Client, file: &DispatchFile) -> Result<Dispatch> {
+    let csvs = fetch_zip_csvs(client, &file.url).await?;
+    let mut out = Dispatch::default();
+    for csv in &csvs {
+        let parsed = parser::parse_dispatch(csv);
+        out.prices.extend(parsed.prices);
+        out.interconnectors.extend(parsed.interconnectors);
+    }
+    Ok(out)
 }
 
 /// Fetch latest pre-dispatch forecasts with retries.
-pub async fn fetch_predispatch(client: &reqwest::Client) -> Result<Vec<ForecastRecord>> {
+pub async fn fetch_predispatch(client: &reqwest::Client, base: &str) -> Result<ForecastRun> {
     for attempt in 0..3 {
-        match fetch_latest_zip(client, PREDISPATCH_URL, "PUBLIC_PREDISPATCHIS_").await {
+        match fetch_latest_zip(client, base, PREDISPATCH_PATH, "PUBLIC_PREDISPATCHIS_").await {
             Ok(csv) => return Ok(parser::parse_predispatch(&csv)),
             Err(e) => {
                 tracing::warn!(attempt, error=%e, "Pre-dispatch fetch failed");
@@ -67,3 +149,100 @@ pub async fn fetch_predispatch(client: &reqwest::Client) -> Result<Vec<ForecastR
     }
     anyhow::bail!("Failed to fetch pre-dispatch data after 3 attempts")
 }
+
+/// Fetch the latest 5-minute pre-dispatch (next hour) with retries.
+pub async fn fetch_p5min(client: &reqwest::Client, base: &str) -> Result<ForecastRun> {
+    for attempt in 0..3 {
+        match fetch_latest_zip(client, base, P5MIN_PATH, "PUBLIC_P5MIN_").await {
+            Ok(csv) => return Ok(parser::parse_p5min(&csv)),
+            Err(e) => {
+                tracing::warn!(attempt, error=%e, "P5MIN fetch failed");
+                if attempt < 2 {
+                    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+                }
+            }
+        }
+    }
+    anyhow::bail!("Failed to fetch P5MIN data after 3 attempts")
+}
+
+/// Fetch the latest unit outputs from Dispatch_SCADA.
+pub async fn fetch_scada(client: &reqwest::Client, base: &str) -> Result<Vec<ScadaRecord>> {
+    let csv = fetch_latest_zip(client, base, SCADA_PATH, "PUBLIC_DISPATCHSCADA_").await?;
+    Ok(parser::parse_scada(&csv))
+}
+
+/// Fetch AEMO's list of registered generating units with their fuel and emissions
+/// factor (the CDEII available-generators file, published as CSV or zipped).
+pub async fn fetch_generators(client: &reqwest::Client, base: &str) -> Result<Vec<GeneratorRecord>> {
+    let re = Regex::new(r#"(?i)href="([^"]*CO2EII_AVAILABLE_GENERATORS[^"]*\.(?:csv|zip))""#)?;
+    let files = list_files(client, base, CDEII_PATH, &re).await?;
+    let latest = files.last().context("No generator list found in AEMO listing")?;
+    let csv = if latest.to_ascii_lowercase().ends_with(".zip") {
+        fetch_zip_csvs(client, latest).await?.into_iter().next().context("Zip contained no CSV")?
+    } else {
+        client.get(latest).send().await?.error_for_status()?.text().await?
+    };
+    Ok(parser::parse_generators(&csv))
+}
+
+/// Fetch the latest half-hourly rooftop PV estimates (metered, not satellite).
+pub async fn fetch_rooftop_actual(client: &reqwest::Client, base: &str) -> Result<Vec<RooftopPvRecord>> {
+    let csv = fetch_latest_zip(client, base, ROOFTOP_PV_ACTUAL_PATH, "PUBLIC_ROOFTOP_PV_ACTUAL_MEASUREMENT_").await?;
+    Ok(parser::parse_rooftop_pv(&csv))
+}
+
+/// Fetch the latest rooftop PV forecast (about a week ahead, half-hourly).
+pub async fn fetch_rooftop_forecast(client: &reqwest::Client, base: &str) -> Result<Vec<RooftopPvRecord>> {
+    let csv = fetch_latest_zip(client, base, ROOFTOP_PV_FORECAST_PATH, "PUBLIC_ROOFTOP_PV_FORECAST_").await?;
+    Ok(parser::parse_rooftop_pv(&csv))
+}
+
+/// Fetch one day of dispatch prices from the NEMweb archive (a zip of 5-minute zips).
+pub async fn fetch_archive_day(client: &reqwest::Client, base: &str, date: NaiveDate) -> Result<Vec<PriceRecord>> {
+    let url = format!("{base}{DISPATCH_ARCHIVE_PATH}PUBLIC_DISPATCHIS_{}.zip", date.format("%Y%m%d"));
+    let csvs = fetch_zip_csvs(client, &url).await?;
+    Ok(csvs.iter().flat_map(|csv| parser::parse_dispatch(csv).prices).collect())
+}
+
+/// Fetch a month of DISPATCHPRICE from the MMSDM data archive.
+/// AEMO renamed these files in 2024, so both naming schemes are tried.
+pub async fn fetch_mmsdm_month(client: &reqwest::Client, base: &str, year: i32, month: u32) -> Result<Vec<PriceRecord>> {
+    let dir = format!(
+        "{base}{MMSDM_PATH}{year}/MMSDM_{year}_{month:02}/MMSDM_Historical_Data_SQLLoader/DATA/"
+    );
+    let names = [
+        format!("PUBLIC_ARCHIVE%23DISPATCHPRICE%23FILE01%23{year}{month:02}010000.zip"),
+        format!("PUBLIC_DVD_DISPATCHPRICE_{year}{month:02}010000.zip"),
+    ];
+    let mut last_err = None;
+    for name in &names {
+        match fetch_zip_csvs(client, &format!("{dir}{name}")).await {
+            Ok(csvs) => return Ok(csvs.iter().flat_map(|csv| parser::parse_dispatch(csv).prices).collect()),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow::anyhow!("No MMSDM file names to try")))
+}
+
+/// List market notices on NEMweb as (notice ID, URL), oldest first.
+/// Names look like `NEMITWEB1_MKTNOTICE_20250101.R119876`; the suffix is the notice ID.
+pub async fn list_market_notices(client: &reqwest::Client, base: &str) -> Result<Vec<(i64, String)>> {
+    let link_re = Regex::new(r#"(?i)href="([^"]*MKTNOTICE_\d{8}\.R\d+)""#)?;
+    let id_re = Regex::new(r"\.R(\d+)$")?;
+    let mut notices: Vec<(i64, String)> = list_files(client, base, MARKET_NOTICE_PATH, &link_re)
+        .await?
+        .into_iter()
+        .filter_map(|url| {
+            let id = id_re.captures(&url)?.get(1)?.as_str().parse().ok()?;
+            Some((id, url))
+        })
+        .collect();
+    notices.sort();
+    Ok(notices)
+}
+
+/// Download one market notice (plain text, not zipped).
+pub async fn fetch_market_notice(client: &reqwest::Client, url: &str) -> Result<String> {
+    Ok(client.get(url).send().await?.error_for_status()?.text().await?)
+}
@@ -0,0 +1,375 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::data::fetcher;
+use crate::data::parser::{GeneratorRecord, ScadaRecord};
+
+/// Fuel categories shown by `/mix`, in display order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fuel {
+    Coal,
+    Gas,
+    Hydro,
+    Wind,
+    Solar,
+    Battery,
+    Other,
+}
+
+pub const FUELS: &[Fuel] = &[Fuel::Coal, Fuel::Gas, Fuel::Hydro, Fuel::Wind, Fuel::Solar, Fuel::Battery, Fuel::Other];
+
+impl Fuel {
+    pub fn key(self) -> &'static str {
+        match self {
+            Fuel::Coal => "coal",
+            Fuel::Gas => "gas",
+            Fuel::Hydro => "hydro",
+            Fuel::Wind => "wind",
+            Fuel::Solar => "solar",
+            Fuel::Battery => "battery",
+            Fuel::Other => "other",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        FUELS.iter().copied().find(|f| f.key() == key)
+    }
+
+    /// Map a CDEII energy source ("Brown coal", "Coal seam methane", "Solar", ...) to a
+    /// fuel. Gas is checked first so coal seam and coal mine methane count as gas.
+    pub fn from_energy_source(source: &str) -> Self {
+        let s = source.to_lowercase();
+        if s.contains("gas") || s.contains("methane") {
+            Fuel::Gas
+        } else if s.contains("coal") {
+            Fuel::Coal
+        } else if s.contains("hydro") || s.contains("water") {
+            Fuel::Hydro
+        } else if s.contains("wind") {
+            Fuel::Wind
+        } else if s.contains("solar") {
+            Fuel::Solar
+        } else if s.contains("batter") {
+            Fuel::Battery
+        } else {
+            Fuel::Other
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Fuel::Coal => "Coal",
+            Fuel::Gas => "Gas",
+            Fuel::Hydro => "Hydro",
+            Fuel::Wind => "Wind",
+            Fuel::Solar => "Solar",
+            Fuel::Battery => "Battery",
+            Fuel::Other => "Other",
+        }
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Fuel::Coal => "\u{26ab}",
+            Fuel::Gas => "\u{1f525}",
+            Fuel::Hydro => "\u{1f4a7}",
+            Fuel::Wind => "\u{1f32c}\u{fe0f}",
+            Fuel::Solar => "\u{2600}\u{fe0f}",
+            Fuel::Battery => "\u{1f50b}",
+            Fuel::Other => "\u{2699}\u{fe0f}",
+        }
+    }
+
+    /// Typical tonnes CO2-e per MWh sent out, used for units listed without their own
+    /// factor. Roughly the NEM averages from the CDEII: black and brown coal together,
+    /// and gas across CCGT, OCGT and reciprocating plant.
+    pub fn default_emissions(self) -> f64 {
+        match self {
+            Fuel::Coal => 0.95,
+            Fuel::Gas => 0.55,
+            Fuel::Other => 0.7,
+            Fuel::Hydro | Fuel::Wind | Fuel::Solar | Fuel::Battery => 0.0,
+        }
+    }
+
+    /// Batteries only shift energy, so they count as neither renewable nor fossil.
+    pub fn is_renewable(self) -> bool {
+        matches!(self, Fuel::Hydro | Fuel::Wind | Fuel::Solar)
+    }
+}
+
+/// A registered generating unit.
+pub struct Unit {
+    pub region: String,
+    pub fuel: Fuel,
+    /// Tonnes CO2-e per MWh sent out.
+    pub emissions: f64,
+}
+
+/// Dispatchable unit IDs (DUIDs) mapped to region, fuel and emissions intensity.
+pub struct Registry {
+    units: HashMap<String, Unit>,
+    /// Unknown DUIDs already logged, so each is warned about once per run.
+    warned: Mutex<HashSet<String>>,
+}
+
+/// One region's output from one fuel in one interval.
+pub struct MixRecord {
+    pub region: String,
+    pub interval_time: String,
+    pub fuel: Fuel,
+    pub mw: f64,
+    /// Emissions rate in tonnes CO2-e per hour.
+    pub emissions_tph: f64,
+}
+
+/// Share of a region's generation the registry must place before intensity is trusted
+/// for carbon alerts; below it the figure says more about the registry than the grid.
+pub const MIN_COVERAGE: f64 = 0.8;
+
+const BUILTIN: &str = include_str!("../../assets/duid_registry.csv");
+
+impl Registry {
+    /// Load from `path` if given, else the built-in list of major units.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let registry = match path {
+            Some(p) => {
+                let csv = std::fs::read_to_string(p).with_context(|| format!("Failed to read DUID registry {p}"))?;
+                Self::parse(&csv)
+            }
+            None => Self::parse(BUILTIN),
+        };
+        tracing::info!(units = registry.units.len(), "Loaded DUID registry");
+        Ok(registry)
+    }
+
+    /// `duid,region,fuel,emissions_t_per_mwh` with a header row; unknown fuels count as other
+    /// and a blank emissions factor falls back to the fuel's default.
+    fn parse(csv: &str) -> Self {
+        let mut units = HashMap::new();
+        for line in csv.lines().skip(1) {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [duid, region, fuel, ..] = fields[..] else { continue };
+            if duid.is_empty() {
+                continue;
+            }
+            let fuel = Fuel::from_key(&fuel.to_lowercase()).unwrap_or(Fuel::Other);
+            let emissions = fields.get(3).and_then(|e| e.parse().ok()).unwrap_or(fuel.default_emissions());
+            units.insert(duid.to_string(), Unit { region: region.to_string(), fuel, emissions });
+        }
+        Registry { units, warned: Mutex::default() }
+    }
+
+    /// Sum unit outputs into per-region, per-fuel totals. Units not in the registry are
+    /// skipped (SCADA doesn't say which region they're in); their output shows up as the
+    /// mix's unclassified share.
+    pub fn aggregate(&self, scada: &[ScadaRecord]) -> Vec<MixRecord> {
+        let mut totals: HashMap<(&str, &str, Fuel), (f64, f64)> = HashMap::new();
+        for rec in scada {
+            let Some(unit) = self.units.get(&rec.duid) else {
+                if rec.mw.abs() >= 1.0 && self.warned.lock().unwrap().insert(rec.duid.clone()) {
+                    tracing::warn!(duid = %rec.duid, mw = rec.mw, "SCADA unit missing from the DUID registry");
+                }
+                continue;
+            };
+            let t = totals.entry((&unit.region, &rec.interval_time, unit.fuel)).or_default();
+            t.0 += rec.mw;
+            // A charging battery's negative output isn't negative emissions
+            t.1 += rec.mw.max(0.0) * unit.emissions;
+        }
+        totals
+            .into_iter()
+            .map(|((region, time, fuel), (mw, emissions_tph))| MixRecord {
+                region: region.to_string(),
+                interval_time: time.to_string(),
+                fuel,
+                mw,
+                emissions_tph,
+            })
+            .collect()
+    }
+}
+
+/// Build a registry CSV from AEMO's generator list, sorted by region and DUID. Units
+/// without an emissions factor get a blank one, which loads as the fuel's default.
+pub fn registry_csv(generators: &[GeneratorRecord]) -> String {
+    let mut rows: Vec<&GeneratorRecord> = generators.iter().collect();
+    rows.sort_by(|a, b| (&a.region, &a.duid).cmp(&(&b.region, &b.duid)));
+    let mut out = String::from("duid,region,fuel,emissions_t_per_mwh\n");
+    for g in rows {
+        let fuel = Fuel::from_energy_source(&g.energy_source);
+        let emissions = g.emissions.map(|e| format!("{e:.4}")).unwrap_or_default();
+        out.push_str(&format!("{},{},{},{}\n", g.duid, g.region, fuel.key(), emissions));
+    }
+    out
+}
+
+/// `nem-price-bot registry <out.csv>`: download the current generator list and write it
+/// in the format `DUID_REGISTRY` takes (and the built-in `assets/duid_registry.csv`).
+pub async fn write_registry(client: &reqwest::Client, base: &str, args: &[String]) -> Result<()> {
+    let path = args.first().context("Usage: nem-price-bot registry <out.csv>")?;
+    let generators = fetcher::fetch_generators(client, base).await?;
+    if generators.is_empty() {
+        anyhow::bail!("AEMO's generator list had no units");
+    }
+    std::fs::write(path, registry_csv(&generators)).with_context(|| format!("Failed to write {path}"))?;
+    tracing::info!(units = generators.len(), path, "Wrote DUID registry");
+    Ok(())
+}
+
+/// A region's generation by fuel for one interval.
+pub struct GenerationMix {
+    pub interval_time: String,
+    /// (fuel, MW, tonnes CO2-e per hour) in `FUELS` order.
+    pub by_fuel: Vec<(Fuel, f64, f64)>,
+    /// The region's own generation from REGIONSUM (demand plus net exports), when the
+    /// interval's price has been stored.
+    pub region_mw: Option<f64>,
+}
+
+impl GenerationMix {
+    /// Output of the units in the registry, leaving out batteries that are charging.
+    pub fn generation_mw(&self) -> f64 {
+        self.by_fuel.iter().map(|(_, mw, _)| mw.max(0.0)).sum()
+    }
+
+    /// Generation the registry couldn't put a fuel to: the region's total less the
+    /// listed units' output.
+    pub fn unclassified_mw(&self) -> f64 {
+        self.region_mw.map_or(0.0, |total| (total - self.generation_mw()).max(0.0))
+    }
+
+    /// Everything generated in the region, classified or not.
+    pub fn total_mw(&self) -> f64 {
+        self.generation_mw() + self.unclassified_mw()
+    }
+
+    /// Share of all generation from renewables; unclassified output counts against it.
+    pub fn renewable_pct(&self) -> f64 {
+        let total = self.total_mw();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let renewable: f64 = self.by_fuel.iter().filter(|(f, _, _)| f.is_renewable()).map(|(_, mw, _)| mw.max(0.0)).sum();
+        renewable / total * 100.0
+    }
+
+    /// Share of all generation the registry could place, 0..1.
+    pub fn coverage(&self) -> f64 {
+        let total = self.total_mw();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.generation_mw() / total
+    }
+
+    /// Carbon intensity of all the region's generation in grams CO2-e per kWh
+    /// (t/MWh × 1000). Unclassified output adds no emissions, so with a patchy
+    /// registry this is a lower bound; see `coverage`.
+    pub fn intensity(&self) -> f64 {
+        let total = self.total_mw();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.by_fuel.iter().map(|(_, _, e)| e).sum::<f64>() / total * 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: &str = "duid,region,fuel,emissions_t_per_mwh\n\
+        BW01,NSW1,coal,0.90\n\
+        TALWA1,NSW1,gas,\n\
+        BOCORWF1,NSW1,wind,0\n\
+        HPR1,SA1,Battery,\n";
+
+    fn scada(duid: &str, mw: f64) -> ScadaRecord {
+        ScadaRecord { duid: duid.into(), interval_time: "2026/01/15 17:30:00".into(), mw }
+    }
+
+    #[test]
+    fn energy_sources() {
+        assert_eq!(Fuel::from_energy_source("Black coal"), Fuel::Coal);
+        assert_eq!(Fuel::from_energy_source("Brown coal"), Fuel::Coal);
+        assert_eq!(Fuel::from_energy_source("Coal seam methane"), Fuel::Gas);
+        assert_eq!(Fuel::from_energy_source("Natural Gas (Pipeline)"), Fuel::Gas);
+        assert_eq!(Fuel::from_energy_source("Hydro"), Fuel::Hydro);
+        assert_eq!(Fuel::from_energy_source("Solar"), Fuel::Solar);
+        assert_eq!(Fuel::from_energy_source("Battery Storage"), Fuel::Battery);
+        assert_eq!(Fuel::from_energy_source("Diesel oil"), Fuel::Other);
+    }
+
+    #[test]
+    fn parse_fills_default_emissions() {
+        let registry = Registry::parse(REGISTRY);
+        assert_eq!(registry.units.len(), 4);
+        assert_eq!(registry.units["BW01"].emissions, 0.90);
+        assert_eq!(registry.units["TALWA1"].emissions, Fuel::Gas.default_emissions());
+        assert_eq!(registry.units["HPR1"].fuel, Fuel::Battery);
+    }
+
+    #[test]
+    fn generated_csv_loads_back() {
+        let generators = vec![
+            GeneratorRecord { duid: "LYA1".into(), region: "VIC1".into(), energy_source: "Brown coal".into(), emissions: Some(1.2) },
+            GeneratorRecord { duid: "BW01".into(), region: "NSW1".into(), energy_source: "Black coal".into(), emissions: Some(0.9) },
+            GeneratorRecord { duid: "ARWF1".into(), region: "VIC1".into(), energy_source: "Wind".into(), emissions: None },
+        ];
+        let csv = registry_csv(&generators);
+        assert!(csv.starts_with("duid,region,fuel,emissions_t_per_mwh\nBW01,NSW1,coal,0.9000\nARWF1,VIC1,wind,\n"));
+        let registry = Registry::parse(&csv);
+        assert_eq!(registry.units.len(), 3);
+        assert_eq!(registry.units["LYA1"].emissions, 1.2);
+        assert_eq!(registry.units["ARWF1"].emissions, 0.0);
+    }
+
+    #[test]
+    fn aggregate_skips_and_remembers_unknown_units() {
+        let registry = Registry::parse(REGISTRY);
+        let mix = registry.aggregate(&[scada("BW01", 600.0), scada("TALWA1", 100.0), scada("NEWSF1", 50.0), scada("NEWSF1", 40.0)]);
+        assert_eq!(mix.len(), 2);
+        let coal = mix.iter().find(|m| m.fuel == Fuel::Coal).unwrap();
+        assert_eq!(coal.emissions_tph, 540.0);
+        assert_eq!(registry.warned.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unclassified_output_completes_the_mix() {
+        let mix = GenerationMix {
+            interval_time: "2026/01/15 17:30:00".into(),
+            by_fuel: vec![(Fuel::Coal, 600.0, 540.0), (Fuel::Wind, 200.0, 0.0), (Fuel::Battery, -50.0, 0.0)],
+            region_mw: Some(1000.0),
+        };
+        assert_eq!(mix.generation_mw(), 800.0);
+        assert_eq!(mix.unclassified_mw(), 200.0);
+        assert_eq!(mix.total_mw(), 1000.0);
+        assert_eq!(mix.renewable_pct(), 20.0);
+        assert_eq!(mix.coverage(), 0.8);
+        // Over everything generated, not just the listed units
+        assert_eq!(mix.intensity(), 540.0);
+
+        // Without a REGIONSUM total, the listed units are all there is
+        let mix = GenerationMix { region_mw: None, ..mix };
+        assert_eq!(mix.unclassified_mw(), 0.0);
+        assert_eq!(mix.renewable_pct(), 25.0);
+        assert_eq!(mix.coverage(), 1.0);
+        assert_eq!(mix.intensity(), 675.0);
+    }
+
+    #[test]
+    fn builtin_registry_covers_every_region() {
+        let registry = Registry::load(None).unwrap();
+        for region in ["NSW1", "VIC1", "QLD1", "SA1", "TAS1"] {
+            let units: Vec<_> = registry.units.values().filter(|u| u.region == region).collect();
+            assert!(units.len() >= 30, "{region} has {} units", units.len());
+            let count = |fuel| units.iter().filter(|u| u.fuel == fuel).count();
+            assert!(count(Fuel::Hydro) + count(Fuel::Wind) >= 1, "{region} has no hydro or wind");
+            if region != "TAS1" {
+                assert!(count(Fuel::Wind) >= 3 && count(Fuel::Solar) >= 2, "{region} is missing wind or solar");
+            }
+        }
+    }
+}
@@ -0,0 +1,21 @@
+/// A NEM interconnector. Positive `MWFLOW` in `DISPATCH,INTERCONNECTORRES` runs from `from` to `to`.
+pub struct Interconnector {
+    /// `INTERCONNECTORID` in AEMO files.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+pub const INTERCONNECTORS: &[Interconnector] = &[
+    Interconnector { id: "NSW1-QLD1", name: "QNI", from: "NSW1", to: "QLD1" },
+    Interconnector { id: "N-Q-MNSP1", name: "Terranora", from: "NSW1", to: "QLD1" },
+    Interconnector { id: "VIC1-NSW1", name: "VIC-NSW", from: "VIC1", to: "NSW1" },
+    Interconnector { id: "V-SA", name: "Heywood", from: "VIC1", to: "SA1" },
+    Interconnector { id: "V-S-MNSP1", name: "Murraylink", from: "VIC1", to: "SA1" },
+    Interconnector { id: "T-V-MNSP1", name: "Basslink", from: "TAS1", to: "VIC1" },
+];
+
+pub fn find(id: &str) -> Option<&'static Interconnector> {
+    INTERCONNECTORS.iter().find(|i| i.id == id)
+}
@@ -0,0 +1,129 @@
+use chrono::Datelike;
+use std::sync::Once;
+
+use crate::timezone;
+
+/// NEM reliability settings for one financial year (1 July – 30 June), in $/MWh.
+/// The AEMC publishes the next year's values by the end of February; add them to
+/// `MARKET_PARAMS` before 1 July. Until then the latest listed year is used, with a
+/// warning in the log.
+pub struct MarketParams {
+    /// Calendar year the financial year starts in (2025 is 2025-26).
+    pub year: i32,
+    /// Market price cap: the highest price dispatch can set.
+    pub price_cap: f64,
+    pub price_floor: f64,
+    /// Cumulative price threshold: when the sum of a region's prices over the last
+    /// 7 days (2016 intervals) reaches this, AEMO declares an administered price period.
+    pub cumulative_threshold: f64,
+    /// Administered price cap applied during an administered price period.
+    pub administered_cap: f64,
+}
+
+/// Market price cap and cumulative price threshold from the AEMC's annual "Schedule of
+/// reliability settings" (NER clauses 3.9.4 and 3.14.1), indexed from the values set by
+/// the Reliability Standard and Settings Review 2022 final determination; the floor and
+/// administered price cap aren't indexed. FY2026-27 ($23,100 cap, $2,078,700 threshold)
+/// is from the 2026-27 schedule.
+pub const MARKET_PARAMS: &[MarketParams] = &[
+    MarketParams { year: 2022, price_cap: 15_500.0, price_floor: -1_000.0, cumulative_threshold: 1_398_100.0, administered_cap: 600.0 },
+    MarketParams { year: 2023, price_cap: 16_600.0, price_floor: -1_000.0, cumulative_threshold: 1_490_100.0, administered_cap: 600.0 },
+    MarketParams { year: 2024, price_cap: 17_500.0, price_floor: -1_000.0, cumulative_threshold: 1_573_700.0, administered_cap: 600.0 },
+    MarketParams { year: 2025, price_cap: 20_300.0, price_floor: -1_000.0, cumulative_threshold: 1_823_600.0, administered_cap: 600.0 },
+    MarketParams { year: 2026, price_cap: 23_100.0, price_floor: -1_000.0, cumulative_threshold: 2_078_700.0, administered_cap: 600.0 },
+];
+
+/// Length of the cumulative price window (2016 dispatch intervals).
+pub const CUMULATIVE_DAYS: i64 = 7;
+
+/// Share of the cumulative price threshold at which an administered price period is likely.
+const APP_LIKELY_RATIO: f64 = 0.8;
+
+/// Where a region's 7-day cumulative price stands against the threshold.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AppStatus {
+    Normal,
+    Likely,
+    Active,
+}
+
+fn financial_year(interval_time: &str) -> Option<i32> {
+    let d = timezone::parse_market(interval_time)?.date_naive();
+    Some(if d.month() >= 7 { d.year() } else { d.year() - 1 })
+}
+
+/// Settings for the financial year an interval falls in. Years not yet listed use
+/// the latest known settings; years before the list use the earliest.
+pub fn for_interval(interval_time: &str) -> &'static MarketParams {
+    match financial_year(interval_time) {
+        Some(fy) => {
+            let params = MARKET_PARAMS.iter().rev().find(|p| p.year <= fy).unwrap_or(&MARKET_PARAMS[0]);
+            if fy > params.year {
+                UNLISTED_WARNING.call_once(|| {
+                    tracing::warn!(
+                        year = fy, using = params.year,
+                        "Market settings for this financial year aren't in MARKET_PARAMS; add the AEMC's figures"
+                    );
+                });
+            }
+            params
+        }
+        None => current(),
+    }
+}
+
+/// Warn about a missing year once per run rather than on every interval.
+static UNLISTED_WARNING: Once = Once::new();
+
+pub fn current() -> &'static MarketParams {
+    for_interval(&timezone::market_str(&timezone::now_market()))
+}
+
+/// Whether dispatch could have produced `price` in that interval. Past the listed
+/// years the cap keeps rising, so only the floor is checked there.
+pub fn is_valid_price(interval_time: &str, price: f64) -> bool {
+    let p = for_interval(interval_time);
+    let unlisted = financial_year(interval_time).is_some_and(|fy| fy > p.year);
+    price >= p.price_floor && (unlisted || price <= p.price_cap)
+}
+
+impl MarketParams {
+    pub fn app_status(&self, cumulative: f64) -> AppStatus {
+        if cumulative >= self.cumulative_threshold {
+            AppStatus::Active
+        } else if cumulative >= self.cumulative_threshold * APP_LIKELY_RATIO {
+            AppStatus::Likely
+        } else {
+            AppStatus::Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn years_resolve_by_financial_year() {
+        assert_eq!(for_interval("2026/06/30 23:55:00").year, 2025);
+        assert_eq!(for_interval("2026/07/01 00:05:00").year, 2026);
+        assert_eq!(for_interval("2019/01/01 00:05:00").year, 2022);
+        assert!(is_valid_price("2026/07/01 00:05:00", 23_000.0));
+        assert!(!is_valid_price("2026/06/30 23:55:00", 23_000.0));
+        assert!(!is_valid_price("2026/07/01 00:05:00", -1_001.0));
+    }
+
+    #[test]
+    fn prices_are_checked_against_their_year() {
+        // At the cap and floor is valid, past them isn't
+        assert!(is_valid_price("2025/07/01 00:05:00", 20_300.0));
+        assert!(!is_valid_price("2025/07/01 00:05:00", 20_300.5));
+        assert!(is_valid_price("2025/07/01 00:05:00", -1_000.0));
+        // Years after the list use the latest settings, but only the floor is checked
+        assert_eq!(for_interval("2030/01/15 12:00:00").year, 2026);
+        assert!(is_valid_price("2030/01/15 12:00:00", 30_000.0));
+        assert!(!is_valid_price("2030/01/15 12:00:00", -1_000.5));
+        // Years before it use the earliest, cap included
+        assert!(!is_valid_price("2019/01/01 00:05:00", 15_600.0));
+    }
+}
@@ -1,3 +1,8 @@
+pub mod fcas;
 pub mod fetcher;
+pub mod generators;
+pub mod interconnectors;
+pub mod market_params;
+pub mod notices;
 pub mod parser;
 pub mod weather;
@@ -0,0 +1,315 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::timezone;
+
+/// How urgent a notice is; users opt in to a minimum severity.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn key(self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NoticeKind {
+    /// Lack of Reserve level 1-3, forecast or actual.
+    Lor(u8),
+    Suspension,
+    /// AEMO directing a participant, usually a generator, to keep the system secure.
+    Direction,
+    Intervention,
+    /// Everything else (reclassifications, transfer limits, price confirmations...).
+    Other,
+}
+
+impl NoticeKind {
+    pub fn key(self) -> &'static str {
+        match self {
+            NoticeKind::Lor(1) => "lor1",
+            NoticeKind::Lor(2) => "lor2",
+            NoticeKind::Lor(_) => "lor3",
+            NoticeKind::Suspension => "suspension",
+            NoticeKind::Direction => "direction",
+            NoticeKind::Intervention => "intervention",
+            NoticeKind::Other => "other",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NoticeKind::Lor(1) => "LOR1",
+            NoticeKind::Lor(2) => "LOR2",
+            NoticeKind::Lor(_) => "LOR3",
+            NoticeKind::Suspension => "Market suspension",
+            NoticeKind::Direction => "Direction",
+            NoticeKind::Intervention => "Intervention",
+            NoticeKind::Other => "Notice",
+        }
+    }
+}
+
+/// One AEMO market notice, classified from its type and external reference.
+pub struct MarketNotice {
+    pub id: i64,
+    /// AEMO's `Notice Type ID`, e.g. "RESERVE NOTICE".
+    pub notice_type: String,
+    /// AEMO's one-line `External Reference`.
+    pub reference: String,
+    pub reason: String,
+    /// Creation time in market time.
+    pub issued_at: String,
+    pub kind: NoticeKind,
+    /// Cancels an earlier notice of the same kind.
+    pub cancelled: bool,
+    /// Region IDs named in the reference; empty means NEM-wide.
+    pub regions: Vec<String>,
+}
+
+static LOR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"LOR\s*([123])|LACK OF RESERVE LEVEL\s*([123])").unwrap());
+static REGION_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(NSW|QLD|VIC|SA|TAS)1?\b").unwrap());
+
+impl MarketNotice {
+    pub fn new(id: i64, notice_type: String, reference: String, reason: String, issued_at: String) -> Self {
+        // Only the type and reference are used: reasons often mention other notice kinds in passing
+        let text = format!("{notice_type} {reference}").to_uppercase();
+        let kind = if text.contains("SUSPEN") {
+            NoticeKind::Suspension
+        } else if let Some(c) = LOR_RE.captures(&text) {
+            let level = c.get(1).or(c.get(2)).and_then(|m| m.as_str().parse().ok()).unwrap_or(1);
+            NoticeKind::Lor(level)
+        } else if text.contains("DIRECTION") {
+            NoticeKind::Direction
+        } else if text.contains("INTERVENTION") {
+            NoticeKind::Intervention
+        } else {
+            NoticeKind::Other
+        };
+        let mut regions: Vec<String> = Vec::new();
+        for c in REGION_RE.captures_iter(&reference.to_uppercase()) {
+            let region = format!("{}1", &c[1]);
+            if !regions.contains(&region) {
+                regions.push(region);
+            }
+        }
+        MarketNotice {
+            id,
+            notice_type,
+            cancelled: text.contains("CANCEL"),
+            reference,
+            reason,
+            issued_at,
+            kind,
+            regions,
+        }
+    }
+
+    /// None for notices that are never alerted on.
+    pub fn severity(&self) -> Option<Severity> {
+        if self.kind == NoticeKind::Other {
+            return None;
+        }
+        if self.cancelled {
+            return Some(Severity::Low);
+        }
+        Some(match self.kind {
+            NoticeKind::Lor(3) | NoticeKind::Suspension => Severity::High,
+            NoticeKind::Lor(1) => Severity::Low,
+            _ => Severity::Medium,
+        })
+    }
+
+    pub fn affects(&self, region: &str) -> bool {
+        self.regions.is_empty() || self.regions.iter().any(|r| r == region)
+    }
+}
+
+/// Parse a NEMweb market notice. These are fixed-layout text files:
+///
+/// ```text
+/// Creation Date :     16/10/2026     14:35:12
+/// Notice ID               :         119876
+/// Notice Type ID          :         RESERVE NOTICE
+/// External Reference      :         Actual LOR2 in the SA region - 16/10/2026
+/// Reason :
+/// ...
+/// ```
+pub fn parse_notice(text: &str) -> Option<MarketNotice> {
+    let value = |name: &str| {
+        text.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    let id = value("Notice ID")?.parse().ok()?;
+    let notice_type = value("Notice Type ID").unwrap_or_default();
+    let reference = value("External Reference").unwrap_or_default();
+
+    // "16/10/2026     14:35:12" — the time's own colons are past the first split
+    let issued_at = text
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Creation Date")?.trim_start().strip_prefix(':'))
+        .and_then(|v| {
+            let mut parts = v.split_whitespace();
+            let stamp = format!("{} {}", parts.next()?, parts.next()?);
+            chrono::NaiveDateTime::parse_from_str(&stamp, "%d/%m/%Y %H:%M:%S").ok()
+        })
+        .map(|t| t.format(timezone::MARKET_FMT).to_string())
+        .unwrap_or_else(|| timezone::market_str(&timezone::now_market()));
+
+    // Body runs from "Reason :" to the closing rule, minus AEMO's boilerplate heading
+    let reason = text
+        .split_once("Reason :")
+        .map(|(_, rest)| rest.split("\n---").next().unwrap_or(rest))
+        .unwrap_or("")
+        .lines()
+        .map(str::trim)
+        .filter(|l| *l != "AEMO ELECTRICITY MARKET NOTICE")
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+
+    Some(MarketNotice::new(id, notice_type, reference, reason, issued_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A NEMweb market notice file as AEMO lays it out.
+    fn file(id: i64, notice_type: &str, reference: &str, body: &str) -> String {
+        let rule = "-".repeat(67);
+        format!(
+            "{rule}\n                              MARKET NOTICE\n{rule}\n\n\
+             From :              AEMO\n\
+             To   :              NEMITWEB1\n\
+             Creation Date :     16/10/2026     14:35:12\n\n\
+             {rule}\n\n\
+             Notice ID               :         {id}\n\
+             Notice Type ID          :         {notice_type}\n\
+             Notice Type Description :         LRC/LOR1/LOR2/LOR3\n\
+             Issue Date              :         16/10/2026\n\
+             External Reference      :         {reference}\n\n\
+             {rule}\n\n\
+             Reason :\n\n\
+             AEMO ELECTRICITY MARKET NOTICE\n\n\
+             {body}\n\n\
+             AEMO Operations\n\
+             {rule}\n\
+             END OF REPORT\n\
+             {rule}\n"
+        )
+    }
+
+    #[test]
+    fn actual_lor2() {
+        let text = file(
+            119876,
+            "RESERVE NOTICE",
+            "Actual Lack Of Reserve Level 2 (LOR2) in the SA region - 16/10/2026",
+            "Actual Lack Of Reserve Level 2 (LOR2) in the SA region - 16/10/2026\n\n\
+             An Actual LOR2 condition has been declared under clause 4.8.4(b) of the National Electricity Rules for the SA region from 1430 hrs.\n\
+             The capacity reserve requirement is 450 MW\n\
+             The minimum capacity reserve available is 390 MW",
+        );
+        let notice = parse_notice(&text).unwrap();
+        assert_eq!(notice.id, 119876);
+        assert_eq!(notice.notice_type, "RESERVE NOTICE");
+        assert_eq!(notice.issued_at, "2026/10/16 14:35:12");
+        assert!(notice.kind == NoticeKind::Lor(2) && !notice.cancelled);
+        assert_eq!(notice.regions, ["SA1"]);
+        assert!(notice.affects("SA1") && !notice.affects("VIC1"));
+        assert!(notice.severity() == Some(Severity::Medium));
+        // The body without AEMO's heading or the closing rule
+        assert!(notice.reason.starts_with("Actual Lack Of Reserve Level 2 (LOR2) in the SA region"));
+        assert!(notice.reason.ends_with("AEMO Operations"));
+        assert!(!notice.reason.contains("MARKET NOTICE") && !notice.reason.contains("END OF REPORT"));
+    }
+
+    #[test]
+    fn cancellation_is_low_severity() {
+        let text = file(
+            119881,
+            "RESERVE NOTICE",
+            "Cancellation of Actual (LOR2) condition in the SA region - 16/10/2026",
+            "The Actual LOR2 condition in the SA region advised in AEMO Electricity Market Notice No. 119876 is cancelled at 1530 hrs 16/10/2026.",
+        );
+        let notice = parse_notice(&text).unwrap();
+        assert!(notice.kind == NoticeKind::Lor(2) && notice.cancelled);
+        assert_eq!(notice.regions, ["SA1"]);
+        assert!(notice.severity() == Some(Severity::Low));
+    }
+
+    #[test]
+    fn direction_from_an_intervention_notice() {
+        let text = file(
+            119890,
+            "MARKET INTERVENTION",
+            "Direction - SA region - 16/10/2026",
+            "AEMO has issued a direction to a participant in the SA region to maintain the power system in a secure operating state.\n\
+             Refer to market notice 119889 for the LOR2 that preceded it.",
+        );
+        let notice = parse_notice(&text).unwrap();
+        // Direction wins over intervention, and the LOR in the reason isn't read
+        assert!(notice.kind == NoticeKind::Direction && !notice.cancelled);
+        assert_eq!(notice.regions, ["SA1"]);
+        assert!(notice.severity() == Some(Severity::Medium));
+    }
+
+    #[test]
+    fn nem_wide_notices() {
+        let text = file(
+            119902,
+            "MARKET SYSTEMS",
+            "Planned outage of AEMO Market Systems - 18/10/2026",
+            "AEMO will be carrying out a planned outage of its market systems from 0900 hrs to 1100 hrs on 18/10/2026.",
+        );
+        let notice = parse_notice(&text).unwrap();
+        assert!(notice.kind == NoticeKind::Other);
+        assert!(notice.regions.is_empty());
+        assert!(["NSW1", "QLD1", "VIC1", "SA1", "TAS1"].iter().all(|r| notice.affects(r)));
+        assert!(notice.severity().is_none());
+
+        let text = file(
+            119910,
+            "MARKET SUSPENSION",
+            "Suspension of the NEM spot market - 16/10/2026",
+            "AEMO has declared the spot market suspended in all regions from 1600 hrs.",
+        );
+        let notice = parse_notice(&text).unwrap();
+        assert!(notice.kind == NoticeKind::Suspension && notice.regions.is_empty());
+        assert!(notice.severity() == Some(Severity::High));
+    }
+
+    #[test]
+    fn lor_levels_set_severity() {
+        let level = |reference: &str| {
+            let notice = parse_notice(&file(1, "RESERVE NOTICE", reference, "")).unwrap();
+            (notice.kind.key(), notice.severity().map(Severity::key), notice.regions)
+        };
+        assert_eq!(level("Forecast LOR1 in the NSW region - 17/10/2026"), ("lor1", Some("low"), vec!["NSW1".to_string()]));
+        assert_eq!(level("Actual LOR3 in the QLD1 and NSW1 regions"), ("lor3", Some("high"), vec!["QLD1".to_string(), "NSW1".to_string()]));
+        assert!(parse_notice("Notice Type ID : RESERVE NOTICE").is_none());
+    }
+}
@@ -1,9 +1,81 @@
 use std::collections::HashMap;
 
+use crate::data::{fcas, market_params};
+
 pub struct PriceRecord {
     pub region: String,
     pub price: f64,
     pub interval_time: String,
+    pub demand: Option<RegionDemand>,
+    /// FCAS prices as (service key, $/MWh); empty for files that predate a service.
+    pub fcas: Vec<(&'static str, f64)>,
+}
+
+/// Regional supply/demand balance from the DISPATCH,REGIONSUM table.
+#[derive(Clone, Copy)]
+pub struct RegionDemand {
+    pub demand_mw: f64,
+    pub available_generation_mw: f64,
+    /// Positive when the region is exporting.
+    pub net_interchange_mw: f64,
+}
+
+impl RegionDemand {
+    /// Available generation left over after serving local demand and exports.
+    pub fn supply_margin_mw(&self) -> f64 {
+        self.available_generation_mw - self.demand_mw - self.net_interchange_mw
+    }
+
+    pub fn margin_pct(&self) -> f64 {
+        if self.demand_mw > 0.0 {
+            self.supply_margin_mw() / self.demand_mw * 100.0
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// Interconnector flow and limits from the DISPATCH,INTERCONNECTORRES table.
+pub struct InterconnectorRecord {
+    pub interconnector_id: String,
+    pub interval_time: String,
+    /// Positive in the interconnector's nominal direction (see `data::interconnectors`).
+    pub mw_flow: f64,
+    /// Limit in the nominal direction.
+    pub export_limit: f64,
+    /// Limit in the reverse direction, negative like the flow.
+    pub import_limit: f64,
+    /// $/MWh value of relaxing the limit; non-zero when a constraint on it binds.
+    pub marginal_value: f64,
+}
+
+/// Flow within this of a limit counts as at the limit.
+const LIMIT_TOLERANCE_MW: f64 = 1.0;
+
+impl InterconnectorRecord {
+    /// The limit in the direction power is currently flowing.
+    pub fn limit_mw(&self) -> f64 {
+        if self.mw_flow >= 0.0 { self.export_limit } else { self.import_limit }
+    }
+
+    /// Whether the interconnector can't carry any more in its current direction: a
+    /// constraint on it binds, or the flow is at (or, in a violation, past) the limit on
+    /// the side it's flowing towards.
+    pub fn is_binding(&self) -> bool {
+        let at_limit = if self.mw_flow >= 0.0 {
+            self.mw_flow > self.export_limit - LIMIT_TOLERANCE_MW
+        } else {
+            self.mw_flow < self.import_limit + LIMIT_TOLERANCE_MW
+        };
+        self.marginal_value.abs() > 0.0 || at_limit
+    }
+}
+
+/// Everything the bot reads from one DispatchIS file.
+#[derive(Default)]
+pub struct Dispatch {
+    pub prices: Vec<PriceRecord>,
+    pub interconnectors: Vec<InterconnectorRecord>,
 }
 
 pub struct ForecastRecord {
@@ -12,10 +84,52 @@ pub struct ForecastRecord {
     pub price: f64,
 }
 
+/// One pre-dispatch or P5MIN run. AEMO reruns pre-dispatch every 30 minutes and
+/// P5MIN every 5, so successive runs give successive versions of the same periods.
+pub struct ForecastRun {
+    /// When the run was made (market time), if the file says.
+    pub published_at: Option<String>,
+    pub forecasts: Vec<ForecastRecord>,
+}
+
+fn read_header<'a>(col_map: &mut HashMap<&'a str, usize>, fields: &[&'a str]) {
+    col_map.clear();
+    for (i, f) in fields.iter().enumerate() {
+        col_map.insert(f.trim().trim_matches('"'), i);
+    }
+}
+
+/// Look up a column by name in a D-row, stripping whitespace and quotes.
+fn field<'a>(fields: &[&'a str], col_map: &HashMap<&str, usize>, name: &str) -> Option<&'a str> {
+    let i = *col_map.get(name)?;
+    fields.get(i).map(|f| f.trim().trim_matches('"'))
+}
+
+fn num(fields: &[&str], col_map: &HashMap<&str, usize>, name: &str) -> Option<f64> {
+    field(fields, col_map, name)
+        .and_then(|f| f.parse::<f64>().ok())
+        .filter(|v| v.is_finite())
+}
+
+/// `market_params::is_valid_price`, logging the row it rejects so a shift in AEMO's
+/// bounds doesn't silently empty a region.
+fn keep_price(region: &str, time: &str, price: f64) -> bool {
+    let valid = market_params::is_valid_price(time, price);
+    if !valid {
+        tracing::warn!(region, time, price, "Dropped price outside the market floor and cap");
+    }
+    valid
+}
+
 /// Parse AEMO dispatch CSV. Uses the I-row to dynamically find column positions.
-pub fn parse_dispatch(csv: &str) -> Vec<PriceRecord> {
-    let mut col_map: HashMap<&str, usize> = HashMap::new();
+/// REGIONSUM demand figures are attached to the matching region/interval price.
+pub fn parse_dispatch(csv: &str) -> Dispatch {
+    let mut price_cols: HashMap<&str, usize> = HashMap::new();
+    let mut sum_cols: HashMap<&str, usize> = HashMap::new();
+    let mut ic_cols: HashMap<&str, usize> = HashMap::new();
     let mut records = Vec::new();
+    let mut interconnectors = Vec::new();
+    let mut demand: HashMap<(String, String), RegionDemand> = HashMap::new();
 
     for line in csv.lines() {
         let fields: Vec<&str> = line.split(',').collect();
@@ -25,37 +139,80 @@ pub fn parse_dispatch(csv: &str) -> Vec<PriceRecord> {
         let tag = fields[0].trim();
         let table = fields[1].trim();
         let sub = fields[2].trim();
-
-        if tag == "I" && table == "DISPATCH" && sub == "PRICE" {
-            col_map.clear();
-            for (i, f) in fields.iter().enumerate() {
-                col_map.insert(f.trim().trim_matches('"'), i);
-            }
+        if table != "DISPATCH" {
+            continue;
         }
 
-        if tag == "D" && table == "DISPATCH" && sub == "PRICE" {
-            let ri = col_map.get("REGIONID").copied();
-            let pi = col_map.get("RRP").copied();
-            let ti = col_map.get("SETTLEMENTDATE").copied();
-            if let (Some(ri), Some(pi), Some(ti)) = (ri, pi, ti) {
-                if ri < fields.len() && pi < fields.len() && ti < fields.len() {
-                    let region = fields[ri].trim().trim_matches('"').to_string();
-                    let price: f64 = fields[pi].trim().trim_matches('"').parse().unwrap_or(f64::NAN);
-                    let time = fields[ti].trim().trim_matches('"').to_string();
-                    if price.is_finite() && (-1000.0..=17500.0).contains(&price) {
-                        records.push(PriceRecord { region, price, interval_time: time });
+        match (tag, sub) {
+            ("I", "PRICE") => read_header(&mut price_cols, &fields),
+            ("I", "REGIONSUM") => read_header(&mut sum_cols, &fields),
+            ("I", "INTERCONNECTORRES") => read_header(&mut ic_cols, &fields),
+            // Intervention pricing runs duplicate each interval; keep the market run
+            ("D", "PRICE") if field(&fields, &price_cols, "INTERVENTION") != Some("1") => {
+                let region = field(&fields, &price_cols, "REGIONID");
+                let price = num(&fields, &price_cols, "RRP");
+                let time = field(&fields, &price_cols, "SETTLEMENTDATE");
+                if let (Some(region), Some(price), Some(time)) = (region, price, time) {
+                    if keep_price(region, time, price) {
+                        let fcas = fcas::SERVICES
+                            .iter()
+                            .filter_map(|s| num(&fields, &price_cols, s.column).map(|p| (s.key, p)))
+                            .collect();
+                        records.push(PriceRecord {
+                            region: region.to_string(),
+                            price,
+                            interval_time: time.to_string(),
+                            demand: None,
+                            fcas,
+                        });
                     }
                 }
             }
+            ("D", "REGIONSUM") if field(&fields, &sum_cols, "INTERVENTION") != Some("1") => {
+                let region = field(&fields, &sum_cols, "REGIONID");
+                let time = field(&fields, &sum_cols, "SETTLEMENTDATE");
+                let d = num(&fields, &sum_cols, "TOTALDEMAND");
+                let g = num(&fields, &sum_cols, "AVAILABLEGENERATION");
+                let n = num(&fields, &sum_cols, "NETINTERCHANGE");
+                if let (Some(region), Some(time), Some(d), Some(g), Some(n)) = (region, time, d, g, n) {
+                    demand.insert(
+                        (region.to_string(), time.to_string()),
+                        RegionDemand { demand_mw: d, available_generation_mw: g, net_interchange_mw: n },
+                    );
+                }
+            }
+            ("D", "INTERCONNECTORRES") if field(&fields, &ic_cols, "INTERVENTION") != Some("1") => {
+                let id = field(&fields, &ic_cols, "INTERCONNECTORID");
+                let time = field(&fields, &ic_cols, "SETTLEMENTDATE");
+                let flow = num(&fields, &ic_cols, "MWFLOW");
+                let export = num(&fields, &ic_cols, "EXPORTLIMIT");
+                let import = num(&fields, &ic_cols, "IMPORTLIMIT");
+                if let (Some(id), Some(time), Some(flow), Some(export), Some(import)) = (id, time, flow, export, import) {
+                    interconnectors.push(InterconnectorRecord {
+                        interconnector_id: id.to_string(),
+                        interval_time: time.to_string(),
+                        mw_flow: flow,
+                        export_limit: export,
+                        import_limit: import,
+                        marginal_value: num(&fields, &ic_cols, "MARGINALVALUE").unwrap_or(0.0),
+                    });
+                }
+            }
+            _ => {}
         }
     }
-    records
+
+    for rec in &mut records {
+        rec.demand = demand.remove(&(rec.region.clone(), rec.interval_time.clone()));
+    }
+    Dispatch { prices: records, interconnectors }
 }
 
 /// Parse AEMO pre-dispatch CSV.
-pub fn parse_predispatch(csv: &str) -> Vec<ForecastRecord> {
+pub fn parse_predispatch(csv: &str) -> ForecastRun {
     let mut col_map: HashMap<&str, usize> = HashMap::new();
     let mut records = Vec::new();
+    let mut published_at = None;
 
     for line in csv.lines() {
         let fields: Vec<&str> = line.split(',').collect();
@@ -65,6 +222,14 @@ pub fn parse_predispatch(csv: &str) -> Vec<ForecastRecord> {
         let tag = fields[0].trim();
         let table = fields[1].trim();
 
+        // C,SETP.WORLD,PREDISPATCHIS,AEMO,PUBLIC,2025/01/01,10:02:01,...
+        if tag == "C" && published_at.is_none() && fields.len() > 6 {
+            let stamp = format!("{} {}", fields[5].trim_matches('"'), fields[6].trim_matches('"'));
+            if chrono::NaiveDateTime::parse_from_str(&stamp, "%Y/%m/%d %H:%M:%S").is_ok() {
+                published_at = Some(stamp);
+            }
+        }
+
         // Match both PREDISPATCH,REGION_PRICES and PREDISPATCH,PRICE
         let is_pred_row = table == "PREDISPATCH"
             && (fields[2].trim() == "PRICE" || fields[2].trim() == "REGION_PRICES");
@@ -86,12 +251,403 @@ pub fn parse_predispatch(csv: &str) -> Vec<ForecastRecord> {
                     let region = fields[ri].trim().trim_matches('"').to_string();
                     let price: f64 = fields[pi].trim().trim_matches('"').parse().unwrap_or(f64::NAN);
                     let time = fields[ti].trim().trim_matches('"').to_string();
-                    if price.is_finite() && (-1000.0..=17500.0).contains(&price) {
+                    if price.is_finite() && keep_price(&region, &time, price) {
                         records.push(ForecastRecord { region, price, forecast_time: time });
                     }
                 }
             }
         }
     }
+    ForecastRun { published_at, forecasts: records }
+}
+
+/// One generating unit's output from DISPATCH,UNIT_SCADA.
+pub struct ScadaRecord {
+    pub duid: String,
+    pub interval_time: String,
+    pub mw: f64,
+}
+
+/// Parse AEMO Dispatch_SCADA CSV: metered output per unit (DUID) at the interval end.
+pub fn parse_scada(csv: &str) -> Vec<ScadaRecord> {
+    let mut cols: HashMap<&str, usize> = HashMap::new();
+    let mut records = Vec::new();
+
+    for line in csv.lines() {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 3 || fields[1].trim() != "DISPATCH" || fields[2].trim() != "UNIT_SCADA" {
+            continue;
+        }
+        match fields[0].trim() {
+            "I" => read_header(&mut cols, &fields),
+            "D" => {
+                let duid = field(&fields, &cols, "DUID");
+                let time = field(&fields, &cols, "SETTLEMENTDATE");
+                let mw = num(&fields, &cols, "SCADAVALUE");
+                if let (Some(duid), Some(time), Some(mw)) = (duid, time, mw) {
+                    records.push(ScadaRecord { duid: duid.to_string(), interval_time: time.to_string(), mw });
+                }
+            }
+            _ => {}
+        }
+    }
+    records
+}
+
+/// A generating unit from AEMO's CDEII available-generators list.
+pub struct GeneratorRecord {
+    pub duid: String,
+    pub region: String,
+    /// CO2E_ENERGY_SOURCE, e.g. "Black coal" or "Natural Gas (Pipeline)".
+    pub energy_source: String,
+    /// Tonnes CO2-e per MWh sent out.
+    pub emissions: Option<f64>,
+}
+
+/// Parse the CDEII `CO2EII_AVAILABLE_GENERATORS` CSV, one record per DUID (units with
+/// several gensets are listed once per genset; the first is kept). Station names are
+/// quoted and can hold commas, so this one goes through a real CSV reader.
+pub fn parse_generators(csv: &str) -> Vec<GeneratorRecord> {
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(csv.as_bytes());
+    let mut cols: HashMap<String, usize> = HashMap::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut records = Vec::new();
+
+    for row in reader.records().flatten() {
+        let get = |cols: &HashMap<String, usize>, name: &str| {
+            cols.get(name).and_then(|&i| row.get(i)).map(str::trim).filter(|v| !v.is_empty())
+        };
+        match row.get(0).map(str::trim) {
+            Some("I") => {
+                cols = row.iter().enumerate().map(|(i, f)| (f.trim().to_string(), i)).collect();
+            }
+            Some("D") => {
+                let (Some(duid), Some(region)) = (get(&cols, "DUID"), get(&cols, "REGIONID")) else { continue };
+                if !seen.insert(duid.to_string()) {
+                    continue;
+                }
+                records.push(GeneratorRecord {
+                    duid: duid.to_string(),
+                    region: region.to_string(),
+                    energy_source: get(&cols, "CO2E_ENERGY_SOURCE").unwrap_or_default().to_string(),
+                    emissions: get(&cols, "CO2E_EMISSIONS_FACTOR").and_then(|e| e.parse().ok()),
+                });
+            }
+            _ => {}
+        }
+    }
+    records
+}
+
+/// Regional rooftop PV output for one half-hour.
+pub struct RooftopPvRecord {
+    pub region: String,
+    /// Half-hour end in market time.
+    pub interval_time: String,
+    pub power_mw: f64,
+}
+
+/// Parse AEMO ROOFTOP_PV CSV: `ROOFTOP,ACTUAL` (POWER, metered rows only) or
+/// `ROOFTOP,FORECAST` (median POWERPOE50, else POWERMEAN). Sub-regions such as QLDC
+/// are skipped; the region rows already include them.
+pub fn parse_rooftop_pv(csv: &str) -> Vec<RooftopPvRecord> {
+    let mut cols: HashMap<&str, usize> = HashMap::new();
+    let mut records = Vec::new();
+
+    for line in csv.lines() {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 3 || fields[1].trim() != "ROOFTOP" {
+            continue;
+        }
+        match fields[0].trim() {
+            "I" => read_header(&mut cols, &fields),
+            // Satellite estimates would overwrite the metered figure for the same half-hour
+            "D" if field(&fields, &cols, "TYPE").is_none_or(|t| t == "MEASUREMENT") => {
+                let region = field(&fields, &cols, "REGIONID").filter(|r| r.ends_with('1'));
+                let time = field(&fields, &cols, "INTERVAL_DATETIME");
+                let power = num(&fields, &cols, "POWER")
+                    .or_else(|| num(&fields, &cols, "POWERPOE50"))
+                    .or_else(|| num(&fields, &cols, "POWERMEAN"));
+                if let (Some(region), Some(time), Some(power)) = (region, time, power) {
+                    records.push(RooftopPvRecord {
+                        region: region.to_string(),
+                        interval_time: time.to_string(),
+                        power_mw: power,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
     records
 }
+
+/// Parse AEMO 5-minute pre-dispatch (P5MIN) CSV: one price per region for each
+/// of the next twelve dispatch intervals.
+pub fn parse_p5min(csv: &str) -> ForecastRun {
+    let mut cols: HashMap<&str, usize> = HashMap::new();
+    let mut forecasts = Vec::new();
+    let mut published_at = None;
+
+    for line in csv.lines() {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 3 || fields[1].trim() != "P5MIN" || fields[2].trim() != "REGIONSOLUTION" {
+            continue;
+        }
+        match fields[0].trim() {
+            "I" => read_header(&mut cols, &fields),
+            "D" if field(&fields, &cols, "INTERVENTION") != Some("1") => {
+                let region = field(&fields, &cols, "REGIONID");
+                let price = num(&fields, &cols, "RRP");
+                let time = field(&fields, &cols, "INTERVAL_DATETIME");
+                if published_at.is_none() {
+                    published_at = field(&fields, &cols, "RUN_DATETIME").map(str::to_string);
+                }
+                if let (Some(region), Some(price), Some(time)) = (region, price, time) {
+                    if keep_price(region, time, price) {
+                        forecasts.push(ForecastRecord {
+                            region: region.to_string(),
+                            forecast_time: time.to_string(),
+                            price,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    ForecastRun { published_at, forecasts }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn generators_from_cdeii() {
+        let csv = "C,NEMP.WORLD,CO2EII_AVAILABLE_GENERATORS,AEMO,PUBLIC,2026/01/02,10:00:00\n\
+            I,CO2EII,PUBLISHING,2,STATIONNAME,DUID,GENSETID,REGIONID,CO2E_EMISSIONS_FACTOR,CO2E_ENERGY_SOURCE,CO2E_DATA_SOURCE\n\
+            D,CO2EII,PUBLISHING,2,\"Bayswater Power Station\",BW01,BW01,NSW1,0.9,Black coal,NGA 2025\n\
+            D,CO2EII,PUBLISHING,2,\"Tallawarra, Unit A\",TALWA1,TALWA1A,NSW1,0.37,Natural Gas (Pipeline),NGA 2025\n\
+            D,CO2EII,PUBLISHING,2,\"Tallawarra, Unit A\",TALWA1,TALWA1B,NSW1,0.37,Natural Gas (Pipeline),NGA 2025\n\
+            D,CO2EII,PUBLISHING,2,Sunny Farm,SUNNY1,SUNNY1,QLD1,,Solar,\n\
+            C,\"END OF REPORT\",6\n";
+        let gens = parse_generators(csv);
+        assert_eq!(gens.len(), 3);
+        assert_eq!(gens[1].duid, "TALWA1");
+        assert_eq!(gens[1].region, "NSW1");
+        assert_eq!(gens[1].energy_source, "Natural Gas (Pipeline)");
+        assert_eq!(gens[1].emissions, Some(0.37));
+        assert_eq!(gens[2].emissions, None);
+    }
+
+    #[test]
+    fn dispatch_keeps_the_market_run() {
+        let csv = "C,NEMP.WORLD,DISPATCHIS,AEMO,PUBLIC,2026/01/15,10:00:05\n\
+            I,DISPATCH,PRICE,5,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP\n\
+            D,DISPATCH,PRICE,5,\"2026/01/15 10:05:00\",1,NSW1,20260115123,0,85.5\n\
+            D,DISPATCH,PRICE,5,\"2026/01/15 10:05:00\",1,NSW1,20260115123,1,300\n\
+            I,DISPATCH,REGIONSUM,8,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,TOTALDEMAND,AVAILABLEGENERATION,NETINTERCHANGE\n\
+            D,DISPATCH,REGIONSUM,8,\"2026/01/15 10:05:00\",1,NSW1,20260115123,0,8000,11000,-500\n\
+            D,DISPATCH,REGIONSUM,8,\"2026/01/15 10:05:00\",1,NSW1,20260115123,1,8100,10000,-600\n\
+            C,\"END OF REPORT\",8\n";
+        let dispatch = parse_dispatch(csv);
+        assert_eq!(dispatch.prices.len(), 1);
+        assert_eq!(dispatch.prices[0].price, 85.5);
+        let demand = dispatch.prices[0].demand.as_ref().unwrap();
+        assert_eq!((demand.demand_mw, demand.available_generation_mw, demand.net_interchange_mw), (8000.0, 11000.0, -500.0));
+    }
+
+    #[test]
+    fn prices_past_the_floor_are_dropped() {
+        let csv = "I,DISPATCH,PRICE,5,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP\n\
+            D,DISPATCH,PRICE,5,\"2026/01/15 10:05:00\",1,NSW1,20260115123,0,-1500\n\
+            D,DISPATCH,PRICE,5,\"2026/01/15 10:05:00\",1,VIC1,20260115123,0,-1000\n";
+        let prices = parse_dispatch(csv).prices;
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].region, "VIC1");
+    }
+
+    /// Cut from a PUBLIC_DISPATCHIS file: SA importing and QLD exporting with little to
+    /// spare, VIC exporting with exactly 10% left, and a REGIONSUM row with no price.
+    pub(crate) const REGIONSUM: &str = "C,NEMP.WORLD,DISPATCHIS,AEMO,PUBLIC,2026/01/15,17:00:07,0000000467245318,DISPATCHIS,0000000467245312\n\
+        I,DISPATCH,PRICE,5,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP,EEP,ROP,APCFLAG,MARKETSUSPENDEDFLAG\n\
+        D,DISPATCH,PRICE,5,\"2026/01/15 17:05:00\",1,QLD1,20260115206,0,142.18,0,142.18,0,0\n\
+        D,DISPATCH,PRICE,5,\"2026/01/15 17:05:00\",1,SA1,20260115206,0,310.5,0,310.5,0,0\n\
+        D,DISPATCH,PRICE,5,\"2026/01/15 17:05:00\",1,VIC1,20260115206,0,118.02,0,118.02,0,0\n\
+        I,DISPATCH,REGIONSUM,8,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,TOTALDEMAND,AVAILABLEGENERATION,AVAILABLELOAD,DEMANDFORECAST,DISPATCHABLEGENERATION,DISPATCHABLELOAD,NETINTERCHANGE,EXCESSGENERATION\n\
+        D,DISPATCH,REGIONSUM,8,\"2026/01/15 17:05:00\",1,QLD1,20260115206,0,8000,9300,0,12.5,8600,0,600,0\n\
+        D,DISPATCH,REGIONSUM,8,\"2026/01/15 17:05:00\",1,SA1,20260115206,0,2000,1750,0,-8.2,1570,0,-430,0\n\
+        D,DISPATCH,REGIONSUM,8,\"2026/01/15 17:05:00\",1,VIC1,20260115206,0,5000,6200,0,4.1,5700,0,700,0\n\
+        D,DISPATCH,REGIONSUM,8,\"2026/01/15 17:05:00\",1,TAS1,20260115206,0,1100,2100,0,1.3,1120,0,20,0\n\
+        C,\"END OF REPORT\",11\n";
+
+    #[test]
+    fn regionsum_joins_its_price_and_nets_off_interchange() {
+        let prices = parse_dispatch(REGIONSUM).prices;
+        assert_eq!(prices.len(), 3);
+        let demand = |region: &str| prices.iter().find(|p| p.region == region).unwrap().demand.as_ref().unwrap();
+
+        // Importing: 430 MW coming in covers what local generation can't
+        let sa = demand("SA1");
+        assert_eq!((sa.demand_mw, sa.available_generation_mw, sa.net_interchange_mw), (2000.0, 1750.0, -430.0));
+        assert_eq!(sa.supply_margin_mw(), 180.0);
+        assert_eq!(sa.margin_pct(), 9.0);
+        // Exporting: 600 MW leaving comes out of the margin
+        let qld = demand("QLD1");
+        assert_eq!(qld.supply_margin_mw(), 700.0);
+        assert_eq!(qld.margin_pct(), 8.75);
+        assert_eq!(demand("VIC1").margin_pct(), 10.0);
+    }
+
+    /// Cut from a PUBLIC_DISPATCHIS file: one interval with every interconnector, plus an
+    /// intervention run for Heywood.
+    const INTERCONNECTORRES: &str = "C,NEMP.WORLD,DISPATCHIS,AEMO,PUBLIC,2026/01/15,17:00:07,0000000467245318,DISPATCHIS,0000000467245312\n\
+        I,DISPATCH,INTERCONNECTORRES,3,SETTLEMENTDATE,RUNNO,INTERCONNECTORID,DISPATCHINTERVAL,INTERVENTION,METEREDMWFLOW,MWFLOW,MWLOSSES,MARGINALVALUE,VIOLATIONDEGREE,EXPORTLIMIT,IMPORTLIMIT,MARGINALLOSS,EXPORTGENCONID,IMPORTGENCONID,FCASEXPORTLIMIT,FCASIMPORTLIMIT,LASTCHANGED,LOCAL_PRICE_ADJUSTMENT_EXPORT,LOCALLY_CONSTRAINED_EXPORT,LOCAL_PRICE_ADJUSTMENT_IMPORT,LOCALLY_CONSTRAINED_IMPORT\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,N-Q-MNSP1,20260115206,0,-41.3,-42,0.64,0,0,107,-210,0.9712,NQTE1,NQTE1,107,-210,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,NSW1-QLD1,20260115206,0,-512.6,-498.17,17.43,0,0,930.52,-1074.09,0.9431,N^^Q_NIL_B1,Q^^NIL_QNI_SRAR,1193,-1157,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,T-V-MNSP1,20260115206,0,-477.8,-478,12.3,-34.52,0,478,-478,0.9624,T_V_NIL_BL1,V_T_NIL_BL1,478,-478,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,V-S-MNSP1,20260115206,0,118.2,120,4.1,0,0,220,-100,1.0412,VSML_ROCOF,S>V_NIL_NIL_RBNW,220,-100,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,V-SA,20260115206,0,652.4,650,23.8,-212.07,0,650,-600,1.0871,V^SML_NSWRB_2,S>V_NIL_HYTX_HYTX,650,-600,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,VIC1-NSW1,20260115206,0,1003.1,1002.4,38.6,0,2.4,1000,-1350,1.0734,V>>N_NIL_HSNW,N^^V_NIL_1,1000,-1350,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        D,DISPATCH,INTERCONNECTORRES,3,\"2026/01/15 17:05:00\",1,V-SA,20260115206,1,652.4,430,12.1,0,0,650,-600,1.0871,V^SML_NSWRB_2,S>V_NIL_HYTX_HYTX,650,-600,\"2026/01/15 17:00:02\",0,0,0,0\n\
+        C,\"END OF REPORT\",9\n";
+
+    #[test]
+    fn interconnectors_from_dispatchis() {
+        let flows = parse_dispatch(INTERCONNECTORRES).interconnectors;
+        assert_eq!(flows.len(), 6);
+        let get = |id: &str| flows.iter().find(|f| f.interconnector_id == id).unwrap();
+
+        let heywood = get("V-SA");
+        assert_eq!(heywood.interval_time, "2026/01/15 17:05:00");
+        assert_eq!((heywood.mw_flow, heywood.export_limit, heywood.import_limit), (650.0, 650.0, -600.0));
+        assert_eq!(heywood.marginal_value, -212.07);
+
+        let qni = get("NSW1-QLD1");
+        assert_eq!(qni.limit_mw(), -1074.09);
+        assert!(!qni.is_binding());
+        // Basslink southward at its import limit, VIC-NSW past its export limit in a violation
+        assert!(get("T-V-MNSP1").is_binding());
+        assert!(get("VIC1-NSW1").is_binding());
+        assert!(get("V-SA").is_binding());
+        assert!(!get("V-S-MNSP1").is_binding());
+        assert!(!get("N-Q-MNSP1").is_binding());
+    }
+
+    fn flow(mw_flow: f64, export_limit: f64, import_limit: f64) -> InterconnectorRecord {
+        InterconnectorRecord {
+            interconnector_id: "V-SA".into(),
+            interval_time: "2026/01/15 17:05:00".into(),
+            mw_flow,
+            export_limit,
+            import_limit,
+            marginal_value: 0.0,
+        }
+    }
+
+    #[test]
+    fn binding_follows_the_flow_direction() {
+        // Exporting: only the export limit matters
+        assert!(flow(649.5, 650.0, -600.0).is_binding());
+        assert!(flow(655.0, 650.0, -600.0).is_binding());
+        assert!(!flow(640.0, 650.0, -600.0).is_binding());
+        // Importing: only the import limit, which is negative like the flow
+        assert!(flow(-599.5, 650.0, -600.0).is_binding());
+        assert!(flow(-610.0, 650.0, -600.0).is_binding());
+        assert!(!flow(-590.0, 650.0, -600.0).is_binding());
+        // Near the limit on the other side doesn't count
+        assert!(!flow(-0.5, 0.0, -600.0).is_binding());
+        assert!(!flow(0.5, 650.0, 0.0).is_binding());
+        // A binding constraint does, wherever the flow is
+        assert!(InterconnectorRecord { marginal_value: 35.1, ..flow(300.0, 650.0, -600.0) }.is_binding());
+    }
+
+    #[test]
+    fn fcas_prices_ride_on_the_price_row() {
+        // DISPATCH,PRICE v5 without the pre-administered-price columns
+        let csv = "C,NEMP.WORLD,DISPATCHIS,AEMO,PUBLIC,2026/01/15,17:00:07,0000000467245318,DISPATCHIS,0000000467245312\n\
+            I,DISPATCH,PRICE,5,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP,EEP,ROP,APCFLAG,MARKETSUSPENDEDFLAG,LASTCHANGED,RAISE6SECRRP,RAISE6SECROP,RAISE6SECAPCFLAG,RAISE60SECRRP,RAISE60SECROP,RAISE60SECAPCFLAG,RAISE5MINRRP,RAISE5MINROP,RAISE5MINAPCFLAG,RAISEREGRRP,RAISEREGROP,RAISEREGAPCFLAG,LOWER6SECRRP,LOWER6SECROP,LOWER6SECAPCFLAG,LOWER60SECRRP,LOWER60SECROP,LOWER60SECAPCFLAG,LOWER5MINRRP,LOWER5MINROP,LOWER5MINAPCFLAG,LOWERREGRRP,LOWERREGROP,LOWERREGAPCFLAG,PRICE_STATUS,OCD_STATUS,MII_STATUS,RAISE1SECRRP,RAISE1SECROP,RAISE1SECAPCFLAG,LOWER1SECRRP,LOWER1SECROP,LOWER1SECAPCFLAG\n\
+            D,DISPATCH,PRICE,5,\"2026/01/15 17:05:00\",1,SA1,20260115206,0,312.45,0,312.45,0,0,\"2026/01/15 17:00:02\",18.5,18.5,0,4.2,4.2,0,1.1,1.1,0,35.8,35.8,0,0.49,0.49,0,0.28,0.28,0,0.19,0.19,0,12.06,12.06,0,FIRM,NOT_OCD,NOT_MII,2.75,2.75,0,0.1,0.1,0\n\
+            C,\"END OF REPORT\",4\n";
+        let prices = parse_dispatch(csv).prices;
+        assert_eq!(prices.len(), 1);
+        let fcas: HashMap<&str, f64> = prices[0].fcas.iter().copied().collect();
+        assert_eq!(fcas.len(), fcas::SERVICES.len());
+        assert_eq!(fcas["raise6sec"], 18.5);
+        assert_eq!(fcas["raisereg"], 35.8);
+        assert_eq!(fcas["lowerreg"], 12.06);
+        assert_eq!(fcas["raise1sec"], 2.75);
+
+        // Files from before the 1-second services have no columns for them
+        let old = "I,DISPATCH,PRICE,4,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP,RAISE6SECRRP,LOWER6SECRRP\n\
+            D,DISPATCH,PRICE,4,\"2023/01/15 17:05:00\",1,SA1,20230115206,0,80,5.5,0.3\n";
+        let prices = parse_dispatch(old).prices;
+        assert_eq!(prices[0].fcas, [("raise6sec", 5.5), ("lower6sec", 0.3)]);
+    }
+
+    #[test]
+    fn p5min_keeps_the_market_run_of_region_solutions() {
+        // P5MIN,REGIONSOLUTION cut down to the columns around the price
+        let csv = "C,NEMP.WORLD,P5MIN,AEMO,PUBLIC,2026/01/15,17:00:31,0000000467245401,P5MIN,0000000467245395\n\
+            I,P5MIN,CASESOLUTION,2,RUN_DATETIME,STARTINTERVAL_DATETIME,TOTALOBJECTIVE,LASTCHANGED\n\
+            D,P5MIN,CASESOLUTION,2,\"2026/01/15 17:05:00\",\"2026/01/15 17:05:00\",-4.1e9,\"2026/01/15 17:00:28\"\n\
+            I,P5MIN,REGIONSOLUTION,9,RUN_DATETIME,INTERVENTION,INTERVAL_DATETIME,REGIONID,RRP,ROP,EXCESSGENERATION,TOTALDEMAND,LASTCHANGED\n\
+            D,P5MIN,REGIONSOLUTION,9,\"2026/01/15 17:05:00\",0,\"2026/01/15 17:05:00\",NSW1,92.1,92.1,0,9120.4,\"2026/01/15 17:00:28\"\n\
+            D,P5MIN,REGIONSOLUTION,9,\"2026/01/15 17:05:00\",0,\"2026/01/15 17:10:00\",NSW1,118.6,118.6,0,9177.2,\"2026/01/15 17:00:28\"\n\
+            D,P5MIN,REGIONSOLUTION,9,\"2026/01/15 17:05:00\",1,\"2026/01/15 17:10:00\",NSW1,300,300,0,9177.2,\"2026/01/15 17:00:28\"\n\
+            D,P5MIN,REGIONSOLUTION,9,\"2026/01/15 17:05:00\",0,\"2026/01/15 17:10:00\",SA1,-40,-40,0,1310.8,\"2026/01/15 17:00:28\"\n\
+            D,P5MIN,REGIONSOLUTION,9,\"2026/01/15 17:05:00\",0,\"2026/01/15 17:15:00\",SA1,-1200,-1200,0,1302.5,\"2026/01/15 17:00:28\"\n\
+            I,P5MIN,INTERCONNECTORSOLN,4,RUN_DATETIME,INTERCONNECTORID,INTERVAL_DATETIME,MWFLOW\n\
+            D,P5MIN,INTERCONNECTORSOLN,4,\"2026/01/15 17:05:00\",V-SA,\"2026/01/15 17:05:00\",410.2\n\
+            C,\"END OF REPORT\",10\n";
+        let run = parse_p5min(csv);
+        assert_eq!(run.published_at.as_deref(), Some("2026/01/15 17:05:00"));
+        let got: Vec<_> = run.forecasts.iter().map(|f| (f.region.as_str(), f.forecast_time.as_str(), f.price)).collect();
+        // The intervention run is skipped, and a price below the floor dropped as corrupt
+        assert_eq!(
+            got,
+            [
+                ("NSW1", "2026/01/15 17:05:00", 92.1),
+                ("NSW1", "2026/01/15 17:10:00", 118.6),
+                ("SA1", "2026/01/15 17:10:00", -40.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rooftop_pv_keeps_regions_and_metered_actuals() {
+        let actual = "C,NEMP.WORLD,ROOFTOP_PV_ACTUAL_MEASUREMENT,AEMO,PUBLIC,2026/01/15,12:31:12,0000000467201234,ROOFTOP_PV_ACTUAL_MEASUREMENT,0000000467201230\n\
+            I,ROOFTOP,ACTUAL,2,INTERVAL_DATETIME,REGIONID,POWER,QI,TYPE,LASTCHANGED\n\
+            D,ROOFTOP,ACTUAL,2,\"2026/01/15 12:30:00\",NSW1,4210.552,1,MEASUREMENT,\"2026/01/15 12:31:05\"\n\
+            D,ROOFTOP,ACTUAL,2,\"2026/01/15 12:30:00\",NSW1,4388.1,0.6,SATELLITE,\"2026/01/15 12:31:05\"\n\
+            D,ROOFTOP,ACTUAL,2,\"2026/01/15 12:30:00\",QLD1,3302.8,1,MEASUREMENT,\"2026/01/15 12:31:05\"\n\
+            D,ROOFTOP,ACTUAL,2,\"2026/01/15 12:30:00\",QLDC,1020.4,1,MEASUREMENT,\"2026/01/15 12:31:05\"\n\
+            D,ROOFTOP,ACTUAL,2,\"2026/01/15 12:30:00\",TASN,40.2,1,MEASUREMENT,\"2026/01/15 12:31:05\"\n\
+            C,\"END OF REPORT\",8\n";
+        let got: Vec<_> = parse_rooftop_pv(actual).into_iter().map(|r| (r.region, r.interval_time, r.power_mw)).collect();
+        assert_eq!(
+            got,
+            [
+                ("NSW1".to_string(), "2026/01/15 12:30:00".to_string(), 4210.552),
+                ("QLD1".to_string(), "2026/01/15 12:30:00".to_string(), 3302.8),
+            ]
+        );
+
+        let forecast = "C,NEMP.WORLD,ROOFTOP_PV_FORECAST,AEMO,PUBLIC,2026/01/15,12:00:32,0000000467199876,ROOFTOP_PV_FORECAST,0000000467199870\n\
+            I,ROOFTOP,FORECAST,1,VERSION_DATETIME,REGIONID,INTERVAL_DATETIME,POWERMEAN,POWERPOE50,POWERPOELOW,POWERPOEHIGH,LASTCHANGED\n\
+            D,ROOFTOP,FORECAST,1,\"2026/01/15 12:00:00\",SA1,\"2026/01/15 13:00:00\",1480.2,1502.7,1205.3,1690.1,\"2026/01/15 12:00:21\"\n\
+            D,ROOFTOP,FORECAST,1,\"2026/01/15 12:00:00\",SA1,\"2026/01/15 13:30:00\",1455.9,,1190.4,1670.8,\"2026/01/15 12:00:21\"\n\
+            D,ROOFTOP,FORECAST,1,\"2026/01/15 12:00:00\",TAS1,\"2026/01/15 13:00:00\",,,,,\"2026/01/15 12:00:21\"\n\
+            C,\"END OF REPORT\",5\n";
+        let got: Vec<_> = parse_rooftop_pv(forecast).into_iter().map(|r| (r.region, r.interval_time, r.power_mw)).collect();
+        // The median when given, else the mean; a row with neither is skipped
+        assert_eq!(
+            got,
+            [
+                ("SA1".to_string(), "2026/01/15 13:00:00".to_string(), 1502.7),
+                ("SA1".to_string(), "2026/01/15 13:30:00".to_string(), 1455.9),
+            ]
+        );
+    }
+}
@@ -31,6 +31,7 @@ pub struct WeatherForecast {
     pub solar: SolarPotential,
 }
 
+#[derive(Clone, Copy)]
 pub enum SolarPotential {
     Excellent,
     Good,
@@ -57,6 +58,22 @@ impl SolarPotential {
     }
 }
 
+/// Solar potential from a day's forecast rooftop PV peak, as a share of the
+/// region's recent best (which tracks installed capacity and the season).
+pub fn classify_solar_mw(peak_mw: f64, reference_mw: f64) -> SolarPotential {
+    let share = if reference_mw > 0.0 { peak_mw / reference_mw } else { 0.0 };
+    if share >= 0.85 {
+        SolarPotential::Excellent
+    } else if share >= 0.7 {
+        SolarPotential::Good
+    } else if share >= 0.45 {
+        SolarPotential::Moderate
+    } else {
+        SolarPotential::Poor
+    }
+}
+
+/// Fallback when no rooftop PV forecast is available.
 fn classify_solar(icon: &str) -> SolarPotential {
     match icon {
         "sunny" | "clear" => SolarPotential::Excellent,
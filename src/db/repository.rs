@@ -1,7 +1,46 @@
 use anyhow::Result;
+use chrono_tz::Tz;
 use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Mutex;
 
+use crate::data::generators::{Fuel, GenerationMix, MixRecord, FUELS};
+use crate::data::market_params;
+use crate::data::notices::MarketNotice;
+use crate::data::parser::{ForecastRecord, InterconnectorRecord, PriceRecord, RegionDemand, RooftopPvRecord};
+use crate::engine::analyzer::{PendingAlert, Threshold, Unit};
+use crate::engine::tariff::{self, Tariff};
+use crate::timezone;
+
+/// Applied in order; `PRAGMA user_version` records how many have run.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/001_init.sql"),
+    include_str!("../../migrations/002_user_timezone.sql"),
+    include_str!("../../migrations/003_region_demand.sql"),
+    include_str!("../../migrations/004_battery_profile.sql"),
+    include_str!("../../migrations/005_savings.sql"),
+    include_str!("../../migrations/006_retail_tariff.sql"),
+    include_str!("../../migrations/007_backfill.sql"),
+    include_str!("../../migrations/008_dispatch_gaps.sql"),
+    include_str!("../../migrations/009_forecast_accuracy.sql"),
+    include_str!("../../migrations/010_p5min_forecast.sql"),
+    include_str!("../../migrations/011_fcas.sql"),
+    include_str!("../../migrations/012_interconnector_flow.sql"),
+    include_str!("../../migrations/013_market_notices.sql"),
+    include_str!("../../migrations/014_rooftop_pv.sql"),
+    include_str!("../../migrations/015_generation_mix.sql"),
+    include_str!("../../migrations/016_carbon_alerts.sql"),
+    include_str!("../../migrations/017_user_regions.sql"),
+    include_str!("../../migrations/018_quiet_hours.sql"),
+    include_str!("../../migrations/019_alert_state.sql"),
+    include_str!("../../migrations/020_notify_channels.sql"),
+    include_str!("../../migrations/021_digest_threshold_unit.sql"),
+    include_str!("../../migrations/022_dispatch_retry.sql"),
+    include_str!("../../migrations/023_backfill_retention.sql"),
+    include_str!("../../migrations/024_alert_log_dropped.sql"),
+    include_str!("../../migrations/025_notice_retry.sql"),
+];
+
 pub struct Db {
     conn: Mutex<Connection>,
 }
@@ -11,217 +50,1793 @@ pub struct DailyStats {
     pub max_price: f64,
     pub avg_price: f64,
     pub negative_hours: f64,
+    pub peak_demand: Option<f64>,
+    pub min_margin: Option<f64>,
+}
+
+/// Pre-dispatch error sums for one lead-time bucket, over one or more days.
+#[derive(Default)]
+pub struct ForecastScore {
+    pub samples: i64,
+    /// Sum of |forecast − actual|, $/MWh.
+    pub abs_error: f64,
+    /// Sum of forecast − actual; positive means forecasts ran high.
+    pub error: f64,
+    /// Spike periods forecast as spikes, spikes forecast as normal, and forecast spikes that didn't happen.
+    pub spike_hits: i64,
+    pub spike_misses: i64,
+    pub false_alarms: i64,
+}
+
+impl ForecastScore {
+    pub fn mae(&self) -> f64 {
+        self.abs_error / self.samples.max(1) as f64
+    }
+
+    pub fn bias(&self) -> f64 {
+        self.error / self.samples.max(1) as f64
+    }
+
+    /// Share of actual spikes that were forecast, if there were any.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let spikes = self.spike_hits + self.spike_misses;
+        (spikes > 0).then(|| self.spike_hits as f64 / spikes as f64)
+    }
+}
+
+/// Price statistics over an arbitrary range, for `/history`. Hour counts and the
+/// time-weighted average weight each interval by its length, so 30-minute
+/// intervals from older backfills count six times a 5-minute one.
+pub struct PriceStats {
+    pub intervals: i64,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub mean_price: f64,
+    pub median_price: f64,
+    pub time_weighted_price: f64,
+    pub negative_hours: f64,
+    /// Hours above the threshold passed to `get_price_stats`.
+    pub hours_above: f64,
+}
+
+/// How `get_price_buckets` groups intervals.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// One bucket per local calendar day, keyed "YYYY-MM-DD".
+    Day,
+    /// One bucket per local hour of day across the range, keyed "00".."23".
+    HourOfDay,
+}
+
+/// Aggregates for one day or hour-of-day bucket.
+pub struct PriceBucket {
+    pub key: String,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub mean_price: f64,
+    pub negative_intervals: i64,
+    pub intervals: i64,
+}
+
+/// An alert destination besides the user's Telegram chat.
+pub struct NotifyChannel {
+    pub id: i64,
+    /// "webhook", "email", "ntfy" or "gotify".
+    pub kind: String,
+    /// URL, or the address for email.
+    pub target: String,
+    /// Webhook HMAC key, or the ntfy/Gotify access token.
+    pub secret: Option<String>,
+    /// Alert types routed here; None means every type.
+    pub alert_types: Option<Vec<String>>,
+    /// Why the last delivery failed, cleared by the next success.
+    pub last_error: Option<String>,
+}
+
+fn split_types(types: Option<String>) -> Option<Vec<String>> {
+    types.map(|t| t.split(',').filter(|s| !s.is_empty()).map(String::from).collect())
+}
+
+/// One stored dispatch price, as exported.
+pub struct PriceRow {
+    pub region: String,
+    pub interval_time: String,
+    pub price_mwh: f64,
+    pub demand_mw: Option<f64>,
+    pub available_gen_mw: Option<f64>,
+    pub net_interchange_mw: Option<f64>,
+}
+
+/// One pre-dispatch forecast price from one run, as exported.
+pub struct ForecastRow {
+    pub region: String,
+    pub forecast_time: String,
+    pub price_mwh: f64,
+    pub published_at: String,
+}
+
+/// Successive pre-dispatch runs for one region, oldest run first.
+pub struct ForecastRuns {
+    /// Publish time of each run (market time).
+    pub published: Vec<String>,
+    /// Each forecast period and the price every run gave it (`None` if a run didn't cover it).
+    pub periods: Vec<(String, Vec<Option<f64>>)>,
+}
+
+/// A run of consecutive dispatch intervals missing for one region.
+pub struct DispatchGap {
+    pub region: String,
+    /// First and last missing interval, market time.
+    pub gap_start: String,
+    pub gap_end: String,
+    pub intervals: i64,
+    /// How many of those intervals are still absent (a backfill may have filled some).
+    pub still_missing: i64,
+}
+
+/// A user as seen from one of the regions they follow. Thresholds and `region_active`
+/// belong to that region; everything else is per user.
+#[derive(Clone)]
+pub struct User {
+    pub chat_id: i64,
+    pub region: String,
+    /// The region tariffs, battery plans and the default timezone apply to.
+    pub home_region: String,
+    pub high_alert: f64,
+    pub low_alert: f64,
+    /// False when all alerts are paused.
+    pub is_active: bool,
+    /// False when alerts for this region alone are paused.
+    pub region_active: bool,
+    /// Quiet hours as local "HH:MM"; both set or neither.
+    pub quiet_start: Option<String>,
+    pub quiet_end: Option<String>,
+    /// Queue alerts during quiet hours for a digest instead of dropping them.
+    pub quiet_digest: bool,
+    /// $/MWh the price must move back past a threshold before its alert clears.
+    pub hysteresis: f64,
+    /// Consecutive intervals past a threshold before alerting.
+    pub min_intervals: i64,
+    /// Level this region's high-price event clears below, instead of high minus the band.
+    pub clear_alert: Option<f64>,
+    pub timezone: Option<String>,
+    pub tariff_id: Option<String>,
+    /// Retail thresholds in c/kWh; when set they replace the $/MWh ones.
+    pub high_alert_retail: Option<f64>,
+    pub low_alert_retail: Option<f64>,
+    /// Minimum market notice severity to alert on (`Severity::key`); None means off.
+    pub notice_severity: Option<String>,
+    /// Carbon intensity thresholds in g CO2-e/kWh; None means off.
+    pub carbon_high: Option<f64>,
+    pub carbon_low: Option<f64>,
+    pub created_at: String,
+}
+
+/// Rows read per lock by `for_each_price` and `for_each_forecast`.
+const EXPORT_PAGE: usize = 5_000;
+
+const USER_COLUMNS: &str = "u.chat_id, r.region, r.high_alert, r.low_alert, u.is_active, u.timezone,
+     u.tariff_id, u.high_alert_retail, u.low_alert_retail, u.notice_severity, u.carbon_high, u.carbon_low,
+     u.created_at, u.region, r.is_active, u.quiet_start, u.quiet_end, u.quiet_digest,
+     u.hysteresis, u.min_intervals, r.clear_alert";
+
+/// Users joined to the regions they follow, one row per region.
+const USER_FROM: &str = "users u JOIN user_regions r ON r.chat_id=u.chat_id";
+
+fn user_from_row(row: &rusqlite::Row) -> rusqlite::Result<User> {
+    Ok(User {
+        chat_id: row.get(0)?,
+        region: row.get(1)?,
+        high_alert: row.get(2)?,
+        low_alert: row.get(3)?,
+        is_active: row.get::<_, i32>(4)? != 0,
+        timezone: row.get(5)?,
+        tariff_id: row.get(6)?,
+        high_alert_retail: row.get(7)?,
+        low_alert_retail: row.get(8)?,
+        notice_severity: row.get(9)?,
+        carbon_high: row.get(10)?,
+        carbon_low: row.get(11)?,
+        created_at: row.get(12)?,
+        home_region: row.get(13)?,
+        region_active: row.get::<_, i32>(14)? != 0,
+        quiet_start: row.get(15)?,
+        quiet_end: row.get(16)?,
+        quiet_digest: row.get::<_, i32>(17)? != 0,
+        hysteresis: row.get(18)?,
+        min_intervals: row.get(19)?,
+        clear_alert: row.get(20)?,
+    })
+}
+
+/// Where one threshold alert stands for a user and region.
+#[derive(Default)]
+pub struct AlertState {
+    /// The price is past the threshold and the alert has fired; waiting to clear.
+    pub active: bool,
+    /// Consecutive intervals past the threshold while not yet active.
+    pub streak: i64,
+    /// Last interval this state was advanced for, so an interval only counts once.
+    pub last_interval: Option<String>,
+}
+
+pub struct BatteryProfile {
+    pub capacity_kwh: f64,
+    /// Max charge and discharge power.
+    pub power_kw: f64,
+    /// Round-trip efficiency, 0..1.
+    pub efficiency: f64,
+    /// State of charge kept in reserve, 0..1.
+    pub reserve: f64,
+}
+
+/// Battery cash flows over one or more days. Values are in dollars.
+pub struct Savings {
+    pub days: i64,
+    pub charged_kwh: f64,
+    pub discharged_kwh: f64,
+    /// Net earned at spot prices by following the bot's advice.
+    pub spot_value: f64,
+    /// The same energy flows priced at each day's flat average price.
+    pub flat_value: f64,
+}
+
+impl Savings {
+    pub fn saved(&self) -> f64 {
+        self.spot_value - self.flat_value
+    }
+}
+
+impl User {
+    /// The user's civil timezone: their override if set, else their home region's.
+    pub fn tz(&self) -> Tz {
+        self.timezone
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(|| timezone::region_tz(&self.home_region))
+    }
+
+    pub fn is_home(&self) -> bool {
+        self.region == self.home_region
+    }
+
+    /// Whether `now` falls in the user's quiet hours, which may span midnight.
+    pub fn in_quiet_hours(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let parse = |t: &Option<String>| t.as_deref().and_then(|t| chrono::NaiveTime::parse_from_str(t, "%H:%M").ok());
+        let (Some(start), Some(end)) = (parse(&self.quiet_start), parse(&self.quiet_end)) else { return false };
+        let local = now.with_timezone(&self.tz()).time();
+        if start <= end {
+            local >= start && local < end
+        } else {
+            local >= start || local < end
+        }
+    }
+
+    /// The user's tariff, if it applies to `region`'s network.
+    pub fn tariff_for(&self, region: &str) -> Option<&'static Tariff> {
+        self.tariff_id
+            .as_deref()
+            .and_then(tariff::find)
+            .filter(|t| t.region == region)
+    }
 }
 
-pub struct User {
-    pub chat_id: i64,
-    pub region: String,
-    pub high_alert: f64,
-    pub low_alert: f64,
-    pub is_active: bool,
-    pub created_at: String,
-}
+impl Db {
+    pub fn new(path: &str) -> Result<Self> {
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA busy_timeout=5000;")?;
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(sql)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+        }
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    // ── Users ──
+
+    /// Follow `region`, or stop following it if already followed. A new user's first
+    /// region becomes their home; dropping the home region moves it to the longest-followed
+    /// remaining one. The last region can't be dropped. Returns the followed regions.
+    pub fn toggle_region(&self, chat_id: i64, region: &str) -> Result<Vec<String>> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO users (chat_id, region, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![chat_id, region, now],
+        )?;
+        let followed: Vec<String> = {
+            let mut stmt = tx.prepare("SELECT region FROM user_regions WHERE chat_id=?1 ORDER BY created_at, region")?;
+            let rows = stmt.query_map(params![chat_id], |row| row.get(0))?.collect::<Result<_, _>>()?;
+            rows
+        };
+        if !followed.iter().any(|r| r == region) {
+            tx.execute(
+                "INSERT INTO user_regions (chat_id, region, created_at) VALUES (?1, ?2, ?3)",
+                params![chat_id, region, now],
+            )?;
+        } else if let Some(next) = followed.iter().find(|r| *r != region) {
+            tx.execute("DELETE FROM user_regions WHERE chat_id=?1 AND region=?2", params![chat_id, region])?;
+            tx.execute("DELETE FROM alert_state WHERE chat_id=?1 AND region=?2", params![chat_id, region])?;
+            tx.execute(
+                "UPDATE users SET region=?1, updated_at=?2 WHERE chat_id=?3 AND region=?4",
+                params![next, now, chat_id, region],
+            )?;
+        }
+        tx.commit()?;
+        drop(conn);
+        Ok(self.get_user_regions(chat_id)?.into_iter().map(|u| u.region).collect())
+    }
+
+    /// The user as seen from their home region.
+    pub fn get_user(&self, chat_id: i64) -> Result<Option<User>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("SELECT {USER_COLUMNS} FROM {USER_FROM} WHERE u.chat_id=?1 AND r.region=u.region"),
+            params![chat_id],
+            user_from_row,
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// The user as seen from `region`, if they follow it.
+    pub fn get_user_in(&self, chat_id: i64, region: &str) -> Result<Option<User>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("SELECT {USER_COLUMNS} FROM {USER_FROM} WHERE u.chat_id=?1 AND r.region=?2"),
+            params![chat_id, region],
+            user_from_row,
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// Every region the user follows, home first.
+    pub fn get_user_regions(&self, chat_id: i64) -> Result<Vec<User>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {USER_COLUMNS} FROM {USER_FROM} WHERE u.chat_id=?1
+             ORDER BY r.region<>u.region, r.created_at, r.region"
+        ))?;
+        let users = stmt
+            .query_map(params![chat_id], user_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(users)
+    }
+
+    /// Sets the $/MWh threshold for one region, clearing any retail override if it's home.
+    pub fn update_high_alert(&self, chat_id: i64, region: &str, value: f64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE user_regions SET high_alert=?1 WHERE chat_id=?2 AND region=?3",
+            params![value, chat_id, region],
+        )?;
+        conn.execute(
+            "UPDATE users SET high_alert_retail=NULL, updated_at=?1 WHERE chat_id=?2 AND region=?3",
+            params![now, chat_id, region],
+        )?;
+        Ok(())
+    }
+
+    /// Sets the $/MWh threshold for one region, clearing any retail override if it's home.
+    pub fn update_low_alert(&self, chat_id: i64, region: &str, value: f64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE user_regions SET low_alert=?1 WHERE chat_id=?2 AND region=?3",
+            params![value, chat_id, region],
+        )?;
+        conn.execute(
+            "UPDATE users SET low_alert_retail=NULL, updated_at=?1 WHERE chat_id=?2 AND region=?3",
+            params![now, chat_id, region],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_high_alert_retail(&self, chat_id: i64, cents: f64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET high_alert_retail=?1, updated_at=?2 WHERE chat_id=?3",
+            params![cents, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_low_alert_retail(&self, chat_id: i64, cents: f64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET low_alert_retail=?1, updated_at=?2 WHERE chat_id=?3",
+            params![cents, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    /// Changing tariff also drops retail thresholds, which were relative to the old one.
+    pub fn update_tariff(&self, chat_id: i64, tariff_id: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET tariff_id=?1, high_alert_retail=NULL, low_alert_retail=NULL, updated_at=?2
+             WHERE chat_id=?3",
+            params![tariff_id, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_timezone(&self, chat_id: i64, tz: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET timezone=?1, updated_at=?2 WHERE chat_id=?3",
+            params![tz, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    /// Distinct timezone overrides in use, so the scheduler can run their summaries.
+    pub fn get_custom_timezones(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT DISTINCT timezone FROM users WHERE timezone IS NOT NULL")?;
+        let tzs = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tzs)
+    }
+
+    pub fn get_battery(&self, chat_id: i64) -> Result<Option<BatteryProfile>> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT battery_kwh, battery_power_kw, battery_efficiency, battery_reserve_pct
+                 FROM users WHERE chat_id=?1",
+                params![chat_id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<f64>>(0)?,
+                        row.get::<_, Option<f64>>(1)?,
+                        row.get::<_, Option<f64>>(2)?,
+                        row.get::<_, Option<f64>>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+        Ok(match row {
+            Some((Some(capacity_kwh), Some(power_kw), Some(eff_pct), Some(reserve_pct))) => Some(BatteryProfile {
+                capacity_kwh,
+                power_kw,
+                efficiency: eff_pct / 100.0,
+                reserve: reserve_pct / 100.0,
+            }),
+            _ => None,
+        })
+    }
+
+    pub fn update_battery(&self, chat_id: i64, battery: Option<&BatteryProfile>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET battery_kwh=?1, battery_power_kw=?2, battery_efficiency=?3,
+                    battery_reserve_pct=?4, updated_at=?5
+             WHERE chat_id=?6",
+            params![
+                battery.map(|b| b.capacity_kwh),
+                battery.map(|b| b.power_kw),
+                battery.map(|b| b.efficiency * 100.0),
+                battery.map(|b| b.reserve * 100.0),
+                now,
+                chat_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_active(&self, chat_id: i64, active: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET is_active=?1, updated_at=?2 WHERE chat_id=?3",
+            params![active as i32, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    /// Pause or resume alerts for one followed region.
+    pub fn set_region_active(&self, chat_id: i64, region: &str, active: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE user_regions SET is_active=?1 WHERE chat_id=?2 AND region=?3",
+            params![active as i32, chat_id, region],
+        )?;
+        Ok(())
+    }
+
+    // ── Prices ──
+
+    /// Insert many prices in one transaction; returns how many were new.
+    pub fn insert_prices(&self, recs: &[PriceRecord]) -> Result<usize> {
+        self.insert_price_rows(recs, false)
+    }
+
+    /// `insert_prices` for the backfill CLI: the rows, and any already stored for the same
+    /// intervals, are marked `backfilled` so cleanup keeps them.
+    pub fn insert_backfill_prices(&self, recs: &[PriceRecord]) -> Result<usize> {
+        self.insert_price_rows(recs, true)
+    }
+
+    fn insert_price_rows(&self, recs: &[PriceRecord], backfilled: bool) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO price_history
+                    (region, price_mwh, demand_mw, available_gen_mw, net_interchange_mw, interval_time, fetched_at, backfilled)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            let mut mark_stmt =
+                tx.prepare("UPDATE price_history SET backfilled=1 WHERE region=?1 AND interval_time=?2")?;
+            let mut fcas_stmt = tx.prepare(
+                "INSERT OR IGNORE INTO fcas_price (region, interval_time, service, price_mwh, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for rec in recs {
+                let n = stmt.execute(params![
+                    rec.region,
+                    rec.price,
+                    rec.demand.map(|d| d.demand_mw),
+                    rec.demand.map(|d| d.available_generation_mw),
+                    rec.demand.map(|d| d.net_interchange_mw),
+                    rec.interval_time,
+                    now,
+                    backfilled
+                ])?;
+                if n == 0 && backfilled {
+                    mark_stmt.execute(params![rec.region, rec.interval_time])?;
+                }
+                inserted += n;
+                for (service, price) in &rec.fcas {
+                    fcas_stmt.execute(params![rec.region, rec.interval_time, service, price, now])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn get_latest_price(&self, region: &str) -> Result<Option<(f64, String)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT price_mwh, interval_time FROM price_history
+             WHERE region=?1 ORDER BY interval_time DESC LIMIT 1",
+            params![region],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// REGIONSUM figures for the most recent interval, if they were captured.
+    pub fn get_latest_demand(&self, region: &str) -> Result<Option<RegionDemand>> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT demand_mw, available_gen_mw, net_interchange_mw FROM price_history
+                 WHERE region=?1 ORDER BY interval_time DESC LIMIT 1",
+                params![region],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<f64>>(0)?,
+                        row.get::<_, Option<f64>>(1)?,
+                        row.get::<_, Option<f64>>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        Ok(match row {
+            Some((Some(d), Some(g), Some(n))) => Some(RegionDemand {
+                demand_mw: d,
+                available_generation_mw: g,
+                net_interchange_mw: n,
+            }),
+            _ => None,
+        })
+    }
+
+    pub fn get_previous_price(&self, region: &str) -> Result<Option<f64>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT price_mwh FROM price_history
+             WHERE region=?1 ORDER BY interval_time DESC LIMIT 1 OFFSET 1",
+            params![region],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// Min/max price for intervals in `[start, end)` (market time).
+    pub fn get_daily_range(&self, region: &str, start: &str, end: &str) -> Result<Option<(f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            "SELECT MIN(price_mwh), MAX(price_mwh) FROM price_history
+             WHERE region=?1 AND interval_time>=?2 AND interval_time<?3",
+            params![region, start, end],
+            |row| Ok((row.get::<_, Option<f64>>(0)?, row.get::<_, Option<f64>>(1)?)),
+        )?;
+        match result {
+            (Some(min), Some(max)) => Ok(Some((min, max))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Prices in `[start, end)` in interval order.
+    pub fn get_prices_between(&self, region: &str, start: &str, end: &str) -> Result<Vec<f64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT price_mwh FROM price_history
+             WHERE region=?1 AND interval_time>=?2 AND interval_time<?3
+             ORDER BY interval_time",
+        )?;
+        let prices = stmt
+            .query_map(params![region, start, end], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(prices)
+    }
+
+    /// FCAS prices for the most recent interval that has them, keyed by service.
+    pub fn get_latest_fcas(&self, region: &str) -> Result<Option<(String, HashMap<String, f64>)>> {
+        let conn = self.conn.lock().unwrap();
+        let latest: Option<String> = conn
+            .query_row(
+                "SELECT MAX(interval_time) FROM fcas_price WHERE region=?1",
+                params![region],
+                |row| row.get(0),
+            )?;
+        let Some(time) = latest else { return Ok(None) };
+        let mut stmt = conn.prepare("SELECT service, price_mwh FROM fcas_price WHERE region=?1 AND interval_time=?2")?;
+        let prices = stmt
+            .query_map(params![region, time], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(Some((time, prices)))
+    }
+
+    // ── Market notices ──
+
+    /// Returns false if the notice was already stored.
+    pub fn insert_notice(&self, notice: &MarketNotice) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO market_notice
+             (notice_id, notice_type, reference, reason, issued_at, kind, severity, regions, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                notice.id,
+                notice.notice_type,
+                notice.reference,
+                notice.reason,
+                notice.issued_at,
+                notice.kind.key(),
+                notice.severity().map(|s| s.key()),
+                notice.regions.join(","),
+                now
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    pub fn get_last_notice_id(&self) -> Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row("SELECT MAX(notice_id) FROM market_notice", [], |row| row.get(0))?)
+    }
+
+    /// Market notices waiting to be retried, as notice ID to failed attempts.
+    pub fn get_notice_retries(&self) -> Result<HashMap<i64, i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT notice_id, attempts FROM notice_retry")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Count a failed download of a market notice; returns the attempts so far.
+    pub fn note_notice_failure(&self, notice_id: i64, error: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let attempts = conn.query_row(
+            "INSERT INTO notice_retry (notice_id, attempts, last_error, updated_at) VALUES (?1, 1, ?2, ?3)
+             ON CONFLICT(notice_id) DO UPDATE SET attempts=attempts+1, last_error=?2, updated_at=?3
+             RETURNING attempts",
+            params![notice_id, error, chrono::Utc::now().to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(attempts)
+    }
+
+    pub fn clear_notice_retry(&self, notice_id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM notice_retry WHERE notice_id=?1", params![notice_id])?;
+        Ok(())
+    }
+
+    /// Most recent alertable notices for `region` (including NEM-wide ones), newest first.
+    pub fn get_recent_notices(&self, region: &str, limit: usize) -> Result<Vec<MarketNotice>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT notice_id, notice_type, reference, reason, issued_at FROM market_notice
+             WHERE severity IS NOT NULL AND (regions='' OR ','||regions||',' LIKE '%,'||?1||',%')
+             ORDER BY notice_id DESC LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![region, limit as i64], |row| {
+                Ok(MarketNotice::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn update_notice_severity(&self, chat_id: i64, severity: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET notice_severity=?1, updated_at=?2 WHERE chat_id=?3",
+            params![severity, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    /// `None` for both turns carbon alerts off.
+    pub fn update_carbon_alerts(&self, chat_id: i64, high: Option<f64>, low: Option<f64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET carbon_high=?1, carbon_low=?2, updated_at=?3 WHERE chat_id=?4",
+            params![high, low, now, chat_id],
+        )?;
+        // A removed threshold's event ends with it, so setting it again starts afresh
+        for (alert_type, threshold) in [("carbon_high", high), ("carbon_low", low)] {
+            if threshold.is_none() {
+                conn.execute(
+                    "DELETE FROM alert_state WHERE chat_id=?1 AND alert_type=?2",
+                    params![chat_id, alert_type],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update_hysteresis(&self, chat_id: i64, band: f64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET hysteresis=?1, updated_at=?2 WHERE chat_id=?3",
+            params![band, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_min_intervals(&self, chat_id: i64, intervals: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET min_intervals=?1, updated_at=?2 WHERE chat_id=?3",
+            params![intervals, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    /// `None` goes back to clearing at the high threshold minus the band.
+    pub fn update_clear_alert(&self, chat_id: i64, region: &str, value: Option<f64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE user_regions SET clear_alert=?1 WHERE chat_id=?2 AND region=?3",
+            params![value, chat_id, region],
+        )?;
+        Ok(())
+    }
+
+    /// `None` turns quiet hours off.
+    pub fn update_quiet_hours(&self, chat_id: i64, hours: Option<(&str, &str)>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let (start, end) = hours.unzip();
+        conn.execute(
+            "UPDATE users SET quiet_start=?1, quiet_end=?2, updated_at=?3 WHERE chat_id=?4",
+            params![start, end, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_quiet_digest(&self, chat_id: i64, digest: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE users SET quiet_digest=?1, updated_at=?2 WHERE chat_id=?3",
+            params![digest as i32, now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    // ── Muted alerts and digest ──
+
+    /// Mute `alert_type` if it's on, else unmute it. Returns whether it's now muted.
+    pub fn toggle_muted_alert(&self, chat_id: i64, alert_type: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM muted_alert WHERE chat_id=?1 AND alert_type=?2",
+            params![chat_id, alert_type],
+        )?;
+        if removed == 0 {
+            conn.execute(
+                "INSERT INTO muted_alert (chat_id, alert_type) VALUES (?1, ?2)",
+                params![chat_id, alert_type],
+            )?;
+        }
+        Ok(removed == 0)
+    }
+
+    pub fn get_muted_alerts(&self, chat_id: i64) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT alert_type FROM muted_alert WHERE chat_id=?1")?;
+        let rows = stmt
+            .query_map(params![chat_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn is_alert_muted(&self, chat_id: i64, alert_type: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM muted_alert WHERE chat_id=?1 AND alert_type=?2",
+            params![chat_id, alert_type],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Hold an alert for the digest, replacing any earlier one of the same region and type.
+    pub fn queue_digest(&self, alert: &PendingAlert) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO alert_digest
+                 (chat_id, region, alert_type, price_mwh, text, queued_at, interval_time, threshold, threshold_unit)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                alert.chat_id, alert.region, alert.alert_type, alert.price, alert.text,
+                chrono::Utc::now().to_rfc3339(), alert.interval_time, alert.threshold.map(|t| t.value),
+                alert.threshold.map(|t| t.unit.as_str())
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_digest_chat_ids(&self) -> Result<Vec<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT DISTINCT chat_id FROM alert_digest")?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Remove and return a user's queued alerts, oldest first.
+    pub fn take_digest(&self, chat_id: i64) -> Result<Vec<PendingAlert>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let alerts = {
+            let mut stmt = tx.prepare(
+                "SELECT region, alert_type, price_mwh, text, interval_time, threshold, threshold_unit FROM alert_digest
+                 WHERE chat_id=?1 ORDER BY queued_at",
+            )?;
+            let rows = stmt
+                .query_map(params![chat_id], |row| {
+                    Ok(PendingAlert {
+                        chat_id,
+                        region: row.get(0)?,
+                        alert_type: row.get(1)?,
+                        price: row.get(2)?,
+                        text: row.get(3)?,
+                        interval_time: row.get(4)?,
+                        threshold: match (row.get::<_, Option<f64>>(5)?, row.get::<_, Option<String>>(6)?) {
+                            (Some(value), Some(unit)) => Unit::parse(&unit).map(|u| Threshold::new(value, u)),
+                            _ => None,
+                        },
+                        // Only shown as text in the digest, so not stored
+                        value: None,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        tx.execute("DELETE FROM alert_digest WHERE chat_id=?1", params![chat_id])?;
+        tx.commit()?;
+        Ok(alerts)
+    }
+
+    // ── Notification channels ──
+
+    pub fn add_notify_channel(&self, chat_id: i64, kind: &str, target: &str, secret: Option<&str>) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO notify_channel (chat_id, kind, target, secret, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![chat_id, kind, target, secret, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Returns false if the user has no channel with that id.
+    pub fn remove_notify_channel(&self, chat_id: i64, id: i64) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute("DELETE FROM notify_channel WHERE chat_id=?1 AND id=?2", params![chat_id, id])?;
+        Ok(removed > 0)
+    }
+
+    pub fn get_notify_channels(&self, chat_id: i64) -> Result<Vec<NotifyChannel>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, target, secret, alert_types, last_error FROM notify_channel
+             WHERE chat_id=?1 ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![chat_id], |row| {
+                Ok(NotifyChannel {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    target: row.get(2)?,
+                    secret: row.get(3)?,
+                    alert_types: split_types(row.get(4)?),
+                    last_error: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Set the alert types routed to a channel (None for all). Returns false if the user
+    /// has no channel with that id.
+    pub fn set_channel_alert_types(&self, chat_id: i64, id: i64, types: Option<&[String]>) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE notify_channel SET alert_types=?1 WHERE chat_id=?2 AND id=?3",
+            params![types.map(|t| t.join(",")), chat_id, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn set_channel_error(&self, id: i64, error: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE notify_channel SET last_error=?1 WHERE id=?2", params![error, id])?;
+        Ok(())
+    }
+
+    /// Alert types the Telegram chat receives; None means every type.
+    pub fn get_telegram_alerts(&self, chat_id: i64) -> Result<Option<Vec<String>>> {
+        let conn = self.conn.lock().unwrap();
+        let types = conn
+            .query_row("SELECT telegram_alerts FROM users WHERE chat_id=?1", params![chat_id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(split_types(types))
+    }
+
+    pub fn set_telegram_alerts(&self, chat_id: i64, types: Option<&[String]>) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE users SET telegram_alerts=?1, updated_at=?2 WHERE chat_id=?3",
+            params![types.map(|t| t.join(",")), now, chat_id],
+        )?;
+        Ok(())
+    }
+
+    // ── Generation mix ──
+
+    pub fn insert_mix(&self, recs: &[MixRecord]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO generation_mix (region, interval_time, fuel, mw, emissions_tph, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for rec in recs {
+                inserted += stmt.execute(params![
+                    rec.region, rec.interval_time, rec.fuel.key(), rec.mw, rec.emissions_tph, now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn get_latest_mix(&self, region: &str) -> Result<Option<GenerationMix>> {
+        let conn = self.conn.lock().unwrap();
+        let latest: Option<String> = conn.query_row(
+            "SELECT MAX(interval_time) FROM generation_mix WHERE region=?1",
+            params![region],
+            |row| row.get(0),
+        )?;
+        let Some(time) = latest else { return Ok(None) };
+        Ok(Some(Self::mix_at(&conn, region, time)?))
+    }
+
+    /// The mix for one interval, if its SCADA has been stored.
+    pub fn get_mix(&self, region: &str, interval_time: &str) -> Result<Option<GenerationMix>> {
+        let conn = self.conn.lock().unwrap();
+        let mix = Self::mix_at(&conn, region, interval_time.to_string())?;
+        Ok((!mix.by_fuel.is_empty()).then_some(mix))
+    }
+
+    fn mix_at(conn: &Connection, region: &str, time: String) -> Result<GenerationMix> {
+        let mut stmt = conn.prepare(
+            "SELECT fuel, mw, emissions_tph FROM generation_mix WHERE region=?1 AND interval_time=?2",
+        )?;
+        let rows: HashMap<String, (f64, f64)> = stmt
+            .query_map(params![region, time], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<Result<_, _>>()?;
+        let by_fuel = FUELS
+            .iter()
+            .filter_map(|f: &Fuel| rows.get(f.key()).map(|&(mw, e)| (*f, mw, e)))
+            .collect();
+        let region_mw: Option<f64> = conn
+            .query_row(
+                "SELECT demand_mw + net_interchange_mw FROM price_history WHERE region=?1 AND interval_time=?2",
+                params![region, time],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(GenerationMix { interval_time: time, by_fuel, region_mw })
+    }
+
+    // ── Rooftop PV ──
+
+    /// `source` is "actual" or "forecast"; a later forecast replaces an earlier one.
+    pub fn insert_rooftop_pv(&self, source: &str, recs: &[RooftopPvRecord]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO rooftop_pv (region, interval_time, source, power_mw, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for rec in recs {
+                inserted += stmt.execute(params![rec.region, rec.interval_time, source, rec.power_mw, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Highest rooftop PV half-hour in `(after, before]` as (interval_time, MW).
+    pub fn get_rooftop_peak(&self, region: &str, source: &str, after: &str, before: &str) -> Result<Option<(String, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT interval_time, power_mw FROM rooftop_pv
+             WHERE region=?1 AND source=?2 AND interval_time>?3 AND interval_time<=?4
+             ORDER BY power_mw DESC LIMIT 1",
+            params![region, source, after, before],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// Most recent actual rooftop PV output after `after`, in MW.
+    pub fn get_rooftop_latest(&self, region: &str, after: &str) -> Result<Option<f64>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT power_mw FROM rooftop_pv WHERE region=?1 AND source='actual' AND interval_time>?2
+             ORDER BY interval_time DESC LIMIT 1",
+            params![region, after],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// Highest rooftop PV output, actual or forecast, since `after`: the yardstick for a clear day.
+    pub fn get_rooftop_max(&self, region: &str, after: &str) -> Result<Option<f64>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row(
+            "SELECT MAX(power_mw) FROM rooftop_pv WHERE region=?1 AND interval_time>?2",
+            params![region, after],
+            |row| row.get(0),
+        )?)
+    }
+
+    // ── Interconnectors ──
+
+    pub fn insert_interconnectors(&self, recs: &[InterconnectorRecord]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO interconnector_flow
+                 (interconnector_id, interval_time, mw_flow, export_limit, import_limit, marginal_value, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for rec in recs {
+                inserted += stmt.execute(params![
+                    rec.interconnector_id,
+                    rec.interval_time,
+                    rec.mw_flow,
+                    rec.export_limit,
+                    rec.import_limit,
+                    rec.marginal_value,
+                    now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Every interconnector's flow for one interval.
+    pub fn get_interconnector_flows(&self, interval_time: &str) -> Result<Vec<InterconnectorRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT interconnector_id, interval_time, mw_flow, export_limit, import_limit, marginal_value
+             FROM interconnector_flow WHERE interval_time=?1 ORDER BY interconnector_id",
+        )?;
+        let rows = stmt
+            .query_map(params![interval_time], |row| {
+                Ok(InterconnectorRecord {
+                    interconnector_id: row.get(0)?,
+                    interval_time: row.get(1)?,
+                    mw_flow: row.get(2)?,
+                    export_limit: row.get(3)?,
+                    import_limit: row.get(4)?,
+                    marginal_value: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn get_latest_interconnector_interval(&self) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row("SELECT MAX(interval_time) FROM interconnector_flow", [], |row| row.get(0))?)
+    }
+
+    /// Sum of a region's prices over the `CUMULATIVE_DAYS` ending at `interval_time`.
+    pub fn get_cumulative_price(&self, region: &str, interval_time: &str) -> Result<f64> {
+        let Some(end) = timezone::parse_market(interval_time) else { return Ok(0.0) };
+        let start = timezone::market_str(&(end - chrono::Duration::days(market_params::CUMULATIVE_DAYS)));
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row(
+            "SELECT COALESCE(SUM(price_mwh), 0) FROM price_history
+             WHERE region=?1 AND interval_time>?2 AND interval_time<=?3",
+            params![region, start, interval_time],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// (interval_time, price) in `(after, before]`, matching forecast period-end bounds.
+    pub fn get_interval_prices(&self, region: &str, after: &str, before: &str) -> Result<Vec<(String, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT interval_time, price_mwh FROM price_history
+             WHERE region=?1 AND interval_time>?2 AND interval_time<=?3
+             ORDER BY interval_time",
+        )?;
+        let rows = stmt
+            .query_map(params![region, after, before], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Call `f` for each price in `(start, end]`, for one region or all of them, ordered by
+    /// region then time. Rows are read a page at a time, keyed on the last row seen, and
+    /// the connection is released between pages, so an export of any size neither sits
+    /// in memory nor holds up the scheduler and other commands.
+    pub fn for_each_price(
+        &self, region: Option<&str>, start: &str, end: &str, mut f: impl FnMut(PriceRow) -> Result<()>,
+    ) -> Result<()> {
+        let mut after = (String::new(), start.to_string());
+        loop {
+            let page = {
+                let conn = self.conn.lock().unwrap();
+                let mut stmt = conn.prepare_cached(
+                    "SELECT region, interval_time, price_mwh, demand_mw, available_gen_mw, net_interchange_mw
+                     FROM price_history
+                     WHERE (?1 IS NULL OR region=?1) AND (region, interval_time)>(?2, ?3) AND interval_time>?4
+                       AND interval_time<=?5
+                     ORDER BY region, interval_time LIMIT ?6",
+                )?;
+                let rows = stmt
+                    .query_map(params![region, after.0, after.1, start, end, EXPORT_PAGE as i64], |row| {
+                        Ok(PriceRow {
+                            region: row.get(0)?,
+                            interval_time: row.get(1)?,
+                            price_mwh: row.get(2)?,
+                            demand_mw: row.get(3)?,
+                            available_gen_mw: row.get(4)?,
+                            net_interchange_mw: row.get(5)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                rows
+            };
+            let Some(last) = page.last() else { return Ok(()) };
+            after = (last.region.clone(), last.interval_time.clone());
+            let full = page.len() == EXPORT_PAGE;
+            for row in page {
+                f(row)?;
+            }
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+
+    // ── Forecasts ──
+
+    /// Store one pre-dispatch run in a transaction; returns how many rows were new,
+    /// so zero means this run was already stored.
+    pub fn insert_forecasts(&self, forecasts: &[ForecastRecord], published_at: &str) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO forecast (region, forecast_time, price_mwh, published_at, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for f in forecasts {
+                inserted += stmt.execute(params![f.region, f.forecast_time, f.price, published_at, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn get_forecasts(&self, region: &str, after: &str, before: &str) -> Result<Vec<(String, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT forecast_time, price_mwh FROM forecast
+             WHERE region=?1 AND forecast_time>?2 AND forecast_time<=?3
+             ORDER BY forecast_time, published_at DESC",
+        )?;
+        let rows: Vec<(String, f64)> = stmt
+            .query_map(params![region, after, before], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Keep only the latest published forecast per time slot
+        let mut seen = HashSet::new();
+        Ok(rows.into_iter().filter(|(t, _)| seen.insert(t.clone())).collect())
+    }
+
+    /// Store one P5MIN run; returns how many rows were new.
+    pub fn insert_p5min(&self, forecasts: &[ForecastRecord], run_time: &str) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO p5min_forecast (region, interval_time, price_mwh, run_time, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for f in forecasts {
+                inserted += stmt.execute(params![f.region, f.forecast_time, f.price, run_time, now])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Forecast prices in `(after, before]`: 5-minute P5MIN intervals from the latest
+    /// run first, then 30-minute pre-dispatch periods after the last of those.
+    pub fn get_outlook(&self, region: &str, after: &str, before: &str) -> Result<Vec<(String, f64)>> {
+        let mut points = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT interval_time, price_mwh FROM p5min_forecast
+                 WHERE region=?1 AND interval_time>?2 AND interval_time<=?3
+                   AND run_time=(SELECT MAX(run_time) FROM p5min_forecast WHERE region=?1)
+                 ORDER BY interval_time",
+            )?;
+            let rows = stmt.query_map(params![region, after, before], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<(String, f64)>, _>>()?
+        };
+        let p5_end = points.last().map(|(t, _)| t.clone()).unwrap_or_else(|| after.to_string());
+        points.extend(self.get_forecasts(region, &p5_end, before)?);
+        Ok(points)
+    }
+
+    /// Call `f` for every stored run's forecast of each period in `(start, end]`, ordered by
+    /// region, period, then run. Pages like `for_each_price`.
+    pub fn for_each_forecast(
+        &self, region: Option<&str>, start: &str, end: &str, mut f: impl FnMut(ForecastRow) -> Result<()>,
+    ) -> Result<()> {
+        let mut after = (String::new(), start.to_string(), String::new());
+        loop {
+            let page = {
+                let conn = self.conn.lock().unwrap();
+                let mut stmt = conn.prepare_cached(
+                    "SELECT region, forecast_time, price_mwh, published_at FROM forecast
+                     WHERE (?1 IS NULL OR region=?1) AND (region, forecast_time, published_at)>(?2, ?3, ?4)
+                       AND forecast_time>?5 AND forecast_time<=?6
+                     ORDER BY region, forecast_time, published_at LIMIT ?7",
+                )?;
+                let rows = stmt
+                    .query_map(params![region, after.0, after.1, after.2, start, end, EXPORT_PAGE as i64], |row| {
+                        Ok(ForecastRow {
+                            region: row.get(0)?,
+                            forecast_time: row.get(1)?,
+                            price_mwh: row.get(2)?,
+                            published_at: row.get(3)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                rows
+            };
+            let Some(last) = page.last() else { return Ok(()) };
+            after = (last.region.clone(), last.forecast_time.clone(), last.published_at.clone());
+            let full = page.len() == EXPORT_PAGE;
+            for row in page {
+                f(row)?;
+            }
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+
+    /// How the last `runs` pre-dispatch runs forecast each period after `after`.
+    pub fn get_forecast_runs(&self, region: &str, after: &str, runs: usize) -> Result<ForecastRuns> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT published_at FROM forecast WHERE region=?1 ORDER BY published_at DESC LIMIT ?2",
+        )?;
+        let mut published = stmt
+            .query_map(params![region, runs as i64], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        published.reverse();
+        let oldest = match published.first() {
+            Some(p) => p.clone(),
+            None => return Ok(ForecastRuns { published, periods: Vec::new() }),
+        };
 
-impl Db {
-    pub fn new(path: &str) -> Result<Self> {
-        if let Some(parent) = std::path::Path::new(path).parent() {
-            if !parent.as_os_str().is_empty() {
-                std::fs::create_dir_all(parent)?;
+        let mut stmt = conn.prepare(
+            "SELECT forecast_time, published_at, price_mwh FROM forecast
+             WHERE region=?1 AND forecast_time>?2 AND published_at>=?3
+             ORDER BY forecast_time",
+        )?;
+        let rows = stmt
+            .query_map(params![region, after, oldest], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut periods: Vec<(String, Vec<Option<f64>>)> = Vec::new();
+        for (time, run, price) in rows {
+            if periods.last().map(|(t, _)| t != &time).unwrap_or(true) {
+                periods.push((time, vec![None; published.len()]));
+            }
+            if let (Some(i), Some((_, prices))) = (published.iter().position(|p| *p == run), periods.last_mut()) {
+                prices[i] = Some(price);
             }
         }
-        let conn = Connection::open(path)?;
-        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA busy_timeout=5000;")?;
-        conn.execute_batch(include_str!("../../migrations/001_init.sql"))?;
-        Ok(Self { conn: Mutex::new(conn) })
+        Ok(ForecastRuns { published, periods })
     }
 
-    // ── Users ──
+    /// Latest stored interval per region.
+    pub fn get_last_intervals(&self) -> Result<HashMap<String, String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT region, MAX(interval_time) FROM price_history GROUP BY region")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
 
-    pub fn upsert_user(&self, chat_id: i64, region: &str) -> Result<()> {
+    /// Stored interval times for a region in `(after, before)`.
+    pub fn get_interval_times(&self, region: &str, after: &str, before: &str) -> Result<HashSet<String>> {
         let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        conn.execute(
-            "INSERT INTO users (chat_id, region, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?3)
-             ON CONFLICT(chat_id) DO UPDATE SET region=?2, updated_at=?3",
-            params![chat_id, region, now],
+        let mut stmt = conn.prepare(
+            "SELECT interval_time FROM price_history
+             WHERE region=?1 AND interval_time>?2 AND interval_time<?3",
+        )?;
+        let rows = stmt
+            .query_map(params![region, after, before], |row| row.get(0))?
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(rows)
+    }
+
+    // ── Dispatch gaps ──
+
+    /// Record a gap; returns false if it was already known.
+    pub fn insert_gap(&self, region: &str, gap_start: &str, gap_end: &str, intervals: i64) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let n = conn.execute(
+            "INSERT OR IGNORE INTO dispatch_gaps (region, gap_start, gap_end, intervals, detected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![region, gap_start, gap_end, intervals, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Whether any recorded gap overlapping `(after, before]` still has intervals missing.
+    pub fn has_open_gaps(&self, after: &str, before: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let open: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM dispatch_gaps g
+             WHERE g.gap_end>?1 AND g.gap_start<=?2
+               AND g.intervals > (SELECT COUNT(*) FROM price_history p
+                                  WHERE p.region=g.region AND p.interval_time BETWEEN g.gap_start AND g.gap_end)",
+            params![after, before],
+            |row| row.get(0),
+        )?;
+        Ok(open > 0)
+    }
+
+    /// Dispatch files waiting to be retried, as interval time to failed attempts.
+    pub fn get_dispatch_retries(&self) -> Result<HashMap<String, i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT interval_time, attempts FROM dispatch_retry")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Count a failed download of an interval's file; returns the attempts so far.
+    pub fn note_dispatch_failure(&self, interval_time: &str, error: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let attempts = conn.query_row(
+            "INSERT INTO dispatch_retry (interval_time, attempts, last_error, updated_at) VALUES (?1, 1, ?2, ?3)
+             ON CONFLICT(interval_time) DO UPDATE SET attempts=attempts+1, last_error=?2, updated_at=?3
+             RETURNING attempts",
+            params![interval_time, error, chrono::Utc::now().to_rfc3339()],
+            |row| row.get(0),
         )?;
+        Ok(attempts)
+    }
+
+    pub fn clear_dispatch_retry(&self, interval_time: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM dispatch_retry WHERE interval_time=?1", params![interval_time])?;
         Ok(())
     }
 
-    pub fn get_user(&self, chat_id: i64) -> Result<Option<User>> {
+    pub fn get_recent_gaps(&self, limit: i64) -> Result<Vec<DispatchGap>> {
         let conn = self.conn.lock().unwrap();
-        conn.query_row(
-            "SELECT chat_id, region, high_alert, low_alert, is_active, created_at
-             FROM users WHERE chat_id=?1",
-            params![chat_id],
-            |row| {
-                Ok(User {
-                    chat_id: row.get(0)?,
-                    region: row.get(1)?,
-                    high_alert: row.get(2)?,
-                    low_alert: row.get(3)?,
-                    is_active: row.get::<_, i32>(4)? != 0,
-                    created_at: row.get(5)?,
+        let mut stmt = conn.prepare(
+            "SELECT g.region, g.gap_start, g.gap_end, g.intervals,
+                    g.intervals - (SELECT COUNT(*) FROM price_history p
+                                   WHERE p.region=g.region AND p.interval_time BETWEEN g.gap_start AND g.gap_end)
+             FROM dispatch_gaps g ORDER BY g.gap_start DESC, g.region LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit], |row| {
+                Ok(DispatchGap {
+                    region: row.get(0)?,
+                    gap_start: row.get(1)?,
+                    gap_end: row.get(2)?,
+                    intervals: row.get(3)?,
+                    still_missing: row.get(4)?,
                 })
-            },
-        )
-        .optional()
-        .map_err(Into::into)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
     }
 
-    pub fn update_high_alert(&self, chat_id: i64, value: f64) -> Result<()> {
+    // ── Backfill ──
+
+    pub fn is_backfilled(&self, source: &str, period: &str) -> Result<bool> {
         let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE users SET high_alert=?1, updated_at=?2 WHERE chat_id=?3",
-            params![value, now, chat_id],
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM backfill_progress WHERE source=?1 AND period=?2",
+            params![source, period],
+            |row| row.get(0),
         )?;
-        Ok(())
+        Ok(count > 0)
     }
 
-    pub fn update_low_alert(&self, chat_id: i64, value: f64) -> Result<()> {
+    pub fn mark_backfilled(&self, source: &str, period: &str, rows: usize) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
-            "UPDATE users SET low_alert=?1, updated_at=?2 WHERE chat_id=?3",
-            params![value, now, chat_id],
+            "INSERT OR REPLACE INTO backfill_progress (source, period, rows, completed_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![source, period, rows as i64, chrono::Utc::now().to_rfc3339()],
         )?;
         Ok(())
     }
 
-    pub fn set_active(&self, chat_id: i64, active: bool) -> Result<()> {
+    /// Every stored version of each forecast period in `(after, before]`:
+    /// (forecast_time, published_at, price).
+    pub fn get_forecast_versions(&self, region: &str, after: &str, before: &str) -> Result<Vec<(String, String, f64)>> {
         let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE users SET is_active=?1, updated_at=?2 WHERE chat_id=?3",
-            params![active as i32, now, chat_id],
+        let mut stmt = conn.prepare(
+            "SELECT forecast_time, published_at, price_mwh FROM forecast
+             WHERE region=?1 AND forecast_time>?2 AND forecast_time<=?3",
         )?;
-        Ok(())
+        let rows = stmt
+            .query_map(params![region, after, before], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
     }
 
-    // ── Prices ──
+    // ── Forecast accuracy ──
 
-    pub fn insert_price(&self, region: &str, price: f64, interval_time: &str) -> Result<()> {
+    pub fn upsert_accuracy(&self, region: &str, date: &str, bucket: &str, s: &ForecastScore) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR IGNORE INTO price_history (region, price_mwh, interval_time, fetched_at)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![region, price, interval_time, chrono::Utc::now().to_rfc3339()],
+            "INSERT OR REPLACE INTO forecast_accuracy
+                (region, date, lead_bucket, samples, abs_error, error, spike_hits, spike_misses, false_alarms, computed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                region, date, bucket, s.samples, s.abs_error, s.error, s.spike_hits, s.spike_misses,
+                s.false_alarms, chrono::Utc::now().to_rfc3339()
+            ],
         )?;
         Ok(())
     }
 
-    pub fn get_latest_price(&self, region: &str) -> Result<Option<(f64, String)>> {
+    pub fn get_accuracy_dates(&self, region: &str, since: &str) -> Result<HashSet<String>> {
         let conn = self.conn.lock().unwrap();
-        conn.query_row(
-            "SELECT price_mwh, interval_time FROM price_history
-             WHERE region=?1 ORDER BY interval_time DESC LIMIT 1",
-            params![region],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .optional()
-        .map_err(Into::into)
+        let mut stmt = conn.prepare("SELECT DISTINCT date FROM forecast_accuracy WHERE region=?1 AND date>=?2")?;
+        let dates = stmt
+            .query_map(params![region, since], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(dates)
     }
 
-    pub fn get_previous_price(&self, region: &str) -> Result<Option<f64>> {
+    /// Scores summed per lead bucket for market dates on or after `since` (YYYY-MM-DD).
+    pub fn get_accuracy(&self, region: &str, since: &str) -> Result<HashMap<String, ForecastScore>> {
         let conn = self.conn.lock().unwrap();
-        conn.query_row(
-            "SELECT price_mwh FROM price_history
-             WHERE region=?1 ORDER BY interval_time DESC LIMIT 1 OFFSET 1",
-            params![region],
-            |row| row.get(0),
-        )
-        .optional()
-        .map_err(Into::into)
+        let mut stmt = conn.prepare(
+            "SELECT lead_bucket, SUM(samples), SUM(abs_error), SUM(error),
+                    SUM(spike_hits), SUM(spike_misses), SUM(false_alarms)
+             FROM forecast_accuracy WHERE region=?1 AND date>=?2 GROUP BY lead_bucket",
+        )?;
+        let rows = stmt
+            .query_map(params![region, since], |row| {
+                Ok((
+                    row.get(0)?,
+                    ForecastScore {
+                        samples: row.get(1)?,
+                        abs_error: row.get(2)?,
+                        error: row.get(3)?,
+                        spike_hits: row.get(4)?,
+                        spike_misses: row.get(5)?,
+                        false_alarms: row.get(6)?,
+                    },
+                ))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(rows)
+    }
+
+    // ── FCAS alerts ──
+
+    /// Set or clear (`None`) a user's alert threshold for one FCAS service.
+    pub fn update_fcas_alert(&self, chat_id: i64, service: &str, threshold: Option<f64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        match threshold {
+            Some(t) => conn.execute(
+                "INSERT OR REPLACE INTO fcas_alert (chat_id, service, threshold) VALUES (?1, ?2, ?3)",
+                params![chat_id, service, t],
+            )?,
+            None => conn.execute(
+                "DELETE FROM fcas_alert WHERE chat_id=?1 AND service=?2",
+                params![chat_id, service],
+            )?,
+        };
+        Ok(())
     }
 
-    pub fn get_daily_range(&self, region: &str, today_prefix: &str) -> Result<Option<(f64, f64)>> {
+    pub fn get_fcas_alerts(&self, chat_id: i64) -> Result<Vec<(String, f64)>> {
         let conn = self.conn.lock().unwrap();
-        let result = conn.query_row(
-            "SELECT MIN(price_mwh), MAX(price_mwh) FROM price_history
-             WHERE region=?1 AND interval_time LIKE ?2",
-            params![region, format!("{today_prefix}%")],
-            |row| Ok((row.get::<_, Option<f64>>(0)?, row.get::<_, Option<f64>>(1)?)),
+        let mut stmt = conn.prepare("SELECT service, threshold FROM fcas_alert WHERE chat_id=?1 ORDER BY service")?;
+        let rows = stmt
+            .query_map(params![chat_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// FCAS thresholds of active users whose home region this is, keyed by chat_id.
+    /// A paused home region pauses them too.
+    pub fn get_fcas_alerts_by_region(&self, region: &str) -> Result<HashMap<i64, Vec<(String, f64)>>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT a.chat_id, a.service, a.threshold FROM fcas_alert a
+             JOIN users u ON u.chat_id=a.chat_id
+             JOIN user_regions r ON r.chat_id=u.chat_id AND r.region=u.region
+             WHERE r.region=?1 AND u.is_active=1 AND r.is_active=1",
         )?;
-        match result {
-            (Some(min), Some(max)) => Ok(Some((min, max))),
-            _ => Ok(None),
+        let mut out: HashMap<i64, Vec<(String, f64)>> = HashMap::new();
+        let rows = stmt.query_map(params![region], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        for row in rows {
+            let (chat_id, service, threshold) = row?;
+            out.entry(chat_id).or_default().push((service, threshold));
         }
+        Ok(out)
     }
 
-    // ── Forecasts ──
+    // ── Savings ──
 
-    pub fn insert_forecast(
-        &self, region: &str, forecast_time: &str, price: f64, published_at: &str,
-    ) -> Result<()> {
+    pub fn upsert_savings(&self, chat_id: i64, region: &str, date: &str, s: &Savings) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR IGNORE INTO forecast (region, forecast_time, price_mwh, published_at, fetched_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![region, forecast_time, price, published_at, chrono::Utc::now().to_rfc3339()],
+            "INSERT OR REPLACE INTO savings_daily
+                (chat_id, region, date, charged_kwh, discharged_kwh, spot_value, flat_value, computed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                chat_id, region, date, s.charged_kwh, s.discharged_kwh, s.spot_value, s.flat_value,
+                chrono::Utc::now().to_rfc3339()
+            ],
         )?;
         Ok(())
     }
 
-    pub fn get_forecasts(&self, region: &str, after: &str, before: &str) -> Result<Vec<(String, f64)>> {
+    pub fn get_savings_dates(&self, chat_id: i64, since: &str) -> Result<HashSet<String>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT forecast_time, price_mwh FROM forecast
-             WHERE region=?1 AND forecast_time>?2 AND forecast_time<=?3
-             ORDER BY forecast_time, published_at DESC",
-        )?;
-        let rows: Vec<(String, f64)> = stmt
-            .query_map(params![region, after, before], |row| {
-                Ok((row.get(0)?, row.get(1)?))
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
+        let mut stmt = conn.prepare("SELECT date FROM savings_daily WHERE chat_id=?1 AND date>=?2")?;
+        let dates = stmt
+            .query_map(params![chat_id, since], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(dates)
+    }
 
-        // Keep only the latest published forecast per time slot
-        let mut seen = std::collections::HashSet::new();
-        Ok(rows.into_iter().filter(|(t, _)| seen.insert(t.clone())).collect())
+    /// Totals for local dates on or after `since` (YYYY-MM-DD).
+    pub fn get_savings_total(&self, chat_id: i64, since: &str) -> Result<Option<Savings>> {
+        let conn = self.conn.lock().unwrap();
+        let s = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(charged_kwh), 0), COALESCE(SUM(discharged_kwh), 0),
+                    COALESCE(SUM(spot_value), 0), COALESCE(SUM(flat_value), 0)
+             FROM savings_daily WHERE chat_id=?1 AND date>=?2",
+            params![chat_id, since],
+            |row| {
+                Ok(Savings {
+                    days: row.get(0)?,
+                    charged_kwh: row.get(1)?,
+                    discharged_kwh: row.get(2)?,
+                    spot_value: row.get(3)?,
+                    flat_value: row.get(4)?,
+                })
+            },
+        )?;
+        Ok(if s.days > 0 { Some(s) } else { None })
     }
 
     // ── Alert queries ──
 
+    /// Active users who have opted in to market notice alerts, once per active region.
+    pub fn get_notice_subscribers(&self) -> Result<Vec<User>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {USER_COLUMNS} FROM {USER_FROM}
+             WHERE u.notice_severity IS NOT NULL AND u.is_active=1 AND r.is_active=1"
+        ))?;
+        let users = stmt
+            .query_map([], user_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(users)
+    }
+
+    /// Users following `region` with alerts on, seen from that region.
     pub fn get_active_users_by_region(&self, region: &str) -> Result<Vec<User>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT chat_id, region, high_alert, low_alert, is_active, created_at
-             FROM users WHERE region=?1 AND is_active=1",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {USER_COLUMNS} FROM {USER_FROM} WHERE r.region=?1 AND u.is_active=1 AND r.is_active=1"
+        ))?;
         let users = stmt
-            .query_map(params![region], |row| {
-                Ok(User {
-                    chat_id: row.get(0)?,
-                    region: row.get(1)?,
-                    high_alert: row.get(2)?,
-                    low_alert: row.get(3)?,
-                    is_active: true,
-                    created_at: row.get(5)?,
-                })
-            })?
+            .query_map(params![region], user_from_row)?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(users)
     }
 
-    pub fn log_alert(&self, chat_id: i64, alert_type: &str, price: f64, region: &str) -> Result<()> {
+    /// Returns the log entry's id, for `mark_alert_dropped`.
+    pub fn log_alert(&self, chat_id: i64, alert_type: &str, price: f64, region: &str) -> Result<i64> {
+        self.insert_alert_log(chat_id, alert_type, price, region, false)
+    }
+
+    /// Record an alert that reached none of the user's destinations. It doesn't count
+    /// towards dedup or the rate limit, but closes the event for `high_alert_open`.
+    pub fn log_dropped_alert(&self, chat_id: i64, alert_type: &str, price: f64, region: &str) -> Result<()> {
+        self.insert_alert_log(chat_id, alert_type, price, region, true)?;
+        Ok(())
+    }
+
+    /// Mark a logged alert dropped once every destination it was sent to has failed.
+    pub fn mark_alert_dropped(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE alert_log SET dropped=1 WHERE id=?1", params![id])?;
+        Ok(())
+    }
+
+    fn insert_alert_log(&self, chat_id: i64, alert_type: &str, price: f64, region: &str, dropped: bool) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT INTO alert_log (chat_id, alert_type, price_mwh, region, sent_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![chat_id, alert_type, price, region, chrono::Utc::now().to_rfc3339()],
+            "INSERT INTO alert_log (chat_id, alert_type, price_mwh, region, sent_at, dropped)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![chat_id, alert_type, price, region, chrono::Utc::now().to_rfc3339(), dropped],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Whether the user's last high-price alert for `region` reached them (sent, queued
+    /// for the digest or delivered to a channel) and no all clear, sent or dropped, has
+    /// followed it.
+    pub fn high_alert_open(&self, chat_id: i64, region: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let last: Option<(String, bool)> = conn
+            .query_row(
+                "SELECT alert_type, dropped FROM alert_log
+                 WHERE chat_id=?1 AND region=?2 AND alert_type IN ('high_price', 'all_clear')
+                 ORDER BY id DESC LIMIT 1",
+                params![chat_id, region],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(last.is_some_and(|(kind, dropped)| kind == "high_price" && !dropped))
+    }
+
+    pub fn get_alert_state(&self, chat_id: i64, region: &str, alert_type: &str) -> Result<AlertState> {
+        let conn = self.conn.lock().unwrap();
+        let state = conn
+            .query_row(
+                "SELECT active, streak, last_interval FROM alert_state
+                 WHERE chat_id=?1 AND region=?2 AND alert_type=?3",
+                params![chat_id, region, alert_type],
+                |row| {
+                    Ok(AlertState {
+                        active: row.get::<_, i32>(0)? != 0,
+                        streak: row.get(1)?,
+                        last_interval: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(state.unwrap_or_default())
+    }
+
+    pub fn set_alert_state(&self, chat_id: i64, region: &str, alert_type: &str, state: &AlertState) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO alert_state (chat_id, region, alert_type, active, streak, last_interval, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                chat_id, region, alert_type, state.active as i32, state.streak, state.last_interval,
+                chrono::Utc::now().to_rfc3339()
+            ],
         )?;
         Ok(())
     }
 
-    pub fn was_alert_sent_recently(&self, chat_id: i64, alert_type: &str, minutes: i64) -> Result<bool> {
+    /// Dedup is per region, so a user following several regions hears about each.
+    pub fn was_alert_sent_recently(&self, chat_id: i64, region: &str, alert_type: &str, minutes: i64) -> Result<bool> {
         let cutoff = (chrono::Utc::now() - chrono::Duration::minutes(minutes)).to_rfc3339();
         let conn = self.conn.lock().unwrap();
         let count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM alert_log
-             WHERE chat_id=?1 AND alert_type=?2 AND sent_at>?3",
-            params![chat_id, alert_type, cutoff],
+             WHERE chat_id=?1 AND region=?2 AND alert_type=?3 AND sent_at>?4 AND dropped=0",
+            params![chat_id, region, alert_type, cutoff],
             |row| row.get(0),
         )?;
         Ok(count > 0)
@@ -231,7 +1846,7 @@ impl Db {
         let cutoff = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
         let conn = self.conn.lock().unwrap();
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM alert_log WHERE chat_id=?1 AND sent_at>?2",
+            "SELECT COUNT(*) FROM alert_log WHERE chat_id=?1 AND sent_at>?2 AND dropped=0",
             params![chat_id, cutoff],
             |row| row.get(0),
         )?;
@@ -242,7 +1857,7 @@ impl Db {
         let cutoff = (chrono::Utc::now() - chrono::Duration::days(7)).to_rfc3339();
         let conn = self.conn.lock().unwrap();
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM alert_log WHERE chat_id=?1 AND sent_at>?2",
+            "SELECT COUNT(*) FROM alert_log WHERE chat_id=?1 AND sent_at>?2 AND dropped=0",
             params![chat_id, cutoff],
             |row| row.get(0),
         )?;
@@ -251,15 +1866,17 @@ impl Db {
 
     // ── Daily summary queries ──
 
-    pub fn get_daily_stats(&self, region: &str, date_prefix: &str) -> Result<Option<DailyStats>> {
+    pub fn get_daily_stats(&self, region: &str, start: &str, end: &str) -> Result<Option<DailyStats>> {
         let conn = self.conn.lock().unwrap();
         let result = conn.query_row(
             "SELECT MIN(price_mwh), MAX(price_mwh), AVG(price_mwh),
                     SUM(CASE WHEN price_mwh < 0 THEN 1 ELSE 0 END),
-                    COUNT(*)
+                    COUNT(*),
+                    MAX(demand_mw),
+                    MIN(available_gen_mw - demand_mw - net_interchange_mw)
              FROM price_history
-             WHERE region=?1 AND interval_time LIKE ?2",
-            params![region, format!("{date_prefix}%")],
+             WHERE region=?1 AND interval_time>=?2 AND interval_time<?3",
+            params![region, start, end],
             |row| {
                 Ok((
                     row.get::<_, Option<f64>>(0)?,
@@ -267,40 +1884,162 @@ impl Db {
                     row.get::<_, Option<f64>>(2)?,
                     row.get::<_, i64>(3)?,
                     row.get::<_, i64>(4)?,
+                    row.get::<_, Option<f64>>(5)?,
+                    row.get::<_, Option<f64>>(6)?,
                 ))
             },
         )?;
         match result {
-            (Some(min), Some(max), Some(avg), neg_count, total) if total > 0 => {
+            (Some(min), Some(max), Some(avg), neg_count, total, peak_demand, min_margin) if total > 0 => {
                 Ok(Some(DailyStats {
                     min_price: min,
                     max_price: max,
                     avg_price: avg,
                     negative_hours: neg_count as f64 * 5.0 / 60.0,
+                    peak_demand,
+                    min_margin,
                 }))
             }
             _ => Ok(None),
         }
     }
 
-    pub fn get_daily_peak_time(&self, region: &str, date_prefix: &str) -> Result<Option<String>> {
+    pub fn get_daily_peak_time(&self, region: &str, start: &str, end: &str) -> Result<Option<String>> {
         let conn = self.conn.lock().unwrap();
         conn.query_row(
             "SELECT interval_time FROM price_history
-             WHERE region=?1 AND interval_time LIKE ?2
+             WHERE region=?1 AND interval_time>=?2 AND interval_time<?3
              ORDER BY price_mwh DESC LIMIT 1",
-            params![region, format!("{date_prefix}%")],
+            params![region, start, end],
             |row| row.get(0),
         )
         .optional()
         .map_err(Into::into)
     }
 
+    /// Statistics for `(start, end]`, matching period-end interval times. Each interval lasts
+    /// from the previous stored one, capped at 30 minutes so gaps don't inflate it.
+    pub fn get_price_stats(&self, region: &str, start: &str, end: &str, threshold: f64) -> Result<Option<PriceStats>> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn.query_row(
+            "WITH p AS (
+                 SELECT price_mwh,
+                        COALESCE(MIN(30.0, (julianday(REPLACE(interval_time, '/', '-'))
+                            - julianday(REPLACE(LAG(interval_time) OVER (ORDER BY interval_time), '/', '-'))) * 1440), 5.0) AS minutes
+                 FROM price_history
+                 WHERE region=?1 AND interval_time>?2 AND interval_time<=?3
+             )
+             SELECT COUNT(*), MIN(price_mwh), MAX(price_mwh), AVG(price_mwh),
+                    SUM(price_mwh * minutes) / SUM(minutes),
+                    SUM(CASE WHEN price_mwh < 0 THEN minutes ELSE 0 END) / 60.0,
+                    SUM(CASE WHEN price_mwh > ?4 THEN minutes ELSE 0 END) / 60.0
+             FROM p",
+            params![region, start, end, threshold],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<f64>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, Option<f64>>(3)?,
+                    row.get::<_, Option<f64>>(4)?,
+                    row.get::<_, Option<f64>>(5)?,
+                    row.get::<_, Option<f64>>(6)?,
+                ))
+            },
+        )?;
+        let (intervals, Some(min), Some(max), Some(mean), Some(weighted), Some(negative), Some(above)) = row else {
+            return Ok(None);
+        };
+        if intervals == 0 {
+            return Ok(None);
+        }
+        // Middle value, or the mean of the middle two
+        let median: f64 = conn.query_row(
+            "SELECT AVG(price_mwh) FROM (
+                 SELECT price_mwh FROM price_history
+                 WHERE region=?1 AND interval_time>?2 AND interval_time<=?3
+                 ORDER BY price_mwh LIMIT 2 - ?4 % 2 OFFSET (?4 - 1) / 2
+             )",
+            params![region, start, end, intervals],
+            |row| row.get(0),
+        )?;
+        Ok(Some(PriceStats {
+            intervals,
+            min_price: min,
+            max_price: max,
+            mean_price: mean,
+            median_price: median,
+            time_weighted_price: weighted,
+            negative_hours: negative,
+            hours_above: above,
+        }))
+    }
+
+    /// The `limit` most expensive intervals in `(start, end]`, dearest first.
+    pub fn get_top_intervals(&self, region: &str, start: &str, end: &str, limit: usize) -> Result<Vec<(String, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT interval_time, price_mwh FROM price_history
+             WHERE region=?1 AND interval_time>?2 AND interval_time<=?3
+             ORDER BY price_mwh DESC, interval_time LIMIT ?4",
+        )?;
+        let rows = stmt
+            .query_map(params![region, start, end, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Prices in `(start, end]` grouped by local day or hour of day in `tz`. An interval
+    /// belongs to the hour it started in, at the offset in force then, so a range across a
+    /// daylight-saving change keeps each interval in its own local hour and day.
+    pub fn get_price_buckets(
+        &self, region: &str, start: &str, end: &str, grouping: Grouping, tz: Tz,
+    ) -> Result<Vec<PriceBucket>> {
+        let format = match grouping {
+            Grouping::Day => "%Y-%m-%d",
+            Grouping::HourOfDay => "%H",
+        };
+        let rows: Vec<(String, f64)> = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT interval_time, price_mwh FROM price_history
+                 WHERE region=?1 AND interval_time>?2 AND interval_time<=?3",
+            )?;
+            let rows = stmt
+                .query_map(params![region, start, end], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        let mut buckets: BTreeMap<String, PriceBucket> = BTreeMap::new();
+        for (interval_time, price) in rows {
+            let Some(local) = timezone::to_local(&interval_time, tz) else { continue };
+            let key = (local - chrono::Duration::minutes(5)).format(format).to_string();
+            let bucket = buckets.entry(key.clone()).or_insert(PriceBucket {
+                key,
+                min_price: f64::INFINITY,
+                max_price: f64::NEG_INFINITY,
+                mean_price: 0.0,
+                negative_intervals: 0,
+                intervals: 0,
+            });
+            bucket.min_price = bucket.min_price.min(price);
+            bucket.max_price = bucket.max_price.max(price);
+            // Running sum until every row is in
+            bucket.mean_price += price;
+            bucket.negative_intervals += i64::from(price < 0.0);
+            bucket.intervals += 1;
+        }
+        Ok(buckets
+            .into_values()
+            .map(|b| PriceBucket { mean_price: b.mean_price / b.intervals as f64, ..b })
+            .collect())
+    }
+
     pub fn count_alerts_last_24h(&self, chat_id: i64) -> Result<i64> {
         let cutoff = (chrono::Utc::now() - chrono::Duration::hours(24)).to_rfc3339();
         let conn = self.conn.lock().unwrap();
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM alert_log WHERE chat_id=?1 AND sent_at>?2",
+            "SELECT COUNT(*) FROM alert_log WHERE chat_id=?1 AND sent_at>?2 AND dropped=0",
             params![chat_id, cutoff],
             |row| row.get(0),
         )?;
@@ -309,11 +2048,324 @@ impl Db {
 
     pub fn cleanup_old_records(&self) -> Result<()> {
         let cutoff_90d = (chrono::Utc::now() - chrono::Duration::days(90)).to_rfc3339();
-        let cutoff_7d = (chrono::Utc::now() - chrono::Duration::days(7)).to_rfc3339();
+        // Forecasts are kept long enough for the accuracy job to catch up after downtime
+        let cutoff_14d = (chrono::Utc::now() - chrono::Duration::days(14)).to_rfc3339();
+        let cutoff_2d = (chrono::Utc::now() - chrono::Duration::days(2)).to_rfc3339();
+        let market_90d = timezone::market_str(&(timezone::now_market() - chrono::Duration::days(90)));
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM price_history WHERE fetched_at<?1", params![cutoff_90d])?;
+        // By interval time, so rows fetched late don't outstay the rest; backfilled history stays
+        conn.execute("DELETE FROM price_history WHERE backfilled=0 AND interval_time<?1", params![market_90d])?;
+        conn.execute("DELETE FROM fcas_price WHERE fetched_at<?1", params![cutoff_90d])?;
+        conn.execute("DELETE FROM interconnector_flow WHERE fetched_at<?1", params![cutoff_90d])?;
+        conn.execute("DELETE FROM market_notice WHERE fetched_at<?1", params![cutoff_90d])?;
+        conn.execute("DELETE FROM rooftop_pv WHERE fetched_at<?1", params![cutoff_90d])?;
+        conn.execute("DELETE FROM generation_mix WHERE fetched_at<?1", params![cutoff_90d])?;
+        conn.execute("DELETE FROM dispatch_retry WHERE updated_at<?1", params![cutoff_2d])?;
+        conn.execute("DELETE FROM notice_retry WHERE updated_at<?1", params![cutoff_2d])?;
         conn.execute("DELETE FROM alert_log WHERE sent_at<?1", params![cutoff_90d])?;
-        conn.execute("DELETE FROM forecast WHERE fetched_at<?1", params![cutoff_7d])?;
+        // Well past `savings::LOOKBACK_DAYS`, so pruned days are never recomputed
+        let date_90d = (chrono::Utc::now() - chrono::Duration::days(90)).format("%Y-%m-%d").to_string();
+        conn.execute("DELETE FROM savings_daily WHERE date<?1", params![date_90d])?;
+        conn.execute("DELETE FROM dispatch_gaps WHERE detected_at<?1", params![cutoff_90d])?;
+        conn.execute("DELETE FROM forecast WHERE fetched_at<?1", params![cutoff_14d])?;
+        conn.execute("DELETE FROM p5min_forecast WHERE fetched_at<?1", params![cutoff_2d])?;
         Ok(())
     }
 }
+
+/// Setup shared by the DB-backed tests here and in the engine and bot modules.
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A dispatch price without demand or FCAS figures.
+    pub(crate) fn price(region: &str, interval_time: &str, price: f64) -> PriceRecord {
+        PriceRecord { region: region.into(), price, interval_time: interval_time.into(), demand: None, fcas: Vec::new() }
+    }
+
+    /// An in-memory database where chat 1 follows `regions`, the first as its home region.
+    pub(crate) fn db_following(regions: &[&str]) -> Db {
+        let db = Db::new(":memory:").unwrap();
+        for region in regions {
+            db.toggle_region(1, region).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn price_stats_weight_by_interval_length() {
+        let db = Db::new(":memory:").unwrap();
+        db.insert_prices(&[
+            price("NSW1", "2026/01/15 00:05:00", -20.0),
+            price("NSW1", "2026/01/15 00:10:00", 40.0),
+            price("NSW1", "2026/01/15 00:15:00", 60.0),
+            // After a 30-minute gap, as in pre-5-minute-settlement data
+            price("NSW1", "2026/01/15 00:45:00", 400.0),
+            price("VIC1", "2026/01/15 00:10:00", 9000.0),
+        ])
+        .unwrap();
+        let stats = db.get_price_stats("NSW1", "2026/01/15 00:00:00", "2026/01/16 00:00:00", 300.0).unwrap().unwrap();
+        assert_eq!(stats.intervals, 4);
+        assert_eq!((stats.min_price, stats.max_price), (-20.0, 400.0));
+        assert_eq!(stats.mean_price, 120.0);
+        assert_eq!(stats.median_price, 50.0);
+        // 5 + 5 + 5 + 30 minutes (julianday is only good to a few microseconds)
+        assert!((stats.time_weighted_price - (-20.0 * 5.0 + 40.0 * 5.0 + 60.0 * 5.0 + 400.0 * 30.0) / 45.0).abs() < 1e-3);
+        assert!((stats.negative_hours - 5.0 / 60.0).abs() < 1e-6);
+        assert!((stats.hours_above - 0.5).abs() < 1e-6);
+
+        assert!(db.get_price_stats("QLD1", "2026/01/15 00:00:00", "2026/01/16 00:00:00", 300.0).unwrap().is_none());
+    }
+
+    #[test]
+    fn buckets_follow_the_offset_at_each_interval() {
+        let db = Db::new(":memory:").unwrap();
+        // Sydney leaves daylight saving at 3am AEDT on 5 April 2026 (2am market time)
+        db.insert_prices(&[
+            price("NSW1", "2026/04/03 23:30:00", 50.0), // 00:30 AEDT on the 4th
+            price("NSW1", "2026/04/04 23:05:00", 10.0), // 00:05 AEDT
+            price("NSW1", "2026/04/05 01:05:00", 20.0), // 02:05 AEDT
+            price("NSW1", "2026/04/05 02:05:00", 30.0), // 02:05 AEST, the repeated hour
+            price("NSW1", "2026/04/05 14:05:00", -5.0), // 14:05 AEST
+            price("NSW1", "2026/04/05 23:05:00", 40.0), // 23:05 AEST, still the 5th
+        ])
+        .unwrap();
+        let sydney = chrono_tz::Australia::Sydney;
+        let (start, _) = timezone::local_day_bounds(sydney, chrono::NaiveDate::from_ymd_opt(2026, 4, 4).unwrap());
+        let (_, end) = timezone::local_day_bounds(sydney, chrono::NaiveDate::from_ymd_opt(2026, 4, 5).unwrap());
+
+        let days = db.get_price_buckets("NSW1", &start, &end, Grouping::Day, sydney).unwrap();
+        let keys: Vec<_> = days.iter().map(|b| (b.key.as_str(), b.intervals)).collect();
+        assert_eq!(keys, [("2026-04-04", 1), ("2026-04-05", 5)]);
+        let fifth = &days[1];
+        assert_eq!((fifth.min_price, fifth.max_price, fifth.mean_price), (-5.0, 40.0, 19.0));
+        assert_eq!(fifth.negative_intervals, 1);
+
+        let hours = db.get_price_buckets("NSW1", &start, &end, Grouping::HourOfDay, sydney).unwrap();
+        let keys: Vec<_> = hours.iter().map(|b| (b.key.as_str(), b.intervals)).collect();
+        assert_eq!(keys, [("00", 2), ("02", 2), ("14", 1), ("23", 1)]);
+    }
+
+    fn forecasts(region: &str, points: &[(&str, f64)]) -> Vec<ForecastRecord> {
+        points.iter().map(|(t, p)| ForecastRecord { region: region.into(), forecast_time: t.to_string(), price: *p }).collect()
+    }
+
+    /// 12 P5MIN intervals from `first`, all at `price`.
+    fn p5min_run(first: &str, price: f64) -> Vec<ForecastRecord> {
+        let first = timezone::parse_market(first).unwrap();
+        (0..12)
+            .map(|i| ForecastRecord {
+                region: "NSW1".into(),
+                forecast_time: timezone::market_str(&(first + chrono::Duration::minutes(5 * i))),
+                price,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn outlook_takes_the_latest_p5min_run_then_predispatch() {
+        let db = Db::new(":memory:").unwrap();
+        db.insert_p5min(&p5min_run("2026/01/15 17:05:00", 1.0), "2026/01/15 17:00:00").unwrap();
+        db.insert_p5min(&p5min_run("2026/01/15 17:10:00", 2.0), "2026/01/15 17:05:00").unwrap();
+        let pd = [("2026/01/15 17:30:00", 10.0), ("2026/01/15 18:00:00", 10.0), ("2026/01/15 18:30:00", 10.0), ("2026/01/15 19:00:00", 10.0)];
+        db.insert_forecasts(&forecasts("NSW1", &pd), "2026/01/15 16:30:00").unwrap();
+        db.insert_forecasts(&forecasts("NSW1", &[("2026/01/15 18:30:00", 20.0)]), "2026/01/15 17:00:00").unwrap();
+
+        // 17:10..18:05 from the 17:05 run, then pre-dispatch only after 18:05
+        let outlook = db.get_outlook("NSW1", "2026/01/15 17:07:00", "2026/01/15 19:00:00").unwrap();
+        assert_eq!(outlook.len(), 14);
+        assert_eq!(outlook[0], ("2026/01/15 17:10:00".to_string(), 2.0));
+        assert_eq!(outlook[11], ("2026/01/15 18:05:00".to_string(), 2.0));
+        assert_eq!(outlook[12], ("2026/01/15 18:30:00".to_string(), 20.0));
+        assert_eq!(outlook[13], ("2026/01/15 19:00:00".to_string(), 10.0));
+        assert_eq!(tariff::outlook_minutes(&outlook)[11..], [5, 30, 30]);
+
+        // A window inside the P5MIN hour is all P5MIN, with nothing from the period ending 17:30
+        let outlook = db.get_outlook("NSW1", "2026/01/15 17:07:00", "2026/01/15 17:30:00").unwrap();
+        assert_eq!(outlook.len(), 5);
+        assert!(outlook.iter().all(|(_, p)| *p == 2.0));
+        // Once the latest run has passed, pre-dispatch covers everything after `after`
+        let outlook = db.get_outlook("NSW1", "2026/01/15 18:05:00", "2026/01/15 19:00:00").unwrap();
+        assert_eq!(outlook, [("2026/01/15 18:30:00".to_string(), 20.0), ("2026/01/15 19:00:00".to_string(), 10.0)]);
+        assert_eq!(db.get_outlook("VIC1", "2026/01/15 17:07:00", "2026/01/15 19:00:00").unwrap(), []);
+    }
+
+    #[test]
+    fn forecast_runs_line_up_the_latest_runs() {
+        let db = Db::new(":memory:").unwrap();
+        let pd = [("2026/01/15 17:30:00", 90.0), ("2026/01/15 18:00:00", 100.0), ("2026/01/15 18:30:00", 110.0)];
+        db.insert_forecasts(&forecasts("NSW1", &pd), "2026/01/15 16:30:00").unwrap();
+        let pd = [("2026/01/15 17:30:00", 95.0), ("2026/01/15 18:00:00", 300.0)];
+        db.insert_forecasts(&forecasts("NSW1", &pd), "2026/01/15 17:00:00").unwrap();
+        let pd = [("2026/01/15 18:00:00", 120.0), ("2026/01/15 18:30:00", 115.0), ("2026/01/15 19:00:00", 105.0)];
+        db.insert_forecasts(&forecasts("NSW1", &pd), "2026/01/15 17:30:00").unwrap();
+
+        // The two latest runs, oldest first; the 16:30 run and periods up to `after` are left out
+        let runs = db.get_forecast_runs("NSW1", "2026/01/15 17:30:00", 2).unwrap();
+        assert_eq!(runs.published, ["2026/01/15 17:00:00", "2026/01/15 17:30:00"]);
+        assert_eq!(
+            runs.periods,
+            [
+                ("2026/01/15 18:00:00".to_string(), vec![Some(300.0), Some(120.0)]),
+                ("2026/01/15 18:30:00".to_string(), vec![None, Some(115.0)]),
+                ("2026/01/15 19:00:00".to_string(), vec![None, Some(105.0)]),
+            ]
+        );
+
+        let runs = db.get_forecast_runs("NSW1", "2026/01/15 17:00:00", 3).unwrap();
+        assert_eq!(runs.published.len(), 3);
+        assert_eq!(runs.periods[0], ("2026/01/15 17:30:00".to_string(), vec![Some(90.0), Some(95.0), None]));
+
+        let runs = db.get_forecast_runs("VIC1", "2026/01/15 17:00:00", 2).unwrap();
+        assert!(runs.published.is_empty() && runs.periods.is_empty());
+    }
+
+    fn alert(alert_type: &str, price: f64, threshold: Option<Threshold>) -> PendingAlert {
+        PendingAlert {
+            chat_id: 1,
+            alert_type: alert_type.into(),
+            region: "NSW1".into(),
+            price,
+            interval_time: "2026/01/15 23:30:00".into(),
+            threshold,
+            value: None,
+            text: format!("{alert_type} at {price}"),
+        }
+    }
+
+    #[test]
+    fn digest_keeps_the_latest_per_type() {
+        let db = db_following(&["NSW1"]);
+        db.queue_digest(&alert("high_price", 310.0, Some(Threshold::new(30.0, Unit::CentsKwh)))).unwrap();
+        db.queue_digest(&alert("high_price", 420.0, Some(Threshold::new(30.0, Unit::CentsKwh)))).unwrap();
+        db.queue_digest(&alert("spike", 420.0, None)).unwrap();
+        assert_eq!(db.get_digest_chat_ids().unwrap(), [1]);
+
+        let alerts = db.take_digest(1).unwrap();
+        assert_eq!(alerts.len(), 2);
+        let high = alerts.iter().find(|a| a.alert_type == "high_price").unwrap();
+        assert_eq!(high.price, 420.0);
+        assert_eq!(high.threshold, Some(Threshold::new(30.0, Unit::CentsKwh)));
+        assert!(db.take_digest(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fcas_alerts_follow_the_home_region() {
+        let db = db_following(&["NSW1", "SA1"]);
+        db.update_fcas_alert(1, "raise6sec", Some(50.0)).unwrap();
+
+        assert_eq!(db.get_fcas_alerts_by_region("NSW1").unwrap()[&1], [("raise6sec".to_string(), 50.0)]);
+        assert!(db.get_fcas_alerts_by_region("SA1").unwrap().is_empty());
+
+        db.set_region_active(1, "NSW1", false).unwrap();
+        assert!(db.get_fcas_alerts_by_region("NSW1").unwrap().is_empty());
+        db.set_region_active(1, "NSW1", true).unwrap();
+        db.set_active(1, false).unwrap();
+        assert!(db.get_fcas_alerts_by_region("NSW1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn dispatch_retries_count_up_and_clear() {
+        let db = Db::new(":memory:").unwrap();
+        assert_eq!(db.note_dispatch_failure("2026/01/15 00:05:00", "timeout").unwrap(), 1);
+        assert_eq!(db.note_dispatch_failure("2026/01/15 00:05:00", "timeout").unwrap(), 2);
+        assert_eq!(db.note_dispatch_failure("2026/01/15 00:10:00", "502").unwrap(), 1);
+        let retries = db.get_dispatch_retries().unwrap();
+        assert_eq!(retries.get("2026/01/15 00:05:00"), Some(&2));
+        db.clear_dispatch_retry("2026/01/15 00:05:00").unwrap();
+        assert_eq!(db.get_dispatch_retries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn notice_retries_count_up_and_clear() {
+        let db = Db::new(":memory:").unwrap();
+        assert_eq!(db.note_notice_failure(120001, "timeout").unwrap(), 1);
+        assert_eq!(db.note_notice_failure(120001, "timeout").unwrap(), 2);
+        assert_eq!(db.note_notice_failure(120003, "502").unwrap(), 1);
+        assert_eq!(db.get_notice_retries().unwrap().get(&120001), Some(&2));
+        db.clear_notice_retry(120001).unwrap();
+        assert_eq!(db.get_notice_retries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn gaps_stay_open_until_filled() {
+        let db = Db::new(":memory:").unwrap();
+        db.insert_gap("NSW1", "2026/01/15 00:05:00", "2026/01/15 00:10:00", 2).unwrap();
+        let day = ("2026/01/15 00:00:00", "2026/01/16 00:00:00");
+        assert!(db.has_open_gaps(day.0, day.1).unwrap());
+        assert!(!db.has_open_gaps("2026/01/14 00:00:00", "2026/01/15 00:00:00").unwrap());
+
+        db.insert_prices(&[price("NSW1", "2026/01/15 00:05:00", 50.0), price("VIC1", "2026/01/15 00:10:00", 50.0)]).unwrap();
+        assert!(db.has_open_gaps(day.0, day.1).unwrap());
+        db.insert_prices(&[price("NSW1", "2026/01/15 00:10:00", 50.0)]).unwrap();
+        assert!(!db.has_open_gaps(day.0, day.1).unwrap());
+    }
+
+    #[test]
+    fn exports_page_through_every_row_in_order() {
+        let db = Db::new(":memory:").unwrap();
+        let start = timezone::parse_market("2026/01/15 00:00:00").unwrap();
+        let at = |i: usize| timezone::market_str(&(start + chrono::Duration::minutes(5 * i as i64)));
+        let rows: Vec<_> = (1..=EXPORT_PAGE + 2)
+            .flat_map(|i| [price("NSW1", &at(i), i as f64), price("VIC1", &at(i), i as f64)])
+            .collect();
+        db.insert_prices(&rows).unwrap();
+
+        let mut seen = Vec::new();
+        db.for_each_price(None, &at(0), &at(EXPORT_PAGE + 1), |r| {
+            seen.push((r.region, r.interval_time));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(seen.len(), 2 * (EXPORT_PAGE + 1));
+        assert!(seen.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(seen[EXPORT_PAGE], ("NSW1".to_string(), at(EXPORT_PAGE + 1)));
+
+        let mut nsw = 0;
+        db.for_each_price(Some("NSW1"), &at(1), &at(EXPORT_PAGE + 2), |_| {
+            nsw += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(nsw, EXPORT_PAGE + 1);
+    }
+
+    #[test]
+    fn backfilled_history_survives_cleanup() {
+        let db = Db::new(":memory:").unwrap();
+        let old = timezone::market_str(&(timezone::now_market() - chrono::Duration::days(100)));
+        let recent = timezone::market_str(&timezone::now_market());
+        db.insert_backfill_prices(&[price("NSW1", "2024/03/01 12:05:00", 80.0)]).unwrap();
+        db.insert_prices(&[price("NSW1", &old, 70.0), price("VIC1", &old, 60.0), price("NSW1", &recent, 90.0)]).unwrap();
+        // A live row a backfill later covers is kept with it
+        assert_eq!(db.insert_backfill_prices(&[price("VIC1", &old, 60.0)]).unwrap(), 0);
+        db.mark_backfilled("archive", "2024-03-01", 1).unwrap();
+        db.cleanup_old_records().unwrap();
+
+        let kept = |region, time: &str| {
+            let all = db.get_interval_times(region, "2000/01/01 00:00:00", "2100/01/01 00:00:00").unwrap();
+            all.contains(time)
+        };
+        assert!(kept("NSW1", "2024/03/01 12:05:00"));
+        assert!(kept("VIC1", &old));
+        assert!(kept("NSW1", &recent));
+        assert!(!kept("NSW1", &old));
+        assert!(db.is_backfilled("archive", "2024-03-01").unwrap());
+    }
+
+    #[test]
+    fn dropped_alerts_close_events_but_skip_limits() {
+        let db = db_following(&["NSW1"]);
+        assert!(!db.high_alert_open(1, "NSW1").unwrap());
+        db.log_alert(1, "high_price", 500.0, "NSW1").unwrap();
+        assert!(db.high_alert_open(1, "NSW1").unwrap());
+        // An all clear dropped in quiet hours still ends the event
+        db.log_dropped_alert(1, "all_clear", 40.0, "NSW1").unwrap();
+        assert!(!db.high_alert_open(1, "NSW1").unwrap());
+        db.log_dropped_alert(1, "high_price", 500.0, "NSW1").unwrap();
+        assert!(!db.high_alert_open(1, "NSW1").unwrap());
+
+        assert_eq!(db.count_alerts_this_hour(1).unwrap(), 1);
+        assert!(!db.was_alert_sent_recently(1, "NSW1", "all_clear", 30).unwrap());
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{NaiveDate, Timelike};
+use std::collections::HashMap;
+
+use crate::db::repository::ForecastScore;
+use crate::db::Db;
+use crate::timezone;
+
+/// A pre-dispatch period is scored against the average of its six dispatch intervals.
+const INTERVALS_PER_PERIOD: usize = 6;
+
+/// Actual or forecast prices above this count as a spike.
+pub const SPIKE_MWH: f64 = 300.0;
+
+/// How far back the daily job fills in unscored days; forecasts are kept 14 days.
+const LOOKBACK_DAYS: i64 = 7;
+
+/// Lead-time buckets as (upper bound in hours, key), nearest first.
+pub const LEAD_BUCKETS: &[(i64, &str)] = &[(1, "0-1h"), (4, "1-4h"), (12, "4-12h"), (i64::MAX, "12h+")];
+
+/// The bucket quoted in the daily summary: far enough ahead to act on.
+pub const HEADLINE_BUCKET: &str = "1-4h";
+
+fn bucket(lead_minutes: i64) -> Option<&'static str> {
+    if lead_minutes < 0 {
+        return None;
+    }
+    LEAD_BUCKETS
+        .iter()
+        .find(|(hours, _)| lead_minutes < hours.saturating_mul(60))
+        .map(|(_, key)| *key)
+}
+
+/// Score every forecast version for periods ending on one market date.
+pub fn score_day(db: &Db, region: &str, date: NaiveDate) -> HashMap<&'static str, ForecastScore> {
+    let mut scores: HashMap<&'static str, ForecastScore> = HashMap::new();
+    let start = format!("{} 00:00:00", date.format("%Y/%m/%d"));
+    let end = format!("{} 00:00:00", date.succ_opt().unwrap_or(date).format("%Y/%m/%d"));
+
+    // Average the 5-minute prices into the half-hour periods they settle in
+    let mut periods: HashMap<String, Vec<f64>> = HashMap::new();
+    for (time, price) in db.get_interval_prices(region, &start, &end).unwrap_or_default() {
+        let Some(t) = timezone::parse_market(&time) else { continue };
+        // 10:05..10:30 belong to the period ending 10:30
+        let past = (t.minute() % 30) as i64;
+        let period_end = if past == 0 { t } else { t + chrono::Duration::minutes(30 - past) };
+        periods.entry(timezone::market_str(&period_end)).or_default().push(price);
+    }
+    let actual: HashMap<String, f64> = periods
+        .into_iter()
+        .filter(|(_, p)| p.len() == INTERVALS_PER_PERIOD)
+        .map(|(t, p)| (t, p.iter().sum::<f64>() / p.len() as f64))
+        .collect();
+
+    for (forecast_time, published_at, forecast) in db.get_forecast_versions(region, &start, &end).unwrap_or_default() {
+        let Some(&actual) = actual.get(&forecast_time) else { continue };
+        let lead = match (timezone::parse_market(&forecast_time), timezone::parse_market(&published_at)) {
+            (Some(f), Some(p)) => (f - p).num_minutes(),
+            _ => continue,
+        };
+        let Some(key) = bucket(lead) else { continue };
+        let s = scores.entry(key).or_default();
+        s.samples += 1;
+        s.abs_error += (forecast - actual).abs();
+        s.error += forecast - actual;
+        match (forecast > SPIKE_MWH, actual > SPIKE_MWH) {
+            (true, true) => s.spike_hits += 1,
+            (false, true) => s.spike_misses += 1,
+            (true, false) => s.false_alarms += 1,
+            (false, false) => {}
+        }
+    }
+    scores
+}
+
+/// Score any of the last `LOOKBACK_DAYS` completed market days not yet stored.
+pub fn update(db: &Db, regions: &[&str]) {
+    let today = timezone::now_market().date_naive();
+    let since = today - chrono::Duration::days(LOOKBACK_DAYS);
+    for region in regions {
+        let done = db
+            .get_accuracy_dates(region, &since.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        for back in 1..=LOOKBACK_DAYS {
+            let date = today - chrono::Duration::days(back);
+            let key = date.format("%Y-%m-%d").to_string();
+            if done.contains(&key) {
+                continue;
+            }
+            for (bucket, score) in score_day(db, region, date) {
+                if let Err(e) = db.upsert_accuracy(region, &key, bucket, &score) {
+                    tracing::error!(region, error = %e, "Failed to store forecast accuracy");
+                }
+            }
+        }
+    }
+}
+
+/// Rolling scores per lead bucket over the last `days` market days.
+pub fn rolling(db: &Db, region: &str, days: i64) -> HashMap<String, ForecastScore> {
+    let since = timezone::now_market().date_naive() - chrono::Duration::days(days);
+    db.get_accuracy(region, &since.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::parser::ForecastRecord;
+    use crate::db::repository::tests::price;
+
+    fn forecast(db: &Db, forecast_time: &str, published_at: &str, price: f64) {
+        let rec = ForecastRecord { region: "NSW1".into(), forecast_time: forecast_time.into(), price };
+        db.insert_forecasts(&[rec], published_at).unwrap();
+    }
+
+    #[test]
+    fn buckets_split_on_whole_hours() {
+        assert_eq!(bucket(-1), None);
+        assert_eq!(bucket(0), Some("0-1h"));
+        assert_eq!(bucket(59), Some("0-1h"));
+        assert_eq!(bucket(60), Some("1-4h"));
+        assert_eq!(bucket(239), Some("1-4h"));
+        assert_eq!(bucket(240), Some("4-12h"));
+        assert_eq!(bucket(719), Some("4-12h"));
+        assert_eq!(bucket(720), Some("12h+"));
+        assert_eq!(bucket(60 * 24 * 7), Some("12h+"));
+    }
+
+    #[test]
+    fn scores_forecasts_against_half_hour_averages() {
+        let db = Db::new(":memory:").unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+        let mut prices = vec![
+            // Ends the previous day's last period, not the first one of this day
+            price("NSW1", "2026/01/15 00:00:00", 1000.0),
+        ];
+        // 00:05..00:30 settle in the period ending 00:30
+        for (i, p) in [10.0, 20.0, 30.0, 40.0, 50.0, 60.0].into_iter().enumerate() {
+            prices.push(price("NSW1", &format!("2026/01/15 00:{:02}:00", 5 + 5 * i), p));
+        }
+        // Only five intervals for the period ending 01:00, so it isn't scored
+        for m in [35, 40, 45, 50, 55] {
+            prices.push(price("NSW1", &format!("2026/01/15 00:{m:02}:00"), 100.0));
+        }
+        // 23:35..00:00 settle in the day's last period, ending at midnight
+        for m in [35, 40, 45, 50, 55] {
+            prices.push(price("NSW1", &format!("2026/01/15 23:{m:02}:00"), 400.0));
+        }
+        prices.push(price("NSW1", "2026/01/16 00:00:00", 400.0));
+        db.insert_prices(&prices).unwrap();
+
+        forecast(&db, "2026/01/15 00:30:00", "2026/01/15 00:00:00", 45.0);
+        forecast(&db, "2026/01/15 00:30:00", "2026/01/14 22:30:00", 500.0);
+        // Published after the period ended
+        forecast(&db, "2026/01/15 00:30:00", "2026/01/15 00:35:00", 35.0);
+        forecast(&db, "2026/01/15 01:00:00", "2026/01/15 00:30:00", 100.0);
+        forecast(&db, "2026/01/16 00:00:00", "2026/01/15 23:00:00", 350.0);
+        forecast(&db, "2026/01/16 00:00:00", "2026/01/15 12:00:00", 100.0);
+
+        let scores = score_day(&db, "NSW1", date);
+        assert_eq!(scores.len(), 3);
+        assert!(!scores.contains_key("4-12h"));
+
+        let near = &scores["0-1h"];
+        assert_eq!(near.samples, 1);
+        assert!((near.abs_error - 10.0).abs() < 1e-9);
+        assert!((near.error - 10.0).abs() < 1e-9);
+        assert_eq!((near.spike_hits, near.spike_misses, near.false_alarms), (0, 0, 0));
+
+        // 500 against 35 is a false alarm; 350 against 400 is a hit
+        let headline = &scores[HEADLINE_BUCKET];
+        assert_eq!(headline.samples, 2);
+        assert!((headline.abs_error - 515.0).abs() < 1e-9);
+        assert!((headline.error - 415.0).abs() < 1e-9);
+        assert_eq!((headline.spike_hits, headline.spike_misses, headline.false_alarms), (1, 0, 1));
+
+        let far = &scores["12h+"];
+        assert_eq!(far.samples, 1);
+        assert!((far.error + 300.0).abs() < 1e-9);
+        assert_eq!((far.spike_hits, far.spike_misses, far.false_alarms), (0, 1, 0));
+    }
+}
@@ -1,22 +1,164 @@
+use chrono_tz::Tz;
+use std::collections::{HashMap, HashSet};
+
 use crate::bot::messages;
+use crate::data::fcas;
+use crate::data::generators;
+use crate::data::interconnectors::{self, Interconnector};
+use crate::data::market_params::{self, AppStatus};
+use crate::data::notices::{MarketNotice, Severity};
 use crate::data::parser::PriceRecord;
+use crate::db::repository::{AlertState, ForecastRuns, User};
 use crate::db::Db;
+use crate::engine::tariff::{self, RetailPrice};
+use crate::timezone;
+
+/// Supply margin (as % of demand) below which users get a tight-supply warning.
+pub const TIGHT_MARGIN_PCT: f64 = 10.0;
+
+/// A move between consecutive dispatch intervals larger than this is a spike.
+pub const SPIKE_MOVE_MWH: f64 = 100.0;
+
+/// Carbon events clear once intensity is this far back past the threshold (g CO2-e/kWh).
+pub const CARBON_BAND_G: f64 = 25.0;
+
+/// A forecast revision is sharp if it moves at least this far...
+const REVISION_MIN_MWH: f64 = 200.0;
+/// ...and by at least half of the earlier forecast (taken as $100 or more, so
+/// small prices swinging around zero don't count).
+const REVISION_MIN_RATIO: f64 = 0.5;
+/// Notices older than this when first seen (e.g. after downtime) are stored but not alerted.
+const NOTICE_MAX_AGE_HOURS: i64 = 2;
+
+/// Only revisions to periods within this many hours are worth an alert.
+const REVISION_HORIZON_HOURS: i64 = 12;
+
+/// A forecast period that moved between two pre-dispatch runs.
+pub struct Revision {
+    pub forecast_time: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+/// Every alert type as (key, label, mutable), for routing alerts to notification channels;
+/// mutable types can be muted with /quiet. FCAS alerts are "fcas_<service>" and route as "fcas".
+pub const ALERT_TYPES: &[(&str, &str, bool)] = &[
+    ("high_price", "High price", true),
+    ("low_price", "Low price", true),
+    ("spike", "Spike", true),
+    ("forecast", "Forecast warning", true),
+    ("forecast_revision", "Forecast revised", true),
+    ("tight_supply", "Tight supply", true),
+    ("all_clear", "All clear", true),
+    ("app_warning", "Administered pricing likely", false),
+    ("app_start", "Administered pricing", false),
+    ("market_notice", "Market notice", false),
+    ("carbon_high", "High carbon", false),
+    ("carbon_low", "Low carbon", false),
+    ("fcas", "FCAS price", false),
+];
+
+/// Alert types users can mute with /quiet, with their labels.
+pub fn mutable_alerts() -> impl Iterator<Item = (&'static str, &'static str)> {
+    ALERT_TYPES.iter().filter(|(_, _, mutable)| *mutable).map(|&(key, label, _)| (key, label))
+}
 
 pub struct PendingAlert {
     pub chat_id: i64,
-    pub text: String,
     pub alert_type: String,
-    pub price: f64,
     pub region: String,
+    /// Spot price, or the forecast price for forecast alerts ($/MWh).
+    pub price: f64,
+    /// Dispatch interval or forecast period the alert is about (market time, period end);
+    /// the notice's issue time for market notices.
+    pub interval_time: String,
+    /// The user's threshold that was crossed; None for alerts without one.
+    pub threshold: Option<Threshold>,
+    /// What was measured against `threshold`, in its unit (e.g. carbon intensity in g/kWh).
+    pub value: Option<f64>,
+    /// Telegram-ready message, also the body for email and push channels.
+    pub text: String,
+}
+
+/// What a threshold is measured in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    /// Wholesale $/MWh: price and FCAS thresholds.
+    Mwh,
+    /// Retail c/kWh, for users who set their price thresholds with `/tariff`.
+    CentsKwh,
+    /// Grid carbon intensity, g CO2-e/kWh.
+    GramsKwh,
+    /// AEMO's 7-day cumulative price threshold, $.
+    Cumulative,
+}
+
+impl Unit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mwh => "$/MWh",
+            Self::CentsKwh => "c/kWh",
+            Self::GramsKwh => "g/kWh",
+            Self::Cumulative => "$",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [Self::Mwh, Self::CentsKwh, Self::GramsKwh, Self::Cumulative].into_iter().find(|u| u.as_str() == s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Threshold {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Threshold {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Self { value, unit }
+    }
+
+    /// A price threshold: the retail one in c/kWh when the user has set it, else wholesale.
+    pub fn price(wholesale: f64, retail: Option<f64>) -> Self {
+        match retail {
+            Some(c) => Self::new(c, Unit::CentsKwh),
+            None => Self::new(wholesale, Unit::Mwh),
+        }
+    }
+}
+
+impl PendingAlert {
+    /// Routing key: the alert type, with all FCAS services as "fcas".
+    pub fn route_key(&self) -> &str {
+        if self.alert_type.starts_with("fcas_") { "fcas" } else { &self.alert_type }
+    }
+
+    /// Short plain-ASCII headline, e.g. "High price - NSW" (push titles go in HTTP headers).
+    pub fn title(&self) -> String {
+        let label = ALERT_TYPES.iter().find(|(k, _, _)| *k == self.route_key()).map_or("Alert", |(_, l, _)| *l);
+        format!("{} - {}", label, messages::region_display(&self.region))
+    }
+}
+
+/// Interconnectors into `region` running at their limit in this interval: the region
+/// can't import any more, so its price separates from its neighbours.
+fn limited_imports(db: &Db, region: &str, interval_time: &str) -> Vec<&'static Interconnector> {
+    db.get_interconnector_flows(interval_time)
+        .unwrap_or_default()
+        .iter()
+        .filter(|f| f.is_binding())
+        .filter_map(|f| {
+            let ic = interconnectors::find(&f.interconnector_id)?;
+            let into = if f.mw_flow >= 0.0 { ic.to } else { ic.from };
+            (into == region).then_some(ic)
+        })
+        .collect()
 }
 
 /// Analyze latest prices and generate alerts for all affected users.
 pub fn analyze(db: &Db, prices: &[PriceRecord]) -> Vec<PendingAlert> {
     let mut alerts = Vec::new();
-    let today_prefix = chrono::Utc::now()
-        .with_timezone(&chrono_tz::Australia::Brisbane)
-        .format("%Y/%m/%d")
-        .to_string();
 
     for rec in prices {
         let region = &rec.region;
@@ -25,16 +167,21 @@ pub fn analyze(db: &Db, prices: &[PriceRecord]) -> Vec<PendingAlert> {
         // Spike detection: compare with previous price
         let prev_price = db.get_previous_price(region).ok().flatten();
         if let Some(prev) = prev_price {
-            if (current - prev).abs() > 100.0 {
+            if (current - prev).abs() > SPIKE_MOVE_MWH {
+                let limited = limited_imports(db, region, &rec.interval_time);
                 if let Ok(users) = db.get_active_users_by_region(region) {
                     for user in &users {
-                        if can_alert(db, user.chat_id, "spike", 30) {
+                        if can_alert(db, user.chat_id, region, "spike", 30) {
+                            let retail = retail_at(user, region, current, &rec.interval_time, tariff::DISPATCH_MINUTES);
                             alerts.push(PendingAlert {
                                 chat_id: user.chat_id,
-                                text: messages::format_spike_alert(region, prev, current),
+                                text: messages::format_spike_alert(region, prev, current, retail, &limited),
                                 alert_type: "spike".into(),
                                 price: current,
                                 region: region.clone(),
+                                interval_time: rec.interval_time.clone(),
+                                threshold: None,
+                                value: None,
                             });
                         }
                     }
@@ -47,43 +194,197 @@ pub fn analyze(db: &Db, prices: &[PriceRecord]) -> Vec<PendingAlert> {
             Ok(u) => u,
             Err(_) => continue,
         };
-        let daily_range = db.get_daily_range(region, &today_prefix).ok().flatten();
+
+        // Tight supply: early warning ahead of likely spikes
+        if let Some(demand) = rec.demand.filter(|d| d.margin_pct() < TIGHT_MARGIN_PCT) {
+            for user in &users {
+                if can_alert(db, user.chat_id, region, "tight_supply", 60) {
+                    let retail = retail_at(user, region, current, &rec.interval_time, tariff::DISPATCH_MINUTES);
+                    alerts.push(PendingAlert {
+                        chat_id: user.chat_id,
+                        text: messages::format_tight_supply_alert(region, &demand, current, retail),
+                        alert_type: "tight_supply".into(),
+                        price: current,
+                        region: region.clone(),
+                        interval_time: rec.interval_time.clone(),
+                        threshold: None,
+                        value: None,
+                    });
+                }
+            }
+        }
+        // Administered pricing: a warning as the 7-day cumulative price nears the
+        // threshold, and a dedicated alert in the interval it crosses
+        let params = market_params::for_interval(&rec.interval_time);
+        let cumulative = db.get_cumulative_price(region, &rec.interval_time).unwrap_or(0.0);
+        let status = params.app_status(cumulative);
+        let app_alert = match status {
+            AppStatus::Active => {
+                let prev_time = timezone::parse_market(&rec.interval_time)
+                    .map(|t| timezone::market_str(&(t - chrono::Duration::minutes(5))))
+                    .unwrap_or_default();
+                let prev = db.get_cumulative_price(region, &prev_time).unwrap_or(0.0);
+                (params.app_status(prev) != AppStatus::Active).then_some(("app_start", 720))
+            }
+            AppStatus::Likely => Some(("app_warning", 720)),
+            AppStatus::Normal => None,
+        };
+        if let Some((alert_type, dedup)) = app_alert {
+            for user in &users {
+                if can_alert(db, user.chat_id, region, alert_type, dedup) {
+                    alerts.push(PendingAlert {
+                        chat_id: user.chat_id,
+                        text: messages::format_app_alert(region, status, cumulative, params),
+                        alert_type: alert_type.into(),
+                        price: current,
+                        region: region.clone(),
+                        interval_time: rec.interval_time.clone(),
+                        threshold: Some(Threshold::new(params.cumulative_threshold, Unit::Cumulative)),
+                        value: Some(cumulative),
+                    });
+                }
+            }
+        }
+
+        // FCAS: VPP households earn when contingency/regulation prices rise
+        if !rec.fcas.is_empty() {
+            let fcas_alerts = db.get_fcas_alerts_by_region(region).unwrap_or_default();
+            for (chat_id, thresholds) in &fcas_alerts {
+                for (key, threshold) in thresholds {
+                    let Some(service) = fcas::find(key) else { continue };
+                    let Some(&(_, price)) = rec.fcas.iter().find(|(k, _)| k == key) else { continue };
+                    let alert_type = format!("fcas_{}", key);
+                    if price > *threshold && can_alert(db, *chat_id, region, &alert_type, 30) {
+                        alerts.push(PendingAlert {
+                            chat_id: *chat_id,
+                            text: messages::format_fcas_alert(region, service, price, *threshold),
+                            alert_type,
+                            price,
+                            region: region.clone(),
+                            interval_time: rec.interval_time.clone(),
+                            threshold: Some(Threshold::new(*threshold, Unit::Mwh)),
+                            value: Some(price),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Carbon intensity of this interval's mix, when its SCADA has been stored and
+        // the registry covers enough of it
+        let mix = db
+            .get_mix(region, &rec.interval_time)
+            .ok()
+            .flatten()
+            .filter(|m| m.generation_mw() > 0.0 && m.coverage() >= generators::MIN_COVERAGE);
+
+        // "Today" is the user's local calendar day, so cache ranges per timezone
+        let mut ranges: HashMap<Tz, Option<(f64, f64)>> = HashMap::new();
 
         for user in &users {
+            let tz = user.tz();
+            let daily_range = *ranges.entry(tz).or_insert_with(|| {
+                let (start, end) = timezone::local_day_bounds(tz, timezone::local_today(tz));
+                db.get_daily_range(region, &start, &end).ok().flatten()
+            });
+            let retail = retail_at(user, region, current, &rec.interval_time, tariff::DISPATCH_MINUTES);
+            let interval = rec.interval_time.as_str();
+            let high = threshold_step(
+                db, user, "high_price", interval, is_high(user, current, retail), below_high_clear(user, current, retail),
+                || can_alert(db, user.chat_id, region, "high_price", 30),
+            );
+            let low = threshold_step(
+                db, user, "low_price", interval, is_low(user, current, retail), above_low_clear(user, current, retail),
+                || can_alert(db, user.chat_id, region, "low_price", 30),
+            );
+
             // High price alert
-            if current > user.high_alert && can_alert(db, user.chat_id, "high_price", 30) {
+            if high == Transition::Entered {
                 alerts.push(PendingAlert {
                     chat_id: user.chat_id,
-                    text: messages::format_high_alert(region, current, user.high_alert, daily_range),
+                    text: messages::format_high_alert(
+                        region, current, user.high_alert, daily_range, retail, user.high_alert_retail,
+                    ),
                     alert_type: "high_price".into(),
                     price: current,
                     region: region.clone(),
+                    interval_time: rec.interval_time.clone(),
+                    threshold: Some(Threshold::price(user.high_alert, user.high_alert_retail)),
+                    value: threshold_value(user.high_alert_retail, current, retail),
                 });
             }
 
             // Low price alert
-            if current < user.low_alert && can_alert(db, user.chat_id, "low_price", 30) {
+            if low == Transition::Entered {
                 alerts.push(PendingAlert {
                     chat_id: user.chat_id,
-                    text: messages::format_low_alert(region, current),
+                    text: messages::format_low_alert(region, current, retail),
                     alert_type: "low_price".into(),
                     price: current,
                     region: region.clone(),
+                    interval_time: rec.interval_time.clone(),
+                    threshold: Some(Threshold::price(user.low_alert, user.low_alert_retail)),
+                    value: threshold_value(user.low_alert_retail, current, retail),
                 });
             }
 
-            // All clear: was high, now normal
-            if current <= user.high_alert {
-                let was_high = db.was_alert_sent_recently(user.chat_id, "high_price", 180).unwrap_or(false);
-                let already_cleared = db.was_alert_sent_recently(user.chat_id, "all_clear", 60).unwrap_or(false);
-                if was_high && !already_cleared {
+            // Carbon alerts: the same duration and clearing as price thresholds, with a fixed
+            // band in g CO2-e/kWh since the user's band is in $/MWh
+            if let Some(mix) = &mix {
+                let intensity = mix.intensity();
+                if let Some(t) = user.carbon_high {
+                    let step = threshold_step(
+                        db, user, "carbon_high", interval, intensity > t, intensity < t - CARBON_BAND_G,
+                        || can_alert(db, user.chat_id, region, "carbon_high", 30),
+                    );
+                    if step == Transition::Entered {
+                        alerts.push(PendingAlert {
+                            chat_id: user.chat_id,
+                            text: messages::format_carbon_high_alert(region, mix, t),
+                            alert_type: "carbon_high".into(),
+                            price: current,
+                            region: region.clone(),
+                            interval_time: rec.interval_time.clone(),
+                            threshold: Some(Threshold::new(t, Unit::GramsKwh)),
+                            value: Some(intensity),
+                        });
+                    }
+                }
+                if let Some(t) = user.carbon_low {
+                    let step = threshold_step(
+                        db, user, "carbon_low", interval, intensity < t, intensity > t + CARBON_BAND_G,
+                        || can_alert(db, user.chat_id, region, "carbon_low", 30),
+                    );
+                    if step == Transition::Entered {
+                        alerts.push(PendingAlert {
+                            chat_id: user.chat_id,
+                            text: messages::format_carbon_low_alert(region, mix, t),
+                            alert_type: "carbon_low".into(),
+                            price: current,
+                            region: region.clone(),
+                            interval_time: rec.interval_time.clone(),
+                            threshold: Some(Threshold::new(t, Unit::GramsKwh)),
+                            value: Some(intensity),
+                        });
+                    }
+                }
+            }
+
+            // All clear: a high-price event just ended. Only when its alert got through; one
+            // dropped in quiet hours (or rate limited) was never seen, so there's nothing to clear
+            if high == Transition::Cleared {
+                let muted = db.is_alert_muted(user.chat_id, "all_clear").unwrap_or(false);
+                if !muted && db.high_alert_open(user.chat_id, region).unwrap_or(false) {
                     let peak = daily_range.map(|(_, max)| max);
                     alerts.push(PendingAlert {
                         chat_id: user.chat_id,
-                        text: messages::format_all_clear(region, current, peak),
+                        text: messages::format_all_clear(region, current, peak, retail),
                         alert_type: "all_clear".into(),
                         price: current,
                         region: region.clone(),
+                        interval_time: rec.interval_time.clone(),
+                        threshold: None,
+                        value: None,
                     });
                 }
             }
@@ -96,11 +397,11 @@ pub fn analyze(db: &Db, prices: &[PriceRecord]) -> Vec<PendingAlert> {
 /// Check forecasts and generate pre-dispatch warnings.
 pub fn analyze_forecasts(db: &Db, region: &str, current_price: f64) -> Vec<PendingAlert> {
     let mut alerts = Vec::new();
-    let now = chrono::Utc::now().with_timezone(&chrono_tz::Australia::Brisbane);
-    let now_str = now.format("%Y/%m/%d %H:%M:%S").to_string();
-    let later_str = (now + chrono::Duration::hours(1)).format("%Y/%m/%d %H:%M:%S").to_string();
+    let now = timezone::now_market();
+    let now_str = timezone::market_str(&now);
+    let later_str = timezone::market_str(&(now + chrono::Duration::hours(1)));
 
-    let forecasts = match db.get_forecasts(region, &now_str, &later_str) {
+    let forecasts = match db.get_outlook(region, &now_str, &later_str) {
         Ok(f) => f,
         Err(_) => return alerts,
     };
@@ -109,15 +410,20 @@ pub fn analyze_forecasts(db: &Db, region: &str, current_price: f64) -> Vec<Pendi
         Err(_) => return alerts,
     };
 
-    for (fc_time, fc_price) in &forecasts {
+    let lengths = tariff::outlook_minutes(&forecasts);
+    for ((fc_time, fc_price), minutes) in forecasts.iter().zip(lengths) {
         for user in &users {
-            if *fc_price > user.high_alert && can_alert(db, user.chat_id, "forecast", 60) {
+            let retail = retail_at(user, region, *fc_price, fc_time, minutes);
+            if is_high(user, *fc_price, retail) && can_alert(db, user.chat_id, region, "forecast", 60) {
                 alerts.push(PendingAlert {
                     chat_id: user.chat_id,
-                    text: messages::format_forecast_alert(region, *fc_price, fc_time, current_price),
+                    text: messages::format_forecast_alert(region, *fc_price, fc_time, current_price, retail, user.tz()),
                     alert_type: "forecast".into(),
                     price: *fc_price,
                     region: region.into(),
+                    interval_time: fc_time.clone(),
+                    threshold: Some(Threshold::price(user.high_alert, user.high_alert_retail)),
+                    value: threshold_value(user.high_alert_retail, *fc_price, retail),
                 });
             }
         }
@@ -126,8 +432,509 @@ pub fn analyze_forecasts(db: &Db, region: &str, current_price: f64) -> Vec<Pendi
     alerts
 }
 
-fn can_alert(db: &Db, chat_id: i64, alert_type: &str, dedup_minutes: i64) -> bool {
-    let not_dup = !db.was_alert_sent_recently(chat_id, alert_type, dedup_minutes).unwrap_or(true);
+/// Sharp moves between the two latest runs for periods in `(after, before]`, largest first.
+pub fn revisions(runs: &ForecastRuns, after: &str, before: &str) -> Vec<Revision> {
+    if runs.published.len() < 2 {
+        return Vec::new();
+    }
+    let (prev, last) = (runs.published.len() - 2, runs.published.len() - 1);
+    let mut out: Vec<Revision> = runs
+        .periods
+        .iter()
+        .filter(|(t, _)| t.as_str() > after && t.as_str() <= before)
+        .filter_map(|(t, prices)| match (prices[prev], prices[last]) {
+            (Some(old), Some(new)) => Some(Revision { forecast_time: t.clone(), old, new }),
+            _ => None,
+        })
+        .filter(|r| {
+            let delta = (r.new - r.old).abs();
+            delta >= REVISION_MIN_MWH && delta >= r.old.abs().max(100.0) * REVISION_MIN_RATIO
+        })
+        .collect();
+    out.sort_by(|a, b| (b.new - b.old).abs().total_cmp(&(a.new - a.old).abs()));
+    out
+}
+
+/// Alert users when the newest pre-dispatch run sharply revises the next few hours.
+pub fn analyze_revisions(db: &Db, region: &str) -> Vec<PendingAlert> {
+    let mut alerts = Vec::new();
+    let now = timezone::now_market();
+    let now_str = timezone::market_str(&now);
+    let horizon = timezone::market_str(&(now + chrono::Duration::hours(REVISION_HORIZON_HOURS)));
+
+    let runs = match db.get_forecast_runs(region, &now_str, 2) {
+        Ok(r) => r,
+        Err(_) => return alerts,
+    };
+    let revs = revisions(&runs, &now_str, &horizon);
+    let Some(biggest) = revs.first() else { return alerts };
+    let users = match db.get_active_users_by_region(region) {
+        Ok(u) => u,
+        Err(_) => return alerts,
+    };
+
+    for user in &users {
+        if can_alert(db, user.chat_id, region, "forecast_revision", 30) {
+            let retail = retail_at(user, region, biggest.new, &biggest.forecast_time, tariff::PREDISPATCH_MINUTES);
+            alerts.push(PendingAlert {
+                chat_id: user.chat_id,
+                text: messages::format_revision_alert(region, &revs, retail, user.tz()),
+                alert_type: "forecast_revision".into(),
+                price: biggest.new,
+                region: region.into(),
+                interval_time: biggest.forecast_time.clone(),
+                threshold: None,
+                value: None,
+            });
+        }
+    }
+
+    alerts
+}
+
+/// Alerts for newly published market notices, for users whose opted-in severity
+/// they meet and who follow a region they affect (one alert per user per notice).
+pub fn analyze_notices(db: &Db, notices: &[MarketNotice]) -> Vec<PendingAlert> {
+    let mut alerts = Vec::new();
+    let mut notified: HashSet<(i64, i64)> = HashSet::new();
+    let users = db.get_notice_subscribers().unwrap_or_default();
+    let cutoff = timezone::market_str(&(timezone::now_market() - chrono::Duration::hours(NOTICE_MAX_AGE_HOURS)));
+
+    for notice in notices {
+        let Some(severity) = notice.severity() else { continue };
+        if notice.issued_at < cutoff {
+            continue;
+        }
+        for user in &users {
+            let wanted = user.notice_severity.as_deref().and_then(Severity::from_key);
+            if wanted.is_none_or(|min| severity < min) || !notice.affects(&user.region) {
+                continue;
+            }
+            // Each notice is its own event, so only mutes and the hourly limit apply, not dedup
+            if !notified.insert((user.chat_id, notice.id))
+                || !can_alert(db, user.chat_id, &user.region, "market_notice", 0)
+            {
+                continue;
+            }
+            let price = db.get_latest_price(&user.region).ok().flatten().map_or(0.0, |(p, _)| p);
+            alerts.push(PendingAlert {
+                chat_id: user.chat_id,
+                text: messages::format_notice_alert(notice, user.tz()),
+                alert_type: "market_notice".into(),
+                price,
+                region: user.region.clone(),
+                interval_time: notice.issued_at.clone(),
+                threshold: None,
+                value: None,
+            });
+        }
+    }
+    alerts
+}
+
+/// What a threshold's state machine did this interval.
+#[derive(Debug, PartialEq, Eq)]
+enum Transition {
+    None,
+    /// Past the threshold for long enough: alert.
+    Entered,
+    /// Back past the clear level after having alerted.
+    Cleared,
+}
+
+/// Advance a threshold's state by one interval. `beyond` is whether the price is past
+/// the alert threshold and `back` whether it's back past the clear level; in between,
+/// an active alert stays active and a pending one starts counting again.
+///
+/// `may_alert` is asked once the streak is long enough. When it says no (muted, deduped
+/// or rate limited) the state stays pending and asks again next interval, so only an
+/// alert that was raised can later be cleared.
+fn advance(
+    state: &mut AlertState,
+    interval: &str,
+    beyond: bool,
+    back: bool,
+    min_intervals: i64,
+    may_alert: impl FnOnce() -> bool,
+) -> Transition {
+    if state.last_interval.as_deref() == Some(interval) {
+        return Transition::None;
+    }
+    state.last_interval = Some(interval.to_string());
+    if state.active {
+        if back {
+            state.active = false;
+            state.streak = 0;
+            return Transition::Cleared;
+        }
+        return Transition::None;
+    }
+    state.streak = if beyond { state.streak + 1 } else { 0 };
+    if state.streak >= min_intervals.max(1) && may_alert() {
+        state.active = true;
+        state.streak = 0;
+        return Transition::Entered;
+    }
+    Transition::None
+}
+
+/// Load, advance and store the state of one of `user`'s thresholds in their region.
+fn threshold_step(
+    db: &Db,
+    user: &User,
+    alert_type: &str,
+    interval: &str,
+    beyond: bool,
+    back: bool,
+    may_alert: impl FnOnce() -> bool,
+) -> Transition {
+    let mut state = db.get_alert_state(user.chat_id, &user.region, alert_type).unwrap_or_default();
+    let transition = advance(&mut state, interval, beyond, back, user.min_intervals, may_alert);
+    if let Err(e) = db.set_alert_state(user.chat_id, &user.region, alert_type, &state) {
+        tracing::error!(chat_id = user.chat_id, error = %e, "Failed to store alert state");
+    }
+    transition
+}
+
+/// Retail prices for an interval of `minutes` ending at `interval_time`.
+fn retail_at(user: &User, region: &str, price: f64, interval_time: &str, minutes: i64) -> Option<RetailPrice> {
+    user.tariff_for(region).map(|t| t.retail_price(price, interval_time, minutes))
+}
+
+/// The price in the unit of a `Threshold::price`: retail c/kWh when the user set a retail
+/// threshold (None without a tariff to convert with), else wholesale $/MWh.
+fn threshold_value(retail_threshold: Option<f64>, price: f64, retail: Option<RetailPrice>) -> Option<f64> {
+    match retail_threshold {
+        Some(_) => retail.map(|r| r.import_c),
+        None => Some(price),
+    }
+}
+
+/// Above the user's high threshold, in retail terms if they set one.
+fn is_high(user: &User, price: f64, retail: Option<RetailPrice>) -> bool {
+    match (user.high_alert_retail, retail) {
+        (Some(cents), Some(r)) => r.import_c > cents,
+        _ => price > user.high_alert,
+    }
+}
+
+/// Below the user's low threshold, in retail terms if they set one.
+fn is_low(user: &User, price: f64, retail: Option<RetailPrice>) -> bool {
+    match (user.low_alert_retail, retail) {
+        (Some(cents), Some(r)) => r.import_c < cents,
+        _ => price < user.low_alert,
+    }
+}
+
+/// Back below the level a high-price event clears at: the user's clear threshold if set,
+/// else the high threshold less the hysteresis band (taken as c/kWh for retail thresholds).
+fn below_high_clear(user: &User, price: f64, retail: Option<RetailPrice>) -> bool {
+    match (user.high_alert_retail, retail) {
+        (Some(cents), Some(r)) => r.import_c < cents - user.hysteresis / 10.0,
+        _ => price < user.clear_alert.filter(|c| *c < user.high_alert).unwrap_or(user.high_alert - user.hysteresis),
+    }
+}
+
+/// Back above the low threshold plus the hysteresis band.
+fn above_low_clear(user: &User, price: f64, retail: Option<RetailPrice>) -> bool {
+    match (user.low_alert_retail, retail) {
+        (Some(cents), Some(r)) => r.import_c > cents + user.hysteresis / 10.0,
+        _ => price > user.low_alert + user.hysteresis,
+    }
+}
+
+fn can_alert(db: &Db, chat_id: i64, region: &str, alert_type: &str, dedup_minutes: i64) -> bool {
+    let not_dup = !db.was_alert_sent_recently(chat_id, region, alert_type, dedup_minutes).unwrap_or(true);
     let under_limit = db.count_alerts_this_hour(chat_id).unwrap_or(10) < 10;
-    not_dup && under_limit
+    let muted = db.is_alert_muted(chat_id, alert_type).unwrap_or(false);
+    not_dup && under_limit && !muted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::parser::InterconnectorRecord;
+    use crate::db::repository::tests::{db_following, price};
+
+    /// Feed (beyond, back) for consecutive 5-minute intervals through `advance`.
+    fn run(state: &mut AlertState, steps: &[(bool, bool)], min_intervals: i64) -> Vec<Transition> {
+        steps
+            .iter()
+            .enumerate()
+            .map(|(i, (beyond, back))| {
+                let interval = format!("2026/01/15 17:{:02}:00", i * 5);
+                advance(state, &interval, *beyond, *back, min_intervals, || true)
+            })
+            .collect()
+    }
+
+    const HIGH: (bool, bool) = (true, false);
+    const BAND: (bool, bool) = (false, false);
+    const CLEAR: (bool, bool) = (false, true);
+
+    #[test]
+    fn enters_after_min_intervals() {
+        let mut state = AlertState::default();
+        let t = run(&mut state, &[HIGH, HIGH, HIGH, HIGH], 3);
+        assert_eq!(t, [Transition::None, Transition::None, Transition::Entered, Transition::None]);
+        assert!(state.active);
+
+        // A dip back into the band restarts the count
+        let mut state = AlertState::default();
+        let t = run(&mut state, &[HIGH, HIGH, BAND, HIGH, HIGH, HIGH], 3);
+        assert_eq!(t.iter().filter(|t| **t == Transition::Entered).count(), 1);
+        assert_eq!(t[5], Transition::Entered);
+    }
+
+    #[test]
+    fn same_interval_counts_once() {
+        let mut state = AlertState::default();
+        assert_eq!(advance(&mut state, "2026/01/15 17:00:00", true, false, 2, || true), Transition::None);
+        assert_eq!(advance(&mut state, "2026/01/15 17:00:00", true, false, 2, || true), Transition::None);
+        assert_eq!(state.streak, 1);
+        assert_eq!(advance(&mut state, "2026/01/15 17:05:00", true, false, 2, || true), Transition::Entered);
+    }
+
+    #[test]
+    fn clears_only_past_the_hysteresis_band() {
+        let mut state = AlertState::default();
+        let t = run(&mut state, &[HIGH, BAND, BAND, HIGH, CLEAR, CLEAR], 1);
+        assert_eq!(
+            t,
+            [Transition::Entered, Transition::None, Transition::None, Transition::None, Transition::Cleared, Transition::None]
+        );
+        assert!(!state.active);
+    }
+
+    #[test]
+    fn suppressed_alert_is_not_cleared() {
+        let mut state = AlertState::default();
+        assert_eq!(advance(&mut state, "2026/01/15 17:00:00", true, false, 1, || false), Transition::None);
+        assert!(!state.active);
+        assert_eq!(advance(&mut state, "2026/01/15 17:05:00", false, true, 1, || true), Transition::None);
+
+        // Still high when the alert is allowed again: it fires then
+        let mut state = AlertState::default();
+        advance(&mut state, "2026/01/15 17:00:00", true, false, 1, || false);
+        assert_eq!(advance(&mut state, "2026/01/15 17:05:00", true, false, 1, || true), Transition::Entered);
+    }
+
+    #[test]
+    fn notices_respect_the_hourly_limit() {
+        let db = db_following(&["SA1"]);
+        db.update_notice_severity(1, Some("low")).unwrap();
+        let notice = |id| MarketNotice {
+            id,
+            notice_type: "RESERVE NOTICE".into(),
+            reference: "Actual LOR2 in the SA region".into(),
+            reason: String::new(),
+            issued_at: timezone::market_str(&timezone::now_market()),
+            kind: crate::data::notices::NoticeKind::Lor(2),
+            cancelled: false,
+            regions: vec!["SA1".into()],
+        };
+        assert_eq!(analyze_notices(&db, &[notice(1), notice(2)]).len(), 2);
+
+        for _ in 0..10 {
+            db.log_alert(1, "market_notice", 0.0, "SA1").unwrap();
+        }
+        assert!(analyze_notices(&db, &[notice(3)]).is_empty());
+    }
+
+    #[test]
+    fn all_clear_only_follows_a_delivered_high_alert() {
+        let db = db_following(&["NSW1"]);
+        let mut minute = 0;
+        let mut step = |p: f64| {
+            minute += 5;
+            let rec = price("NSW1", &format!("2026/01/15 17:{minute:02}:00"), p);
+            db.insert_prices(std::slice::from_ref(&rec)).unwrap();
+            analyze(&db, &[rec]).into_iter().map(|a| a.alert_type).collect::<Vec<_>>()
+        };
+
+        // Dropped in quiet hours, so no all clear when it ends
+        assert!(step(50.0).is_empty());
+        assert!(step(500.0).contains(&"high_price".to_string()));
+        db.log_dropped_alert(1, "high_price", 500.0, "NSW1").unwrap();
+        assert!(!step(40.0).contains(&"all_clear".to_string()));
+
+        // Delivered this time
+        assert!(step(500.0).contains(&"high_price".to_string()));
+        db.log_alert(1, "high_price", 500.0, "NSW1").unwrap();
+        assert!(step(40.0).contains(&"all_clear".to_string()));
+    }
+
+    #[test]
+    fn limited_imports_follow_the_flow_into_the_region() {
+        let db = db_following(&["SA1"]);
+        let flow = |id: &str, mw_flow: f64, export_limit: f64, import_limit: f64| InterconnectorRecord {
+            interconnector_id: id.into(),
+            interval_time: "2026/01/15 17:05:00".into(),
+            mw_flow,
+            export_limit,
+            import_limit,
+            marginal_value: 0.0,
+        };
+        db.insert_interconnectors(&[
+            flow("V-SA", 650.0, 650.0, -600.0),
+            flow("V-S-MNSP1", 120.0, 220.0, -100.0),
+            flow("T-V-MNSP1", -478.0, 478.0, -478.0),
+            flow("VIC1-NSW1", 900.0, 1000.0, -1350.0),
+        ])
+        .unwrap();
+        let names = |region| {
+            limited_imports(&db, region, "2026/01/15 17:05:00").iter().map(|ic| ic.name).collect::<Vec<_>>()
+        };
+        // Heywood is full towards SA, and Basslink is full southward into Tasmania
+        assert_eq!(names("SA1"), ["Heywood"]);
+        assert_eq!(names("TAS1"), ["Basslink"]);
+        assert!(names("VIC1").is_empty());
+        assert!(names("NSW1").is_empty());
+        assert!(limited_imports(&db, "SA1", "2026/01/15 17:10:00").is_empty());
+    }
+
+    #[test]
+    fn fcas_alerts_fire_above_the_users_threshold() {
+        let db = db_following(&["SA1"]);
+        db.update_fcas_alert(1, "raise6sec", Some(50.0)).unwrap();
+        let mut minute = 0;
+        let mut step = |fcas: Vec<(&'static str, f64)>| {
+            minute += 5;
+            let rec = PriceRecord { fcas, ..price("SA1", &format!("2026/01/15 17:{minute:02}:00"), 80.0) };
+            db.insert_prices(std::slice::from_ref(&rec)).unwrap();
+            analyze(&db, &[rec]).into_iter().filter(|a| a.alert_type.starts_with("fcas_")).collect::<Vec<_>>()
+        };
+
+        assert!(step(vec![("raise6sec", 50.0), ("lowerreg", 900.0)]).is_empty());
+        let alerts = step(vec![("raise6sec", 75.5), ("lowerreg", 900.0)]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].alert_type, "fcas_raise6sec");
+        assert_eq!(alerts[0].route_key(), "fcas");
+        assert_eq!(alerts[0].price, 75.5);
+        assert_eq!(alerts[0].threshold, Some(Threshold::new(50.0, Unit::Mwh)));
+        // Stored with the price, as /price fcas reads it
+        let (time, stored) = db.get_latest_fcas("SA1").unwrap().unwrap();
+        assert_eq!(time, "2026/01/15 17:10:00");
+        assert_eq!(stored["raise6sec"], 75.5);
+    }
+
+    #[test]
+    fn carbon_alerts_wait_for_the_duration_and_clear_past_the_band() {
+        use crate::data::generators::{Fuel, MixRecord};
+
+        let db = db_following(&["VIC1"]);
+        db.update_carbon_alerts(1, Some(500.0), None).unwrap();
+        db.update_min_intervals(1, 2).unwrap();
+        let mut minute = 0;
+        let mut step = |intensity: f64| {
+            minute += 5;
+            let rec = price("VIC1", &format!("2026/01/15 17:{minute:02}:00"), 80.0);
+            // 1000 MW of coal, emitting `intensity` kg/MWh
+            let mix = MixRecord {
+                region: "VIC1".into(),
+                interval_time: rec.interval_time.clone(),
+                fuel: Fuel::Coal,
+                mw: 1000.0,
+                emissions_tph: intensity,
+            };
+            db.insert_mix(&[mix]).unwrap();
+            db.insert_prices(std::slice::from_ref(&rec)).unwrap();
+            analyze(&db, &[rec]).into_iter().filter(|a| a.alert_type.starts_with("carbon")).count()
+        };
+
+        assert_eq!(step(600.0), 0);
+        assert_eq!(step(600.0), 1);
+        assert_eq!(step(600.0), 0);
+        // Inside the band the event stays open, so going back over fires nothing
+        assert_eq!(step(490.0), 0);
+        assert_eq!(step(600.0), 0);
+        assert_eq!(step(470.0), 0);
+        assert_eq!(step(600.0), 0);
+        assert_eq!(step(600.0), 1);
+
+        // Removing the threshold ends the event, so setting it again alerts afresh
+        db.update_carbon_alerts(1, None, None).unwrap();
+        assert_eq!(step(600.0), 0);
+        db.update_carbon_alerts(1, Some(500.0), Some(100.0)).unwrap();
+        assert_eq!(step(600.0), 0);
+        assert_eq!(step(600.0), 1);
+    }
+
+    #[test]
+    fn tight_supply_below_a_tenth_of_demand() {
+        let db = db_following(&["SA1", "QLD1", "VIC1"]);
+        let prices = crate::data::parser::parse_dispatch(crate::data::parser::tests::REGIONSUM).prices;
+        db.insert_prices(&prices).unwrap();
+        let alerts: Vec<_> = analyze(&db, &prices).into_iter().filter(|a| a.alert_type == "tight_supply").collect();
+
+        // SA short even with imports, QLD short once its exports leave; VIC at exactly 10% isn't
+        let regions: Vec<_> = alerts.iter().map(|a| a.region.as_str()).collect();
+        assert_eq!(regions, ["QLD1", "SA1"]);
+        assert!(alerts[0].text.contains("Supply margin: 700 MW (9% of demand)"));
+        assert!(alerts[1].text.contains("Supply margin: 180 MW (9% of demand)"));
+        assert_eq!(alerts[1].price, 310.5);
+    }
+
+    /// Three runs of 18:00, 18:30, ... with the given (previous, latest) prices; the
+    /// oldest run is far off every time, so it only matters if it gets compared.
+    fn forecast_runs(moves: &[(f64, f64)]) -> ForecastRuns {
+        ForecastRuns {
+            published: vec!["2026/01/15 16:30:00".into(), "2026/01/15 17:00:00".into(), "2026/01/15 17:30:00".into()],
+            periods: moves
+                .iter()
+                .enumerate()
+                .map(|(i, (old, new))| {
+                    let time = format!("2026/01/15 {:02}:{:02}:00", 18 + i / 2, i % 2 * 30);
+                    (time, vec![Some(10_000.0), Some(*old), Some(*new)])
+                })
+                .collect(),
+        }
+    }
+
+    fn revised(moves: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        revisions(&forecast_runs(moves), "2026/01/15 17:30:00", "2026/01/16 05:30:00")
+            .iter()
+            .map(|r| (r.old, r.new))
+            .collect()
+    }
+
+    #[test]
+    fn revisions_need_the_floor_and_the_ratio() {
+        // The 200 $/MWh floor, where half the old price is below it
+        assert_eq!(revised(&[(300.0, 500.0)]), [(300.0, 500.0)]);
+        assert_eq!(revised(&[(300.0, 100.0)]), [(300.0, 100.0)]);
+        assert!(revised(&[(300.0, 499.99)]).is_empty());
+        // Half the old price, where that's above the floor
+        assert_eq!(revised(&[(500.0, 750.0)]), [(500.0, 750.0)]);
+        assert_eq!(revised(&[(500.0, 250.0)]), [(500.0, 250.0)]);
+        assert!(revised(&[(500.0, 749.99)]).is_empty());
+        assert!(revised(&[(500.0, 250.01)]).is_empty());
+        // Both at once, and negative or small old prices measured against $100
+        assert_eq!(revised(&[(400.0, 600.0)]), [(400.0, 600.0)]);
+        assert!(revised(&[(400.0, 599.99)]).is_empty());
+        assert_eq!(revised(&[(-50.0, 150.0)]), [(-50.0, 150.0)]);
+        assert!(revised(&[(-50.0, 149.99)]).is_empty());
+    }
+
+    #[test]
+    fn revisions_compare_the_latest_two_runs_in_the_window() {
+        // Largest move first; unchanged periods drop out even though the oldest run differed
+        assert_eq!(
+            revised(&[(100.0, 100.0), (100.0, 400.0), (300.0, 1_300.0), (-30.0, -30.0)]),
+            [(300.0, 1_300.0), (100.0, 400.0)]
+        );
+
+        // Periods at or before `after`, after `before`, or missing from either run are skipped
+        let mut runs = forecast_runs(&[(100.0, 400.0), (100.0, 500.0), (100.0, 600.0), (100.0, 700.0)]);
+        runs.periods[2].1[1] = None;
+        let revs = revisions(&runs, "2026/01/15 18:00:00", "2026/01/15 19:30:00");
+        assert_eq!(revs.iter().map(|r| r.forecast_time.as_str()).collect::<Vec<_>>(), ["2026/01/15 19:30:00", "2026/01/15 18:30:00"]);
+
+        // A single run has nothing to compare against
+        runs.published.drain(..2);
+        runs.periods.iter_mut().for_each(|(_, prices)| {
+            prices.drain(..2);
+        });
+        assert!(revisions(&runs, "2026/01/15 17:30:00", "2026/01/16 05:30:00").is_empty());
+    }
 }
@@ -0,0 +1,274 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDate};
+
+use crate::data::fetcher;
+use crate::db::Db;
+use crate::timezone;
+
+#[derive(Clone, Copy)]
+pub enum Source {
+    /// Daily zips under Reports/Archive/DispatchIS_Reports (roughly the last year).
+    Archive,
+    /// Monthly DISPATCHPRICE files from the MMSDM data archive (back to 2009).
+    Mmsdm,
+}
+
+impl Source {
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Archive => "archive",
+            Self::Mmsdm => "mmsdm",
+        }
+    }
+}
+
+pub struct BackfillArgs {
+    pub source: Source,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+pub const USAGE: &str = "Usage: nem-price-bot backfill <from YYYY-MM-DD> <to YYYY-MM-DD> [--source archive|mmsdm]";
+
+/// Parse `backfill` subcommand arguments (everything after the subcommand name).
+pub fn parse_args(args: &[String]) -> Result<BackfillArgs> {
+    let mut dates = Vec::new();
+    let mut source = Source::Archive;
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--source" => {
+                source = match it.next().map(String::as_str) {
+                    Some("archive") => Source::Archive,
+                    Some("mmsdm") => Source::Mmsdm,
+                    _ => bail!("--source must be archive or mmsdm\n{USAGE}"),
+                }
+            }
+            d => dates.push(
+                NaiveDate::parse_from_str(d, "%Y-%m-%d").with_context(|| format!("Invalid date {d}\n{USAGE}"))?,
+            ),
+        }
+    }
+    match dates.as_slice() {
+        [from, to] if from <= to => Ok(BackfillArgs { source, from: *from, to: *to }),
+        [_, _] => bail!("<from> must not be after <to>"),
+        _ => bail!("{USAGE}"),
+    }
+}
+
+/// Import dispatch prices for a date range. Each day (archive) or month (MMSDM)
+/// is recorded in `backfill_progress` once stored, so an interrupted run can be
+/// restarted with the same arguments and picks up where it stopped. A recorded period
+/// is fetched again while a dispatch gap inside it is still unfilled.
+pub async fn run(db: &Db, client: &reqwest::Client, base: &str, args: &BackfillArgs) -> Result<()> {
+    let source = args.source.key();
+    let mut failed = 0;
+
+    for (period, year, month, day) in periods(args) {
+        let (after, before) = bounds(year, month, day);
+        if db.is_backfilled(source, &period)? && !db.has_open_gaps(&after, &before)? {
+            tracing::debug!(source, period, "Already backfilled, skipping");
+            continue;
+        }
+        let result = match (args.source, day) {
+            (Source::Archive, Some(d)) => fetcher::fetch_archive_day(client, base, d).await,
+            _ => fetcher::fetch_mmsdm_month(client, base, year, month).await,
+        };
+        match result {
+            Ok(records) => {
+                let inserted = db.insert_backfill_prices(&records)?;
+                db.mark_backfilled(source, &period, inserted)?;
+                tracing::info!(source, period, parsed = records.len(), inserted, "Backfilled");
+            }
+            Err(e) => {
+                failed += 1;
+                tracing::warn!(source, period, error = %e, "Backfill failed; rerun to retry");
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} period(s) failed to download; rerun the same command to retry them");
+    }
+    Ok(())
+}
+
+/// The interval times a period covers, as `(after, before]` market-time strings
+/// (intervals are stamped with their end). An archive day is a trading day, whose file
+/// runs from 04:05 to 04:00 the next morning; an MMSDM month runs midnight to midnight.
+fn bounds(year: i32, month: u32, day: Option<NaiveDate>) -> (String, String) {
+    let at = |d: NaiveDate, hour: u32| {
+        d.and_hms_opt(hour, 0, 0)
+            .unwrap_or_default()
+            .format(timezone::MARKET_FMT)
+            .to_string()
+    };
+    match day {
+        Some(d) => (at(d, 4), at(d.succ_opt().unwrap_or(d), 4)),
+        None => {
+            let start = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default();
+            let end = start.checked_add_months(chrono::Months::new(1)).unwrap_or(start);
+            (at(start, 0), at(end, 0))
+        }
+    }
+}
+
+/// Progress keys to fetch: one per day for the archive, one per month for MMSDM.
+fn periods(args: &BackfillArgs) -> Vec<(String, i32, u32, Option<NaiveDate>)> {
+    let mut out = Vec::new();
+    match args.source {
+        Source::Archive => {
+            let mut d = args.from;
+            while d <= args.to {
+                out.push((d.format("%Y-%m-%d").to_string(), d.year(), d.month(), Some(d)));
+                d = match d.succ_opt() {
+                    Some(next) => next,
+                    None => break,
+                };
+            }
+        }
+        Source::Mmsdm => {
+            let (mut y, mut m) = (args.from.year(), args.from.month());
+            while (y, m) <= (args.to.year(), args.to.month()) {
+                out.push((format!("{y}-{m:02}"), y, m, None));
+                (y, m) = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use zip::write::SimpleFileOptions;
+
+    fn zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+        let mut w = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, bytes) in files {
+            w.start_file(name.as_str(), SimpleFileOptions::default()).unwrap();
+            w.write_all(bytes).unwrap();
+        }
+        w.finish().unwrap().into_inner()
+    }
+
+    /// An archive day as NEMweb has it: a zip of one 5-minute DISPATCHIS zip per interval.
+    fn archive_day(intervals: &[&str]) -> Vec<u8> {
+        let inner = intervals
+            .iter()
+            .map(|t| {
+                let stamp: String = t.chars().filter(char::is_ascii_digit).take(12).collect();
+                let csv = format!(
+                    "C,NEMP.WORLD,DISPATCHIS,AEMO,PUBLIC,2026/01/15,04:00:05\n\
+                     I,DISPATCH,PRICE,5,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP\n\
+                     D,DISPATCH,PRICE,5,\"{t}\",1,NSW1,1,0,85.5\n\
+                     C,\"END OF REPORT\",4\n"
+                );
+                let name = format!("PUBLIC_DISPATCHIS_{stamp}_0000000500000000.CSV");
+                (format!("PUBLIC_DISPATCHIS_{stamp}_0000000500000000.zip"), zip(&[(name, csv.into_bytes())]))
+            })
+            .collect::<Vec<_>>();
+        zip(&inner)
+    }
+
+    /// Serve `files` (path to body) on a local port, 404 for anything else, recording
+    /// every path asked for.
+    async fn file_server(files: Vec<(String, Vec<u8>)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]);
+                let path = head.split(' ').nth(1).unwrap_or_default().to_string();
+                let body = files.iter().find(|(p, _)| *p == path).map(|(_, b)| b.as_slice());
+                let status = if body.is_some() { "200 OK" } else { "404 Not Found" };
+                let body = body.unwrap_or_default();
+                let head = format!("HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n", body.len());
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(body).await;
+                seen.lock().unwrap().push(path);
+            }
+        });
+        (base, requests)
+    }
+
+    #[tokio::test]
+    async fn archive_days_resume_from_progress() {
+        // Trading day 15 Jan: 04:05 that morning to 04:00 the next
+        let day = archive_day(&["2026/01/15 04:05:00", "2026/01/16 02:00:00", "2026/01/16 04:00:00"]);
+        let path = "/Reports/Archive/DispatchIS_Reports/PUBLIC_DISPATCHIS_20260115.zip".to_string();
+        let (base, requests) = file_server(vec![(path.clone(), day)]).await;
+        let db = Db::new(":memory:").unwrap();
+        let client = reqwest::Client::new();
+        let day = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+        let args = BackfillArgs { source: Source::Archive, from: day, to: day };
+        let fetches = || requests.lock().unwrap().iter().filter(|p| **p == path).count();
+
+        run(&db, &client, &base, &args).await.unwrap();
+        assert_eq!(fetches(), 1);
+        let stored = db.get_interval_times("NSW1", "2026/01/15 00:00:00", "2026/01/17 00:00:00").unwrap();
+        assert_eq!(stored.len(), 3);
+        assert!(stored.contains("2026/01/16 04:00:00"));
+        assert!(db.is_backfilled("archive", "2026-01-15").unwrap());
+
+        // Recorded, so a rerun skips it
+        run(&db, &client, &base, &args).await.unwrap();
+        assert_eq!(fetches(), 1);
+        // A gap after 04:00 belongs to the next trading day's file
+        db.insert_gap("NSW1", "2026/01/16 05:00:00", "2026/01/16 05:05:00", 2).unwrap();
+        run(&db, &client, &base, &args).await.unwrap();
+        assert_eq!(fetches(), 1);
+        // One in the small hours is in this day's file, so it's fetched again
+        db.insert_gap("NSW1", "2026/01/16 03:00:00", "2026/01/16 03:05:00", 2).unwrap();
+        run(&db, &client, &base, &args).await.unwrap();
+        assert_eq!(fetches(), 2);
+
+        // A missing day fails the run and isn't recorded
+        let next = NaiveDate::from_ymd_opt(2026, 1, 16).unwrap();
+        assert!(run(&db, &client, &base, &BackfillArgs { source: Source::Archive, from: next, to: next }).await.is_err());
+        assert!(!db.is_backfilled("archive", "2026-01-16").unwrap());
+    }
+
+    #[tokio::test]
+    async fn mmsdm_months_fall_back_to_the_older_file_name() {
+        let csv = "I,DISPATCH,PRICE,5,SETTLEMENTDATE,RUNNO,REGIONID,DISPATCHINTERVAL,INTERVENTION,RRP\n\
+                   D,DISPATCH,PRICE,5,\"2025/06/01 00:05:00\",1,VIC1,1,0,62.1\n\
+                   D,DISPATCH,PRICE,5,\"2025/07/01 00:00:00\",1,VIC1,1,0,70.4\n";
+        let dir = "/Data_Archive/Wholesale_Electricity/MMSDM/2025/MMSDM_2025_06/MMSDM_Historical_Data_SQLLoader/DATA/";
+        let path = format!("{dir}PUBLIC_DVD_DISPATCHPRICE_202506010000.zip");
+        let (base, requests) = file_server(vec![(path.clone(), zip(&[("PUBLIC_DVD_DISPATCHPRICE_202506010000.CSV".into(), csv.into())]))]).await;
+        let db = Db::new(":memory:").unwrap();
+        let june = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let args = BackfillArgs { source: Source::Mmsdm, from: june, to: june.with_day(30).unwrap() };
+
+        run(&db, &reqwest::Client::new(), &base, &args).await.unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [format!("{dir}PUBLIC_ARCHIVE%23DISPATCHPRICE%23FILE01%23202506010000.zip"), path]
+        );
+        assert_eq!(db.get_interval_times("VIC1", "2025/06/01 00:00:00", "2025/07/01 00:05:00").unwrap().len(), 2);
+        assert!(db.is_backfilled("mmsdm", "2025-06").unwrap());
+    }
+
+    #[test]
+    fn period_bounds() {
+        let d = NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+        assert_eq!(
+            bounds(2025, 12, Some(d)),
+            ("2025/12/31 04:00:00".into(), "2026/01/01 04:00:00".into())
+        );
+        assert_eq!(
+            bounds(2025, 12, None),
+            ("2025/12/01 00:00:00".into(), "2026/01/01 00:00:00".into())
+        );
+        assert_eq!(bounds(2024, 2, None).1, "2024/03/01 00:00:00");
+    }
+}
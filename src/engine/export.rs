@@ -0,0 +1,309 @@
+use anyhow::{bail, Context, Result};
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::db::repository::{ForecastRow, PriceRow};
+use crate::db::Db;
+use crate::timezone;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Csv,
+    Parquet,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+pub struct ExportArgs {
+    pub format: Format,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub region: Option<String>,
+    pub dir: PathBuf,
+}
+
+pub const USAGE: &str = "Usage: nem-price-bot export <from YYYY-MM-DD> <to YYYY-MM-DD> \
+    [--format parquet|csv] [--region NSW1] [--out DIR]";
+
+/// Rows buffered per Parquet record batch.
+const BATCH_ROWS: usize = 8192;
+
+/// Parse `export` subcommand arguments (everything after the subcommand name).
+pub fn parse_args(args: &[String]) -> Result<ExportArgs> {
+    let mut dates = Vec::new();
+    let mut format = Format::Parquet;
+    let mut region = None;
+    let mut dir = PathBuf::from("export");
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match it.next().map(String::as_str) {
+                    Some("parquet") => Format::Parquet,
+                    Some("csv") => Format::Csv,
+                    _ => bail!("--format must be parquet or csv\n{USAGE}"),
+                }
+            }
+            "--region" => match it.next().map(|r| r.to_uppercase()) {
+                Some(r) if ["NSW1", "VIC1", "QLD1", "SA1", "TAS1"].contains(&r.as_str()) => region = Some(r),
+                _ => bail!("--region must be one of NSW1, VIC1, QLD1, SA1, TAS1\n{USAGE}"),
+            },
+            "--out" => dir = it.next().map(PathBuf::from).with_context(|| format!("--out needs a directory\n{USAGE}"))?,
+            d => dates.push(
+                NaiveDate::parse_from_str(d, "%Y-%m-%d").with_context(|| format!("Invalid date {d}\n{USAGE}"))?,
+            ),
+        }
+    }
+    match dates.as_slice() {
+        [from, to] if from <= to => Ok(ExportArgs { format, from: *from, to: *to, region, dir }),
+        [_, _] => bail!("<from> must not be after <to>"),
+        _ => bail!("{USAGE}"),
+    }
+}
+
+/// Write `price_history` and `forecast` rows for the market-time days `from..=to` under
+/// `<dir>/prices/region=<R>/<YYYY-MM>.<ext>` and `<dir>/forecasts/...`, partitioned by the
+/// month each period falls in. Existing files for the same partitions are overwritten.
+pub fn run(db: &Db, args: &ExportArgs) -> Result<()> {
+    let start = format!("{} 00:00:00", args.from.format("%Y/%m/%d"));
+    let end = format!("{} 00:00:00", args.to.succ_opt().unwrap_or(args.to).format("%Y/%m/%d"));
+    let region = args.region.as_deref();
+
+    let mut prices = Partitioned::<PriceRow>::new(args.dir.join("prices"), args.format);
+    db.for_each_price(region, &start, &end, |row| prices.push(row))?;
+    let (files, rows) = prices.finish()?;
+    tracing::info!(files, rows, dir = %args.dir.join("prices").display(), "Exported prices");
+
+    let mut forecasts = Partitioned::<ForecastRow>::new(args.dir.join("forecasts"), args.format);
+    db.for_each_forecast(region, &start, &end, |row| forecasts.push(row))?;
+    let (files, rows) = forecasts.finish()?;
+    tracing::info!(files, rows, dir = %args.dir.join("forecasts").display(), "Exported forecasts");
+    Ok(())
+}
+
+/// Stream one CSV of prices then forecasts for `(start, end]` in each of `regions` to `out`,
+/// as sent by `/export`. Returns (price rows, forecast rows).
+pub fn write_user_csv(db: &Db, regions: &[&str], start: &str, end: &str, out: impl Write) -> Result<(usize, usize)> {
+    let mut w = csv::Writer::from_writer(out);
+    w.write_record(["type", "region", "interval_time", "price_mwh", "demand_mw", "published_at"])?;
+    let (mut prices, mut forecasts) = (0, 0);
+    for region in regions {
+        db.for_each_price(Some(region), start, end, |r| {
+            prices += 1;
+            let demand = r.demand_mw.map(|d| d.to_string()).unwrap_or_default();
+            w.write_record(["actual", &r.region, &r.interval_time, &r.price_mwh.to_string(), &demand, ""])?;
+            Ok(())
+        })?;
+        db.for_each_forecast(Some(region), start, end, |r| {
+            forecasts += 1;
+            w.write_record(["forecast", &r.region, &r.forecast_time, &r.price_mwh.to_string(), "", &r.published_at])?;
+            Ok(())
+        })?;
+    }
+    w.flush()?;
+    Ok((prices, forecasts))
+}
+
+/// A table that can be written as CSV rows or Arrow record batches.
+trait ExportRow: Sized {
+    fn schema() -> Schema;
+    fn region(&self) -> &str;
+    /// Market-time timestamp the row is partitioned on.
+    fn time(&self) -> &str;
+    fn csv_record(&self) -> Vec<String>;
+    fn batch(rows: &[Self]) -> Result<RecordBatch>;
+}
+
+fn opt(v: Option<f64>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn strings<'a>(values: impl Iterator<Item = &'a str>) -> ArrayRef {
+    Arc::new(StringArray::from_iter_values(values))
+}
+
+impl ExportRow for PriceRow {
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new("region", DataType::Utf8, false),
+            Field::new("interval_time", DataType::Utf8, false),
+            Field::new("price_mwh", DataType::Float64, false),
+            Field::new("demand_mw", DataType::Float64, true),
+            Field::new("available_gen_mw", DataType::Float64, true),
+            Field::new("net_interchange_mw", DataType::Float64, true),
+        ])
+    }
+
+    fn region(&self) -> &str {
+        &self.region
+    }
+
+    fn time(&self) -> &str {
+        &self.interval_time
+    }
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.region.clone(),
+            self.interval_time.clone(),
+            self.price_mwh.to_string(),
+            opt(self.demand_mw),
+            opt(self.available_gen_mw),
+            opt(self.net_interchange_mw),
+        ]
+    }
+
+    fn batch(rows: &[Self]) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            strings(rows.iter().map(|r| r.region.as_str())),
+            strings(rows.iter().map(|r| r.interval_time.as_str())),
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.price_mwh))),
+            Arc::new(rows.iter().map(|r| r.demand_mw).collect::<Float64Array>()),
+            Arc::new(rows.iter().map(|r| r.available_gen_mw).collect::<Float64Array>()),
+            Arc::new(rows.iter().map(|r| r.net_interchange_mw).collect::<Float64Array>()),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(Self::schema()), columns)?)
+    }
+}
+
+impl ExportRow for ForecastRow {
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Field::new("region", DataType::Utf8, false),
+            Field::new("forecast_time", DataType::Utf8, false),
+            Field::new("price_mwh", DataType::Float64, false),
+            Field::new("published_at", DataType::Utf8, false),
+        ])
+    }
+
+    fn region(&self) -> &str {
+        &self.region
+    }
+
+    fn time(&self) -> &str {
+        &self.forecast_time
+    }
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![self.region.clone(), self.forecast_time.clone(), self.price_mwh.to_string(), self.published_at.clone()]
+    }
+
+    fn batch(rows: &[Self]) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            strings(rows.iter().map(|r| r.region.as_str())),
+            strings(rows.iter().map(|r| r.forecast_time.as_str())),
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(|r| r.price_mwh))),
+            strings(rows.iter().map(|r| r.published_at.as_str())),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(Self::schema()), columns)?)
+    }
+}
+
+enum Sink {
+    Csv(csv::Writer<File>),
+    Parquet(ArrowWriter<File>),
+}
+
+/// Writes rows arriving in (region, time) order to one file per region and month,
+/// keeping only the current file open and at most one batch of rows in memory.
+struct Partitioned<R: ExportRow> {
+    root: PathBuf,
+    format: Format,
+    /// (region, "YYYY-MM") of the open file.
+    current: Option<(String, String)>,
+    sink: Option<Sink>,
+    buffer: Vec<R>,
+    files: usize,
+    rows: usize,
+}
+
+impl<R: ExportRow> Partitioned<R> {
+    fn new(root: PathBuf, format: Format) -> Self {
+        Self { root, format, current: None, sink: None, buffer: Vec::new(), files: 0, rows: 0 }
+    }
+
+    fn push(&mut self, row: R) -> Result<()> {
+        // Times are period ends, so the period ending "2026/02/01 00:00:00" goes in 2026-01
+        let month = timezone::parse_market(row.time())
+            .map(|t| (t - chrono::Duration::minutes(1)).format("%Y-%m").to_string())
+            .unwrap_or_default();
+        if self.current.as_ref().is_none_or(|(r, m)| r != row.region() || *m != month) {
+            self.close()?;
+            self.open(row.region(), &month)?;
+        }
+        self.rows += 1;
+        match self.sink.as_mut() {
+            Some(Sink::Csv(w)) => w.write_record(row.csv_record())?,
+            Some(Sink::Parquet(_)) => {
+                self.buffer.push(row);
+                if self.buffer.len() >= BATCH_ROWS {
+                    self.flush()?;
+                }
+            }
+            None => unreachable!("partition opened above"),
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, region: &str, month: &str) -> Result<()> {
+        let dir = self.root.join(format!("region={region}"));
+        std::fs::create_dir_all(&dir).with_context(|| format!("Creating {}", dir.display()))?;
+        let path = dir.join(format!("{month}.{}", self.format.extension()));
+        let file = File::create(&path).with_context(|| format!("Creating {}", path.display()))?;
+        self.sink = Some(match self.format {
+            Format::Csv => {
+                let mut w = csv::Writer::from_writer(file);
+                w.write_record(R::schema().fields().iter().map(|f| f.name()))?;
+                Sink::Csv(w)
+            }
+            Format::Parquet => {
+                let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                Sink::Parquet(ArrowWriter::try_new(file, Arc::new(R::schema()), Some(props))?)
+            }
+        });
+        self.current = Some((region.to_string(), month.to_string()));
+        self.files += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let (Some(Sink::Parquet(w)), false) = (self.sink.as_mut(), self.buffer.is_empty()) {
+            w.write(&R::batch(&self.buffer)?)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.flush()?;
+        match self.sink.take() {
+            Some(Sink::Csv(mut w)) => w.flush()?,
+            Some(Sink::Parquet(w)) => {
+                w.close()?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Close the last file; returns (files written, rows written).
+    fn finish(mut self) -> Result<(usize, usize)> {
+        self.close()?;
+        Ok((self.files, self.rows))
+    }
+}
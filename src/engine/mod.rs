@@ -1,2 +1,8 @@
+pub mod accuracy;
 pub mod analyzer;
+pub mod backfill;
+pub mod export;
+pub mod optimiser;
+pub mod savings;
+pub mod tariff;
 pub mod scheduler;
@@ -0,0 +1,223 @@
+use crate::db::repository::BatteryProfile;
+
+/// Pre-dispatch periods are 30 minutes long.
+pub const PERIOD_HOURS: f64 = 0.5;
+
+/// Assumed state of charge when the user doesn't say (e.g. in the daily summary).
+pub const DEFAULT_START_SOC: f64 = 0.5;
+
+/// Minimum number of discrete SoC levels the DP searches over.
+const MIN_SOC_LEVELS: usize = 60;
+const MAX_SOC_LEVELS: usize = 400;
+/// A battery that takes more than `MAX_SOC_LEVELS` periods to fill at full power gets one
+/// level per period's move instead, up to this many; that's cheap since each level then
+/// only reaches its neighbours. Past this a level is more than a period's move, so the
+/// battery is left idle rather than planned beyond its power limit (`/battery` never
+/// allows such a profile).
+const MAX_SLOW_SOC_LEVELS: usize = 20_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Charge,
+    Idle,
+    Discharge,
+}
+
+pub struct PlanStep {
+    /// Period end, AEMO market time.
+    pub time: String,
+    pub price: f64,
+    pub action: Action,
+    /// Energy drawn from (+) or exported to (−) the grid during the period.
+    pub grid_kwh: f64,
+    /// State of charge at the end of the period.
+    pub soc_kwh: f64,
+}
+
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+    /// Net value in dollars of following the plan versus staying idle.
+    pub value: f64,
+}
+
+/// Find the charge/idle/discharge schedule that maximises arbitrage value over
+/// the forecast horizon, by dynamic programming over discretised SoC levels.
+///
+/// The battery must finish with at least the charge it started with, so the plan
+/// never "earns" money by simply draining the battery. Round-trip losses are
+/// split evenly between charging and discharging.
+pub fn optimise(battery: &BatteryProfile, forecasts: &[(String, f64)], start_soc: f64) -> Plan {
+    let capacity = battery.capacity_kwh.max(0.0);
+    let max_move = battery.power_kw.max(0.0) * PERIOD_HOURS;
+    if forecasts.is_empty() || capacity <= 0.0 || max_move <= 0.0 {
+        return idle_plan(forecasts, capacity * start_soc.clamp(0.0, 1.0));
+    }
+
+    // Enough levels that a full-power period spans several steps, and never so few that
+    // a step is more than a period's move (which would leave the battery stuck idle)
+    let levels = ((capacity / max_move * 4.0).ceil() as usize)
+        .clamp(MIN_SOC_LEVELS, MAX_SOC_LEVELS)
+        .max(((capacity / max_move).ceil() as usize).min(MAX_SLOW_SOC_LEVELS));
+    let step_kwh = capacity / levels as f64;
+    // Zero only when the level count is capped; the tolerance keeps an exact fit at one level
+    let reach = (max_move / step_kwh + 1e-9).floor() as usize;
+    let leg_eff = battery.efficiency.clamp(0.01, 1.0).sqrt();
+
+    let start = ((start_soc.clamp(0.0, 1.0) * levels as f64).round() as usize).min(levels);
+    let reserve = ((battery.reserve.clamp(0.0, 1.0) * levels as f64).ceil() as usize).min(levels);
+    // A battery already below reserve may stay there, but never goes lower
+    let floor = reserve.min(start);
+
+    // Grid energy for moving between levels: charging draws extra to cover losses
+    let grid = |from: usize, to: usize| -> f64 {
+        let delta = (to as f64 - from as f64) * step_kwh;
+        if delta > 0.0 { delta / leg_eff } else { delta * leg_eff }
+    };
+    // Cash flow at a price in $/MWh; importing costs money
+    let cash = |from: usize, to: usize, price: f64| -> f64 { -grid(from, to) * price / 1000.0 };
+
+    // value[i] = best value from the current period onwards at level i
+    let periods = forecasts.len();
+    let mut value: Vec<f64> = (0..=levels)
+        .map(|i| if i >= start { 0.0 } else { f64::NEG_INFINITY })
+        .collect();
+    let mut choice = vec![vec![0usize; levels + 1]; periods];
+
+    for t in (0..periods).rev() {
+        let price = forecasts[t].1;
+        let mut next = vec![f64::NEG_INFINITY; levels + 1];
+        for i in floor..=levels {
+            let lo = i.saturating_sub(reach).max(floor);
+            let hi = (i + reach).min(levels);
+            // Try idle first so ties don't produce pointless cycling
+            for j in std::iter::once(i).chain(lo..=hi) {
+                if value[j] == f64::NEG_INFINITY {
+                    continue;
+                }
+                let v = cash(i, j, price) + value[j];
+                if v > next[i] {
+                    next[i] = v;
+                    choice[t][i] = j;
+                }
+            }
+        }
+        value = next;
+    }
+
+    let mut steps = Vec::with_capacity(periods);
+    let mut level = start;
+    let mut total = 0.0;
+    for (t, (time, price)) in forecasts.iter().enumerate() {
+        let to = choice[t][level];
+        let flow = cash(level, to, *price);
+        total += flow;
+        let action = match to.cmp(&level) {
+            std::cmp::Ordering::Greater => Action::Charge,
+            std::cmp::Ordering::Less => Action::Discharge,
+            std::cmp::Ordering::Equal => Action::Idle,
+        };
+        steps.push(PlanStep {
+            time: time.clone(),
+            price: *price,
+            action,
+            grid_kwh: grid(level, to),
+            soc_kwh: to as f64 * step_kwh,
+        });
+        level = to;
+    }
+
+    Plan { steps, value: total }
+}
+
+fn idle_plan(forecasts: &[(String, f64)], soc_kwh: f64) -> Plan {
+    let steps = forecasts
+        .iter()
+        .map(|(time, price)| PlanStep {
+            time: time.clone(),
+            price: *price,
+            action: Action::Idle,
+            grid_kwh: 0.0,
+            soc_kwh,
+        })
+        .collect();
+    Plan { steps, value: 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(capacity_kwh: f64, power_kw: f64) -> BatteryProfile {
+        BatteryProfile { capacity_kwh, power_kw, efficiency: 0.9, reserve: 0.1 }
+    }
+
+    /// Half-hourly forecasts from a list of prices.
+    fn forecasts(prices: &[f64]) -> Vec<(String, f64)> {
+        prices.iter().enumerate().map(|(i, p)| (format!("period {i}"), *p)).collect()
+    }
+
+    /// SoC moves per period never exceed the power limit, and never dip below reserve.
+    fn check_limits(b: &BatteryProfile, plan: &Plan, start_soc: f64) {
+        let mut soc = b.capacity_kwh * start_soc;
+        for step in &plan.steps {
+            assert!((step.soc_kwh - soc).abs() <= b.power_kw * PERIOD_HOURS + 1e-9, "moved too far");
+            assert!(step.soc_kwh >= b.capacity_kwh * b.reserve - 1e-9, "below reserve");
+            soc = step.soc_kwh;
+        }
+    }
+
+    #[test]
+    fn buys_low_and_sells_high() {
+        let b = battery(10.0, 5.0);
+        let plan = optimise(&b, &forecasts(&[20.0, 20.0, 400.0, 400.0, 20.0, 20.0]), 0.5);
+        assert!(plan.value > 0.0);
+        assert_eq!(plan.steps[0].action, Action::Charge);
+        assert_eq!(plan.steps[2].action, Action::Discharge);
+        // Ends no emptier than it started
+        assert!(plan.steps.last().unwrap().soc_kwh >= 5.0 - 1e-9);
+        check_limits(&b, &plan, 0.5);
+    }
+
+    #[test]
+    fn flat_prices_stay_idle() {
+        let b = battery(13.5, 5.0);
+        let plan = optimise(&b, &forecasts(&[100.0; 48]), 0.5);
+        assert_eq!(plan.value, 0.0);
+        assert!(plan.steps.iter().all(|s| s.action == Action::Idle));
+    }
+
+    #[test]
+    fn slow_battery_still_trades() {
+        // 1 kWh per period against 1000 kWh: more periods to fill than the usual level cap
+        let b = battery(1000.0, 2.0);
+        let mut prices = vec![-50.0; 24];
+        prices.extend([600.0; 24]);
+        let plan = optimise(&b, &forecasts(&prices), 0.5);
+        assert!(plan.value > 0.0);
+        assert!(plan.steps.iter().any(|s| s.action == Action::Charge));
+        assert!(plan.steps.iter().any(|s| s.action == Action::Discharge));
+        check_limits(&b, &plan, 0.5);
+    }
+
+    #[test]
+    fn capped_levels_never_exceed_power() {
+        // 100 000 periods to fill: more than `MAX_SLOW_SOC_LEVELS`, so a level is over a period's move
+        let b = battery(50_000.0, 1.0);
+        let mut prices = vec![-50.0; 24];
+        prices.extend([600.0; 24]);
+        let plan = optimise(&b, &forecasts(&prices), 0.5);
+        check_limits(&b, &plan, 0.5);
+        assert!(plan.steps.iter().all(|s| s.action == Action::Idle));
+        // Exactly one level per period's move still trades
+        let b = battery(MAX_SLOW_SOC_LEVELS as f64 * 0.5, 1.0);
+        let plan = optimise(&b, &forecasts(&prices), 0.5);
+        assert!(plan.value > 0.0);
+        check_limits(&b, &plan, 0.5);
+    }
+
+    #[test]
+    fn no_power_or_forecasts_is_idle() {
+        assert!(optimise(&battery(10.0, 0.0), &forecasts(&[0.0, 500.0]), 0.5).steps.iter().all(|s| s.action == Action::Idle));
+        assert!(optimise(&battery(10.0, 5.0), &[], 0.5).steps.is_empty());
+    }
+}
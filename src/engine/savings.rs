@@ -0,0 +1,144 @@
+use chrono_tz::Tz;
+
+use crate::db::repository::{BatteryProfile, Savings, User};
+use crate::db::Db;
+use crate::timezone;
+
+/// Below this price the bot advises charging from the grid (the "Low" level).
+const CHARGE_BELOW: f64 = 50.0;
+
+const INTERVAL_HOURS: f64 = 5.0 / 60.0;
+
+/// Days are only scored once most of their 288 dispatch intervals are stored.
+const MIN_INTERVALS_PER_DAY: usize = 240;
+
+/// How far back the nightly job fills in missing days.
+pub const LOOKBACK_DAYS: i64 = 30;
+
+/// Simulate one day of a household that follows the bot's advice: charge at full
+/// power when prices are Low or Negative, discharge when above the user's high
+/// alert. The battery starts each day at its reserve, so it only sells energy it
+/// bought that day and ends the day at least as full as it started.
+///
+/// The flat-rate baseline prices the same energy flows at the day's average
+/// price, so the difference is what price-following earned.
+pub fn simulate_day(battery: &BatteryProfile, prices: &[f64], high_alert: f64) -> Savings {
+    let leg_eff = battery.efficiency.clamp(0.01, 1.0).sqrt();
+    let floor = battery.capacity_kwh * battery.reserve;
+    let max_move = battery.power_kw * INTERVAL_HOURS;
+    let mut soc = floor;
+    let flat = if prices.is_empty() { 0.0 } else { prices.iter().sum::<f64>() / prices.len() as f64 };
+
+    let mut s = Savings { days: 1, charged_kwh: 0.0, discharged_kwh: 0.0, spot_value: 0.0, flat_value: 0.0 };
+    for &price in prices {
+        // Grid energy this interval: positive import, negative export
+        let grid = if price < CHARGE_BELOW && price < high_alert {
+            let stored = max_move.min(battery.capacity_kwh - soc).max(0.0);
+            soc += stored;
+            s.charged_kwh += stored / leg_eff;
+            stored / leg_eff
+        } else if price > high_alert {
+            let drawn = max_move.min(soc - floor).max(0.0);
+            soc -= drawn;
+            s.discharged_kwh += drawn * leg_eff;
+            -drawn * leg_eff
+        } else {
+            0.0
+        };
+        s.spot_value -= grid * price / 1000.0;
+        s.flat_value -= grid * flat / 1000.0;
+    }
+    s
+}
+
+/// Score any of the last `LOOKBACK_DAYS` completed local days not yet stored.
+pub fn update_user(db: &Db, user: &User, battery: &BatteryProfile, tz: Tz) {
+    let today = timezone::local_today(tz);
+    let since = today - chrono::Duration::days(LOOKBACK_DAYS);
+    let done = db
+        .get_savings_dates(user.chat_id, &since.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+
+    for back in 1..=LOOKBACK_DAYS {
+        let date = today - chrono::Duration::days(back);
+        let key = date.format("%Y-%m-%d").to_string();
+        if done.contains(&key) {
+            continue;
+        }
+        let (start, end) = timezone::local_day_bounds(tz, date);
+        let prices = match db.get_prices_between(&user.region, &start, &end) {
+            Ok(p) if p.len() >= MIN_INTERVALS_PER_DAY => p,
+            _ => continue,
+        };
+        let day = simulate_day(battery, &prices, user.high_alert);
+        if let Err(e) = db.upsert_savings(user.chat_id, &user.region, &key, &day) {
+            tracing::error!(chat_id = user.chat_id, error = %e, "Failed to store savings");
+        }
+    }
+}
+
+/// Totals since `days` local days before today (1 = yesterday only).
+pub fn total_since(db: &Db, chat_id: i64, tz: Tz, days: i64) -> Option<Savings> {
+    let since = timezone::local_today(tz) - chrono::Duration::days(days);
+    db.get_savings_total(chat_id, &since.format("%Y-%m-%d").to_string())
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository::tests::{db_following, price};
+
+    fn battery() -> BatteryProfile {
+        BatteryProfile { capacity_kwh: 10.0, power_kw: 5.0, efficiency: 0.81, reserve: 0.1 }
+    }
+
+    #[test]
+    fn flat_day_saves_nothing() {
+        for price in [20.0, 100.0, 500.0] {
+            let day = simulate_day(&battery(), &[price; 288], 300.0);
+            assert!(day.saved().abs() < 1e-9, "{price}: {}", day.saved());
+        }
+        // Nothing to discharge on a day that never charges
+        let day = simulate_day(&battery(), &[500.0; 288], 300.0);
+        assert_eq!(day.discharged_kwh, 0.0);
+    }
+
+    #[test]
+    fn spread_day_earns_the_spread_on_that_days_charge() {
+        let prices: Vec<f64> = [0.0; 144].into_iter().chain([500.0; 144]).collect();
+        let day = simulate_day(&battery(), &prices, 300.0);
+        // Fills from the 1 kWh reserve to 10 kWh, then sells it all back down
+        assert!((day.charged_kwh - 9.0 / 0.9).abs() < 1e-9);
+        assert!((day.discharged_kwh - 9.0 * 0.9).abs() < 1e-9);
+        let flat = 250.0;
+        let expected = (day.discharged_kwh * (500.0 - flat) + day.charged_kwh * flat) / 1000.0;
+        assert!((day.saved() - expected).abs() < 1e-9);
+        assert!(day.saved() > 0.0);
+    }
+
+    #[test]
+    fn short_days_are_skipped() {
+        let db = db_following(&["NSW1"]);
+        let user = db.get_user(1).unwrap().unwrap();
+        let tz = user.tz();
+        let yesterday = timezone::local_today(tz) - chrono::Duration::days(1);
+        let (start, _) = timezone::local_day_bounds(tz, yesterday);
+        let start = timezone::parse_market(&start).unwrap();
+        let interval = |i: i64| {
+            let interval_time = timezone::market_str(&(start + chrono::Duration::minutes(5 * i)));
+            price("NSW1", &interval_time, if i < 144 { 0.0 } else { 500.0 })
+        };
+        let key = yesterday.format("%Y-%m-%d").to_string();
+        let scored = || db.get_savings_dates(1, &key).unwrap().contains(&key);
+
+        db.insert_prices(&(1..MIN_INTERVALS_PER_DAY as i64).map(interval).collect::<Vec<_>>()).unwrap();
+        update_user(&db, &user, &battery(), tz);
+        assert!(!scored());
+
+        db.insert_prices(&(1..=288).map(interval).collect::<Vec<_>>()).unwrap();
+        update_user(&db, &user, &battery(), tz);
+        assert!(scored());
+    }
+}
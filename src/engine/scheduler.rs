@@ -1,32 +1,62 @@
-use chrono::Timelike;
+use chrono::{NaiveDate, Timelike};
+use chrono_tz::Tz;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use teloxide::prelude::*;
 
-use crate::bot::{messages, notifier};
-use crate::data::{fetcher, weather};
+use crate::bot::notifier::{self, Delivery};
+use crate::bot::{chart, messages};
+use crate::data::generators::Registry;
+use crate::data::{fetcher, notices, weather};
+use crate::db::repository::DispatchGap;
 use crate::db::Db;
-use crate::engine::analyzer;
+use crate::engine::{accuracy, analyzer, optimiser, savings};
+use crate::timezone;
 
 const REGIONS: &[&str] = &["NSW1", "VIC1", "QLD1", "SA1", "TAS1"];
 
-pub async fn run(db: Arc<Db>, bot: Bot, admin_chat_id: Option<i64>) {
+/// Most dispatch files fetched in one catch-up (a day); anything older is left as a gap for `backfill`.
+const MAX_CATCHUP: usize = 288;
+
+/// Failed downloads of one dispatch file or market notice (one per tick) before it is given up.
+const MAX_FETCH_ATTEMPTS: i64 = 5;
+
+/// Days of rooftop PV history whose peak counts as a clear day for solar classification.
+const PV_REFERENCE_DAYS: i64 = 30;
+
+/// Market notices fetched per tick; a fresh install stores this many without alerting.
+const MAX_NOTICES: usize = 20;
+
+pub async fn run(db: Arc<Db>, delivery: Arc<Delivery>, admin_chat_id: Option<i64>, nemweb_base: String, registry: Arc<Registry>) {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(30))
         .build()
         .expect("Failed to build HTTP client");
+    let bot = delivery.bot.clone();
 
     tracing::info!("Scheduler started, fetching initial data...");
 
-    // Fetch immediately on startup
-    fetch_prices(&client, &db, &bot, admin_chat_id).await;
-    forecast_fetch(&client, &db, &bot, admin_chat_id).await;
+    // Fetch immediately on startup. SCADA goes first so carbon alerts see the
+    // generation mix for the interval being analyzed.
+    let base = nemweb_base.as_str();
+    scada_fetch(&client, base, &db, &registry).await;
+    fetch_prices(&client, base, &db, &delivery, admin_chat_id).await;
+    forecast_fetch(&client, base, &db, &delivery, admin_chat_id).await;
+    p5min_fetch(&client, base, &db).await;
+    notice_fetch(&client, base, &db, &delivery).await;
+    rooftop_fetch(&client, base, &db).await;
 
-    // Prices every 60s, forecasts every 5min, cleanup daily
+    // Prices every 60s, forecasts (30-min and 5-min pre-dispatch) every 5min, cleanup daily
     let mut price_tick = tokio::time::interval(Duration::from_secs(60));
     let mut forecast_tick = tokio::time::interval(Duration::from_secs(300));
     let mut cleanup_tick = tokio::time::interval(Duration::from_secs(86400));
-    let mut summary_sent_today = false;
+    // Local date each timezone's summary was last sent, so every user gets it at 21:00 their time
+    let mut summary_sent: HashMap<Tz, NaiveDate> = HashMap::new();
+    // Local date each timezone's savings were last brought up to date
+    let mut savings_done: HashMap<Tz, NaiveDate> = HashMap::new();
+    // Market date forecast accuracy was last scored
+    let mut accuracy_done: Option<NaiveDate> = None;
 
     price_tick.tick().await;
     forecast_tick.tick().await;
@@ -35,19 +65,34 @@ pub async fn run(db: Arc<Db>, bot: Bot, admin_chat_id: Option<i64>) {
     loop {
         tokio::select! {
             _ = price_tick.tick() => {
-                fetch_prices(&client, &db, &bot, admin_chat_id).await;
-                // Check daily summary (piggyback on 60s tick)
-                let now_aest = chrono::Utc::now().with_timezone(&chrono_tz::Australia::Brisbane);
-                if now_aest.hour() == 21 && !summary_sent_today {
-                    summary_sent_today = true;
-                    handle_daily_summary(&client, &db, &bot).await;
+                scada_fetch(&client, base, &db, &registry).await;
+                fetch_prices(&client, base, &db, &delivery, admin_chat_id).await;
+                notifier::send_digests(&bot, &db).await;
+                // Score yesterday's forecasts once its last period (ending midnight) has settled
+                let now_market = timezone::now_market();
+                if now_market.hour() >= 1 && accuracy_done != Some(now_market.date_naive()) {
+                    accuracy_done = Some(now_market.date_naive());
+                    accuracy::update(&db, REGIONS);
                 }
-                if now_aest.hour() == 0 {
-                    summary_sent_today = false;
+                // Check daily summary (piggyback on 60s tick)
+                for tz in summary_timezones(&db) {
+                    let now_local = chrono::Utc::now().with_timezone(&tz);
+                    let today = now_local.date_naive();
+                    if savings_done.get(&tz) != Some(&today) {
+                        savings_done.insert(tz, today);
+                        update_savings(&db, tz);
+                    }
+                    if now_local.hour() == 21 && summary_sent.get(&tz) != Some(&today) {
+                        summary_sent.insert(tz, today);
+                        handle_daily_summary(&client, &db, &bot, tz).await;
+                    }
                 }
             }
             _ = forecast_tick.tick() => {
-                forecast_fetch(&client, &db, &bot, admin_chat_id).await;
+                forecast_fetch(&client, base, &db, &delivery, admin_chat_id).await;
+                p5min_fetch(&client, base, &db).await;
+                notice_fetch(&client, base, &db, &delivery).await;
+                rooftop_fetch(&client, base, &db).await;
             }
             _ = cleanup_tick.tick() => {
                 if let Err(e) = db.cleanup_old_records() {
@@ -62,37 +107,235 @@ pub async fn run(db: Arc<Db>, bot: Bot, admin_chat_id: Option<i64>) {
 
 // ── Fetch implementations ─────────────────────────────────────────────
 
+/// Ingest every dispatch interval newer than what is stored, oldest first, so a
+/// missed tick or a restart doesn't lose intervals. Only the newest interval
+/// is analysed for alerts. A file that fails to download is retried on later ticks;
+/// see `record_gaps` for when a missing interval counts as a gap.
 async fn fetch_prices(
     client: &reqwest::Client,
+    base: &str,
     db: &Arc<Db>,
-    bot: &Bot,
+    delivery: &Delivery,
     admin_chat_id: Option<i64>,
 ) {
-    match fetcher::fetch_dispatch(client).await {
-        Ok(prices) => {
-            tracing::info!(count = prices.len(), "Fetched dispatch prices");
-            process_prices(db, bot, &prices).await;
-        }
+    let files = match fetcher::list_dispatch(client, base).await {
+        Ok(f) => f,
         Err(e) => {
             tracing::error!(error=%e, "Dispatch fetch failed");
             if let Some(admin) = admin_chat_id {
-                let _ = bot
-                    .send_message(ChatId(admin), format!("\u{26a0}\u{fe0f} Dispatch fetch failed\n{e}"))
+                let _ = delivery
+                    .bot
+                    .send_message(
+                        ChatId(admin),
+                        format!("\u{26a0}\u{fe0f} Dispatch fetch failed\n{e}"),
+                    )
                     .await;
             }
+            return;
+        }
+    };
+
+    let last = db.get_last_intervals().unwrap_or_default();
+    // The least advanced region decides where catch-up starts; a fresh install takes the newest file only
+    let cursor = REGIONS.iter().filter_map(|r| last.get(*r)).min();
+    let pending: Vec<_> = match cursor {
+        Some(c) => files.iter().filter(|f| &f.interval_time > c).collect(),
+        None => files.last().into_iter().collect(),
+    };
+    let pending = &pending[pending.len().saturating_sub(MAX_CATCHUP)..];
+    let newest = pending.last().map(|f| f.interval_time.clone());
+
+    // Earlier downloads that failed: retried while their file is still listed
+    let retries = db.get_dispatch_retries().unwrap_or_default();
+    let mut given_up: Vec<String> = retries
+        .keys()
+        .filter(|t| !files.iter().any(|f| &&f.interval_time == t))
+        .cloned()
+        .collect();
+    let retrying: Vec<_> = files
+        .iter()
+        .filter(|f| {
+            retries.contains_key(&f.interval_time)
+                && !pending.iter().any(|p| p.interval_time == f.interval_time)
+        })
+        .collect();
+
+    if newest.is_none() && retrying.is_empty() && given_up.is_empty() {
+        tracing::debug!("No new dispatch interval yet");
+        return;
+    }
+    if pending.len() > 1 {
+        tracing::info!(count = pending.len(), from = %pending[0].interval_time, "Catching up dispatch intervals");
+    }
+
+    let mut latest = Vec::new();
+    for file in retrying.into_iter().chain(pending.iter().copied()) {
+        match fetcher::fetch_dispatch_file(client, file).await {
+            Ok(dispatch) => {
+                // Stored first so spike analysis can see which interconnectors are at their limit
+                if let Err(e) = db.insert_interconnectors(&dispatch.interconnectors) {
+                    tracing::error!(interval = %file.interval_time, error=%e, "Failed to store interconnector flows");
+                }
+                if Some(&file.interval_time) == newest.as_ref() {
+                    latest = dispatch.prices;
+                } else if let Err(e) = db.insert_prices(&dispatch.prices) {
+                    tracing::error!(interval = %file.interval_time, error=%e, "Failed to store dispatch prices");
+                }
+                if retries.contains_key(&file.interval_time) {
+                    let _ = db.clear_dispatch_retry(&file.interval_time);
+                }
+            }
+            Err(e) => {
+                tracing::warn!(interval = %file.interval_time, error=%e, "Dispatch file fetch failed");
+                match db.note_dispatch_failure(&file.interval_time, &e.to_string()) {
+                    Ok(attempts) if attempts >= MAX_FETCH_ATTEMPTS => {
+                        given_up.push(file.interval_time.clone())
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::error!(error=%e, "Failed to record dispatch retry"),
+                }
+            }
+        }
+    }
+
+    if let Some(newest) = &newest {
+        if !latest.is_empty() {
+            tracing::info!(count = latest.len(), interval = %newest, "Fetched dispatch prices");
+            process_prices(db, delivery, &latest).await;
+        }
+    }
+    for interval in &given_up {
+        let _ = db.clear_dispatch_retry(interval);
+    }
+    record_gaps(
+        db,
+        &delivery.bot,
+        admin_chat_id,
+        &last,
+        newest.as_deref(),
+        &given_up,
+    )
+    .await;
+}
+
+/// Record intervals that are missing for good, and tell the admin about any not seen
+/// before. That's intervals between each region's previous latest interval and
+/// `newest` whose file wasn't on the listing (ones still being retried are left for
+/// now), plus the `given_up` intervals whose downloads kept failing.
+async fn record_gaps(
+    db: &Db,
+    bot: &Bot,
+    admin_chat_id: Option<i64>,
+    previous: &HashMap<String, String>,
+    newest: Option<&str>,
+    given_up: &[String],
+) {
+    let retrying: HashSet<String> = db
+        .get_dispatch_retries()
+        .unwrap_or_default()
+        .into_keys()
+        .collect();
+    let step = chrono::Duration::minutes(5);
+    let mut new_gaps = Vec::new();
+    for region in REGIONS {
+        if let (Some(prev), Some(newest)) = (previous.get(*region), newest) {
+            let stored = db
+                .get_interval_times(region, prev, newest)
+                .unwrap_or_default();
+            for run in missing_runs(prev, newest, &stored, &retrying) {
+                new_gaps.extend(store_gap(db, region, &run));
+            }
+        }
+        for interval in given_up {
+            let Some(t) = timezone::parse_market(interval) else {
+                continue;
+            };
+            let around = (
+                timezone::market_str(&(t - step)),
+                timezone::market_str(&(t + step)),
+            );
+            if !db
+                .get_interval_times(region, &around.0, &around.1)
+                .unwrap_or_default()
+                .contains(interval)
+            {
+                new_gaps.extend(store_gap(db, region, std::slice::from_ref(interval)));
+            }
+        }
+    }
+
+    if new_gaps.is_empty() {
+        return;
+    }
+    tracing::warn!(count = new_gaps.len(), "Recorded dispatch gaps");
+    if let Some(admin) = admin_chat_id {
+        let _ = bot
+            .send_message(ChatId(admin), messages::format_gap_alert(&new_gaps))
+            .await;
+    }
+}
+
+/// Runs of consecutive 5-minute intervals after `prev` and before `newest` that are
+/// neither stored nor still being retried. `newest` itself is retried next tick if it
+/// failed, so it's never part of a run.
+fn missing_runs(
+    prev: &str,
+    newest: &str,
+    stored: &HashSet<String>,
+    retrying: &HashSet<String>,
+) -> Vec<Vec<String>> {
+    let (Some(mut t), Some(end)) = (timezone::parse_market(prev), timezone::parse_market(newest))
+    else {
+        return Vec::new();
+    };
+    let step = chrono::Duration::minutes(5);
+    let mut runs = Vec::new();
+    let mut run: Vec<String> = Vec::new();
+    t += step;
+    while t < end {
+        let key = timezone::market_str(&t);
+        if stored.contains(&key) || retrying.contains(&key) {
+            runs.extend((!run.is_empty()).then(|| std::mem::take(&mut run)));
+        } else {
+            run.push(key);
+        }
+        t += step;
+    }
+    runs.extend((!run.is_empty()).then_some(run));
+    runs
+}
+
+/// Store one run of missing intervals; returns it if it wasn't already recorded.
+fn store_gap(db: &Db, region: &str, run: &[String]) -> Option<DispatchGap> {
+    let (first, last) = (run.first()?, run.last()?);
+    match db.insert_gap(region, first, last, run.len() as i64) {
+        Ok(true) => Some(DispatchGap {
+            region: region.to_string(),
+            gap_start: first.clone(),
+            gap_end: last.clone(),
+            intervals: run.len() as i64,
+            still_missing: run.len() as i64,
+        }),
+        Ok(false) => None,
+        Err(e) => {
+            tracing::error!(region, error=%e, "Failed to record dispatch gap");
+            None
         }
     }
 }
 
 /// Store prices in DB and run alert analysis.
-async fn process_prices(db: &Arc<Db>, bot: &Bot, prices: &[crate::data::parser::PriceRecord]) {
-    for p in prices {
-        let _ = db.insert_price(&p.region, p.price, &p.interval_time);
+async fn process_prices(db: &Arc<Db>, delivery: &Delivery, prices: &[crate::data::parser::PriceRecord]) {
+    if let Err(e) = db.insert_prices(prices) {
+        tracing::error!(error=%e, "Failed to store dispatch prices");
+    }
+    if let Some(mqtt) = &delivery.mqtt {
+        mqtt.publish_prices(prices);
     }
     let alerts = analyzer::analyze(db, prices);
     if !alerts.is_empty() {
         tracing::info!(count = alerts.len(), "Sending price alerts");
-        notifier::send_alerts(bot, db, alerts).await;
+        notifier::send_alerts(delivery, db, alerts).await;
     }
     for region in REGIONS {
         let current = prices
@@ -102,32 +345,61 @@ async fn process_prices(db: &Arc<Db>, bot: &Bot, prices: &[crate::data::parser::
             .unwrap_or(0.0);
         let fc_alerts = analyzer::analyze_forecasts(db, region, current);
         if !fc_alerts.is_empty() {
-            notifier::send_alerts(bot, db, fc_alerts).await;
+            notifier::send_alerts(delivery, db, fc_alerts).await;
         }
     }
 }
 
 async fn forecast_fetch(
     client: &reqwest::Client,
+    base: &str,
     db: &Arc<Db>,
-    bot: &Bot,
+    delivery: &Delivery,
     admin_chat_id: Option<i64>,
 ) {
-    match fetcher::fetch_predispatch(client).await {
-        Ok(forecasts) => {
-            tracing::info!(count = forecasts.len(), "Fetched pre-dispatch forecasts");
-            let published_at = chrono::Utc::now()
-                .with_timezone(&chrono_tz::Australia::Brisbane)
-                .format("%Y/%m/%d %H:%M:%S")
-                .to_string();
-            for f in &forecasts {
-                let _ = db.insert_forecast(&f.region, &f.forecast_time, f.price, &published_at);
+    match fetcher::fetch_predispatch(client, base).await {
+        Ok(run) => {
+            // Files without a C row fall back to fetch time, as every fetch used to
+            let published_at = run
+                .published_at
+                .unwrap_or_else(|| timezone::market_str(&chrono::Utc::now()));
+            let inserted = match db.insert_forecasts(&run.forecasts, &published_at) {
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::error!(error=%e, "Failed to store pre-dispatch forecasts");
+                    return;
+                }
+            };
+            // Republished on every fetch so `next_price` moves along with the clock
+            if let Some(mqtt) = &delivery.mqtt {
+                let now = chrono::Utc::now();
+                let (from, to) = (timezone::market_str(&now), timezone::market_str(&(now + chrono::Duration::hours(48))));
+                for region in REGIONS {
+                    match db.get_forecasts(region, &from, &to) {
+                        Ok(forecast) => mqtt.publish_forecast(region, &published_at, &forecast),
+                        Err(e) => tracing::error!(region, error=%e, "Failed to read forecasts for MQTT"),
+                    }
+                }
+            }
+            // The same run is fetched several times between publishes; only a new one can be revised
+            if inserted == 0 {
+                tracing::debug!(%published_at, "Pre-dispatch run already stored");
+                return;
+            }
+            tracing::info!(count = run.forecasts.len(), %published_at, "Fetched pre-dispatch forecasts");
+            for region in REGIONS {
+                let alerts = analyzer::analyze_revisions(db, region);
+                if !alerts.is_empty() {
+                    tracing::info!(region, count = alerts.len(), "Sending forecast revision alerts");
+                    notifier::send_alerts(delivery, db, alerts).await;
+                }
             }
         }
         Err(e) => {
             tracing::error!(error=%e, "Pre-dispatch fetch failed");
             if let Some(admin) = admin_chat_id {
-                let _ = bot
+                let _ = delivery
+                    .bot
                     .send_message(ChatId(admin), format!("\u{26a0}\u{fe0f} Pre-dispatch fetch failed\n{e}"))
                     .await;
             }
@@ -135,39 +407,270 @@ async fn forecast_fetch(
     }
 }
 
+/// Store the latest P5MIN run, which refines the first hour of the outlook.
+async fn p5min_fetch(client: &reqwest::Client, base: &str, db: &Db) {
+    match fetcher::fetch_p5min(client, base).await {
+        Ok(run) => {
+            let run_time = run
+                .published_at
+                .unwrap_or_else(|| timezone::market_str(&chrono::Utc::now()));
+            match db.insert_p5min(&run.forecasts, &run_time) {
+                Ok(n) => tracing::info!(count = n, %run_time, "Fetched P5MIN forecasts"),
+                Err(e) => tracing::error!(error=%e, "Failed to store P5MIN forecasts"),
+            }
+        }
+        // Not worth paging the admin: the outlook falls back to 30-minute pre-dispatch
+        Err(e) => tracing::error!(error=%e, "P5MIN fetch failed"),
+    }
+}
+
+/// Aggregate the latest unit outputs into each region's generation mix.
+async fn scada_fetch(client: &reqwest::Client, base: &str, db: &Db, registry: &Registry) {
+    match fetcher::fetch_scada(client, base).await {
+        Ok(scada) => match db.insert_mix(&registry.aggregate(&scada)) {
+            Ok(0) => {}
+            Ok(n) => tracing::debug!(count = n, "Stored generation mix"),
+            Err(e) => tracing::error!(error=%e, "Failed to store generation mix"),
+        },
+        Err(e) => tracing::warn!(error=%e, "SCADA fetch failed"),
+    }
+}
+
+/// Store the latest rooftop PV actuals and forecast (both published half-hourly).
+async fn rooftop_fetch(client: &reqwest::Client, base: &str, db: &Db) {
+    match fetcher::fetch_rooftop_actual(client, base).await {
+        Ok(recs) => {
+            if let Err(e) = db.insert_rooftop_pv("actual", &recs) {
+                tracing::error!(error=%e, "Failed to store rooftop PV actuals");
+            }
+        }
+        Err(e) => tracing::warn!(error=%e, "Rooftop PV actual fetch failed"),
+    }
+    match fetcher::fetch_rooftop_forecast(client, base).await {
+        Ok(recs) => match db.insert_rooftop_pv("forecast", &recs) {
+            Ok(n) => tracing::debug!(count = n, "Fetched rooftop PV forecast"),
+            Err(e) => tracing::error!(error=%e, "Failed to store rooftop PV forecast"),
+        },
+        // Solar classification falls back to the BOM forecast
+        Err(e) => tracing::warn!(error=%e, "Rooftop PV forecast fetch failed"),
+    }
+}
+
+/// Store market notices published since the last one seen and alert opted-in users.
+async fn notice_fetch(client: &reqwest::Client, base: &str, db: &Arc<Db>, delivery: &Delivery) {
+    let listed = match fetcher::list_market_notices(client, base).await {
+        Ok(l) => l,
+        Err(e) => {
+            tracing::error!(error=%e, "Market notice listing failed");
+            return;
+        }
+    };
+    let last = db.get_last_notice_id().unwrap_or(None);
+    let pending: Vec<_> = listed.iter().filter(|(id, _)| last.is_none_or(|l| *id > l)).collect();
+    let pending = &pending[pending.len().saturating_sub(MAX_NOTICES)..];
+
+    // Newer notices move `last` past earlier ones that failed, so those are retried by ID
+    // while they are still listed
+    let retries = db.get_notice_retries().unwrap_or_default();
+    for id in retries.keys().filter(|id| !listed.iter().any(|(l, _)| l == *id)) {
+        tracing::warn!(id, "Market notice no longer listed; giving up");
+        let _ = db.clear_notice_retry(*id);
+    }
+    let retrying = listed
+        .iter()
+        .filter(|(id, _)| retries.contains_key(id) && !pending.iter().any(|(p, _)| p == id));
+
+    let mut new = Vec::new();
+    for (id, url) in retrying.chain(pending.iter().copied()) {
+        let text = match fetcher::fetch_market_notice(client, url).await {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!(id, error=%e, "Market notice fetch failed");
+                // A fresh install is only seeding history, so nothing is lost
+                if last.is_some() {
+                    match db.note_notice_failure(*id, &e.to_string()) {
+                        Ok(attempts) if attempts >= MAX_FETCH_ATTEMPTS => {
+                            tracing::warn!(id, attempts, "Market notice fetch failed too often; giving up");
+                            let _ = db.clear_notice_retry(*id);
+                        }
+                        Ok(_) => {}
+                        Err(e) => tracing::error!(error=%e, "Failed to record market notice retry"),
+                    }
+                }
+                continue;
+            }
+        };
+        if retries.contains_key(id) {
+            let _ = db.clear_notice_retry(*id);
+        }
+        let Some(notice) = notices::parse_notice(&text) else {
+            tracing::warn!(id, "Unparseable market notice");
+            continue;
+        };
+        match db.insert_notice(&notice) {
+            Ok(true) => new.push(notice),
+            Ok(false) => {}
+            Err(e) => tracing::error!(id, error=%e, "Failed to store market notice"),
+        }
+    }
+    if new.is_empty() {
+        return;
+    }
+    tracing::info!(count = new.len(), "Fetched market notices");
+    // A fresh install only seeds history; those notices are not news
+    if last.is_some() {
+        let alerts = analyzer::analyze_notices(db, &new);
+        if !alerts.is_empty() {
+            tracing::info!(count = alerts.len(), "Sending market notice alerts");
+            notifier::send_alerts(delivery, db, alerts).await;
+        }
+    }
+}
+
 // ── Daily summary ─────────────────────────────────────────────────────
 
-async fn handle_daily_summary(client: &reqwest::Client, db: &Arc<Db>, bot: &Bot) {
-    let now_aest = chrono::Utc::now().with_timezone(&chrono_tz::Australia::Brisbane);
-    let date_prefix = now_aest.format("%Y/%m/%d").to_string();
-    let date_display = now_aest.format("%d %b %Y").to_string();
+/// Every timezone a summary may be due in: region defaults plus user overrides.
+fn summary_timezones(db: &Db) -> Vec<Tz> {
+    let mut names: BTreeSet<&str> = REGIONS.iter().map(|r| timezone::region_tz(r).name()).collect();
+    let custom = db.get_custom_timezones().unwrap_or_default();
+    names.extend(custom.iter().map(String::as_str));
+    names.iter().filter_map(|n| n.parse().ok()).collect()
+}
 
+/// Score yesterday (and any missed days) for battery owners in timezone `tz`.
+fn update_savings(db: &Db, tz: Tz) {
     for region in REGIONS {
-        let stats = db.get_daily_stats(region, &date_prefix).ok().flatten();
+        let users = match db.get_active_users_by_region(region) {
+            Ok(u) => u,
+            Err(_) => continue,
+        };
+        // The battery is at home, so only the home region's prices apply
+        for user in users.iter().filter(|u| u.tz() == tz && u.is_home()) {
+            if let Ok(Some(battery)) = db.get_battery(user.chat_id) {
+                savings::update_user(db, user, &battery, tz);
+            }
+        }
+    }
+}
+
+/// Send the daily summary to users whose local timezone is `tz`, one per region they follow.
+async fn handle_daily_summary(client: &reqwest::Client, db: &Arc<Db>, bot: &Bot, tz: Tz) {
+    let today = timezone::local_today(tz);
+    let (start, end) = timezone::local_day_bounds(tz, today);
+    let (tomorrow_start, tomorrow_end) = timezone::local_day_bounds(tz, today.succ_opt().unwrap_or(today));
+    let date_display = today.format("%d %b %Y").to_string();
+
+    for region in REGIONS {
+        let users: Vec<_> = match db.get_active_users_by_region(region) {
+            Ok(u) => u.into_iter().filter(|u| u.tz() == tz).collect(),
+            Err(_) => continue,
+        };
+        if users.is_empty() {
+            continue;
+        }
+
+        let stats = db.get_daily_stats(region, &start, &end).ok().flatten();
         let peak_time = db
-            .get_daily_peak_time(region, &date_prefix)
+            .get_daily_peak_time(region, &start, &end)
             .ok()
             .flatten();
         let weather_fc = weather::fetch_tomorrow(client, region).await.ok().flatten();
-
-        let users = match db.get_active_users_by_region(region) {
-            Ok(u) => u,
-            Err(_) => continue,
+        let pv_today = db.get_rooftop_peak(region, "actual", &start, &end).ok().flatten();
+        let pv_tomorrow = db
+            .get_rooftop_peak(region, "forecast", &tomorrow_start, &tomorrow_end)
+            .ok()
+            .flatten();
+        let pv_since = timezone::market_str(&(timezone::now_market() - chrono::Duration::days(PV_REFERENCE_DAYS)));
+        let pv_reference = db.get_rooftop_max(region, &pv_since).ok().flatten();
+        let solar = match (&pv_tomorrow, pv_reference) {
+            (Some((_, peak)), Some(reference)) => Some(weather::classify_solar_mw(*peak, reference)),
+            _ => weather_fc.as_ref().map(|w| w.solar),
         };
+        // Forecast times are period ends, which get_forecasts' (after, before] bounds match
+        let tomorrow_fc = db
+            .get_forecasts(region, &tomorrow_start, &tomorrow_end)
+            .unwrap_or_default();
+        let forecast_score = accuracy::rolling(db, region, 7).remove(accuracy::HEADLINE_BUCKET);
+        let today_prices = db.get_interval_prices(region, &start, &end).unwrap_or_default();
 
         for user in &users {
             let alerts_today = db.count_alerts_last_24h(user.chat_id).unwrap_or(0);
-            let text = messages::format_daily_summary(
+            // Battery plans and savings only make sense for the home region
+            let battery = if user.is_home() { db.get_battery(user.chat_id).ok().flatten() } else { None };
+            let plan = battery
+                .as_ref()
+                .map(|b| optimiser::optimise(b, &tomorrow_fc, optimiser::DEFAULT_START_SOC));
+            let saved = battery
+                .as_ref()
+                .and_then(|_| savings::total_since(db, user.chat_id, tz, savings::LOOKBACK_DAYS));
+            let text = messages::format_daily_summary(&messages::DailySummary {
                 region,
-                &date_display,
-                stats.as_ref(),
-                peak_time.as_deref(),
-                weather_fc.as_ref(),
+                date_display: &date_display,
+                stats: stats.as_ref(),
+                peak_time: peak_time.as_deref(),
+                weather: weather_fc.as_ref(),
+                solar,
+                pv_today: pv_today.as_ref().map(|(t, mw)| (t.as_str(), *mw)),
+                pv_tomorrow: pv_tomorrow.as_ref().map(|(t, mw)| (t.as_str(), *mw)),
                 alerts_today,
-            );
-            let _ = bot.send_message(ChatId(user.chat_id), &text).await;
+                plan: plan.as_ref(),
+                savings: saved.as_ref(),
+                accuracy: forecast_score.as_ref(),
+                tz,
+            });
+            let png = chart::try_render(user, &today_prices, &tomorrow_fc).await;
+            let _ = chart::send_with_chart(bot, ChatId(user.chat_id), png, &text).await;
             tokio::time::sleep(Duration::from_millis(50)).await;
         }
     }
-    tracing::info!("Daily summary sent");
+    tracing::info!(timezone = tz.name(), "Daily summary sent");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(times: &[&str]) -> HashSet<String> {
+        times.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn missing_runs_skip_stored_and_retrying() {
+        let stored = set(&["2026/01/15 00:10:00", "2026/01/15 00:25:00"]);
+        let retrying = set(&["2026/01/15 00:20:00"]);
+        let runs = missing_runs(
+            "2026/01/15 00:00:00",
+            "2026/01/15 00:35:00",
+            &stored,
+            &retrying,
+        );
+        assert_eq!(
+            runs,
+            vec![
+                vec!["2026/01/15 00:05:00".to_string()],
+                vec!["2026/01/15 00:15:00".into()],
+                vec!["2026/01/15 00:30:00".into()]
+            ]
+        );
+        // Consecutive intervals after prev, nothing missing
+        assert!(missing_runs(
+            "2026/01/15 00:00:00",
+            "2026/01/15 00:05:00",
+            &set(&[]),
+            &set(&[])
+        )
+        .is_empty());
+        assert_eq!(
+            missing_runs(
+                "2026/01/15 00:00:00",
+                "2026/01/15 00:15:00",
+                &set(&[]),
+                &set(&[])
+            ),
+            vec![vec![
+                "2026/01/15 00:05:00".to_string(),
+                "2026/01/15 00:10:00".into()
+            ]]
+        );
+    }
 }
@@ -0,0 +1,299 @@
+use chrono::{Datelike, Timelike, Weekday};
+
+use crate::timezone;
+
+/// Retailer market, environmental and metering charges, c/kWh excl. GST.
+const MARKET_CHARGES_C: f64 = 2.5;
+const GST: f64 = 1.1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Peak,
+    Shoulder,
+    OffPeak,
+}
+
+impl Period {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Peak => "peak",
+            Self::Shoulder => "shoulder",
+            Self::OffPeak => "off-peak",
+        }
+    }
+}
+
+/// A distribution network tariff on a wholesale pass-through retail plan.
+pub struct Tariff {
+    pub id: &'static str,
+    pub network: &'static str,
+    pub name: &'static str,
+    pub region: &'static str,
+    /// Local-time windows as `[start_hour, end_hour)`; anything else is `default_period`.
+    pub windows: &'static [(u32, u32, Period)],
+    pub default_period: Period,
+    /// Peak windows only apply on weekdays; weekends fall back to shoulder.
+    pub peak_weekdays_only: bool,
+    /// Network charges by period, c/kWh excl. GST.
+    pub peak_c: f64,
+    pub shoulder_c: f64,
+    pub offpeak_c: f64,
+    /// Daily supply charge, c/day excl. GST.
+    pub daily_supply_c: f64,
+    /// Distribution loss factor applied to the wholesale price.
+    pub loss_factor: f64,
+    /// Fee deducted from each exported kWh, c/kWh.
+    pub export_fee_c: f64,
+}
+
+/// What a household pays and earns for one interval, c/kWh incl. GST on imports.
+#[derive(Clone, Copy)]
+pub struct RetailPrice {
+    pub import_c: f64,
+    pub export_c: f64,
+    pub period: Period,
+}
+
+// Indicative network tariffs; check each DNSP's annual price list when updating.
+const TOU_3PM_9PM: &[(u32, u32, Period)] = &[(15, 21, Period::Peak)];
+
+pub const TARIFFS: &[Tariff] = &[
+    Tariff {
+        id: "ausgrid_tou", network: "Ausgrid", name: "Time of use", region: "NSW1",
+        windows: &[(7, 15, Period::Shoulder), (15, 21, Period::Peak), (21, 22, Period::Shoulder)],
+        default_period: Period::OffPeak, peak_weekdays_only: true,
+        peak_c: 24.0, shoulder_c: 6.5, offpeak_c: 4.5, daily_supply_c: 110.0, loss_factor: 1.05, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "ausgrid_flat", network: "Ausgrid", name: "Flat", region: "NSW1",
+        windows: &[], default_period: Period::Shoulder, peak_weekdays_only: false,
+        peak_c: 11.0, shoulder_c: 11.0, offpeak_c: 11.0, daily_supply_c: 105.0, loss_factor: 1.05, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "endeavour_tou", network: "Endeavour Energy", name: "Time of use", region: "NSW1",
+        windows: &[(7, 16, Period::Shoulder), (16, 20, Period::Peak), (20, 22, Period::Shoulder)],
+        default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 27.0, shoulder_c: 8.0, offpeak_c: 5.0, daily_supply_c: 95.0, loss_factor: 1.05, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "essential_tou", network: "Essential Energy", name: "Time of use", region: "NSW1",
+        windows: &[(7, 17, Period::Shoulder), (17, 20, Period::Peak), (20, 22, Period::Shoulder)],
+        default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 30.0, shoulder_c: 10.0, offpeak_c: 6.0, daily_supply_c: 140.0, loss_factor: 1.07, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "citipower_tou", network: "CitiPower", name: "Time of use", region: "VIC1",
+        windows: TOU_3PM_9PM, default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 18.0, shoulder_c: 5.0, offpeak_c: 5.0, daily_supply_c: 60.0, loss_factor: 1.04, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "powercor_tou", network: "Powercor", name: "Time of use", region: "VIC1",
+        windows: TOU_3PM_9PM, default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 20.0, shoulder_c: 6.0, offpeak_c: 6.0, daily_supply_c: 70.0, loss_factor: 1.06, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "ausnet_tou", network: "AusNet Services", name: "Time of use", region: "VIC1",
+        windows: TOU_3PM_9PM, default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 22.0, shoulder_c: 7.0, offpeak_c: 7.0, daily_supply_c: 80.0, loss_factor: 1.06, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "jemena_tou", network: "Jemena", name: "Time of use", region: "VIC1",
+        windows: TOU_3PM_9PM, default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 21.0, shoulder_c: 6.0, offpeak_c: 6.0, daily_supply_c: 65.0, loss_factor: 1.04, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "united_tou", network: "United Energy", name: "Time of use", region: "VIC1",
+        windows: TOU_3PM_9PM, default_period: Period::OffPeak, peak_weekdays_only: false,
+        peak_c: 19.0, shoulder_c: 6.0, offpeak_c: 6.0, daily_supply_c: 62.0, loss_factor: 1.04, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "energex_tou", network: "Energex", name: "Time of use", region: "QLD1",
+        windows: &[(11, 16, Period::OffPeak), (16, 21, Period::Peak)],
+        default_period: Period::Shoulder, peak_weekdays_only: false,
+        peak_c: 23.0, shoulder_c: 8.0, offpeak_c: 3.5, daily_supply_c: 75.0, loss_factor: 1.05, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "energex_flat", network: "Energex", name: "Flat", region: "QLD1",
+        windows: &[], default_period: Period::Shoulder, peak_weekdays_only: false,
+        peak_c: 10.5, shoulder_c: 10.5, offpeak_c: 10.5, daily_supply_c: 70.0, loss_factor: 1.05, export_fee_c: 0.0,
+    },
+    Tariff {
+        id: "sapn_tou", network: "SA Power Networks", name: "Time of use", region: "SA1",
+        windows: &[(6, 10, Period::Peak), (10, 15, Period::OffPeak), (15, 24, Period::Peak)],
+        default_period: Period::Shoulder, peak_weekdays_only: false,
+        peak_c: 25.0, shoulder_c: 9.0, offpeak_c: 4.0, daily_supply_c: 85.0, loss_factor: 1.07, export_fee_c: 1.0,
+    },
+    Tariff {
+        id: "sapn_flat", network: "SA Power Networks", name: "Flat", region: "SA1",
+        windows: &[], default_period: Period::Shoulder, peak_weekdays_only: false,
+        peak_c: 16.0, shoulder_c: 16.0, offpeak_c: 16.0, daily_supply_c: 80.0, loss_factor: 1.07, export_fee_c: 1.0,
+    },
+    Tariff {
+        id: "tasnetworks_tou", network: "TasNetworks", name: "Time of use", region: "TAS1",
+        windows: &[(7, 10, Period::Peak), (16, 21, Period::Peak)],
+        default_period: Period::OffPeak, peak_weekdays_only: true,
+        peak_c: 18.0, shoulder_c: 6.0, offpeak_c: 6.0, daily_supply_c: 90.0, loss_factor: 1.04, export_fee_c: 0.0,
+    },
+];
+
+/// Length of a dispatch (and P5MIN) interval, minutes.
+pub const DISPATCH_MINUTES: i64 = 5;
+/// Length of a 30-minute pre-dispatch period, minutes.
+pub const PREDISPATCH_MINUTES: i64 = 30;
+
+/// Minutes covered by each point of an outlook from `Db::get_outlook`: P5MIN intervals
+/// come 5 minutes apart and pre-dispatch periods follow further apart, so a point is a
+/// pre-dispatch period when the gap from the point before is longer than 5 minutes. The
+/// first point goes by the gap to the second.
+pub fn outlook_minutes(points: &[(String, f64)]) -> Vec<i64> {
+    let times: Vec<_> = points.iter().map(|(t, _)| timezone::parse_market(t)).collect();
+    let length = |a: usize, b: usize| match (times[a], times[b]) {
+        (Some(a), Some(b)) if (b - a).num_minutes() <= DISPATCH_MINUTES => DISPATCH_MINUTES,
+        _ => PREDISPATCH_MINUTES,
+    };
+    (0..times.len())
+        .map(|i| match i {
+            0 if times.len() > 1 => length(0, 1),
+            0 => PREDISPATCH_MINUTES,
+            i => length(i - 1, i),
+        })
+        .collect()
+}
+
+pub fn find(id: &str) -> Option<&'static Tariff> {
+    TARIFFS.iter().find(|t| t.id == id)
+}
+
+pub fn for_region(region: &str) -> impl Iterator<Item = &'static Tariff> + '_ {
+    TARIFFS.iter().filter(move |t| t.region == region)
+}
+
+impl Tariff {
+    /// TOU period for an interval of `minutes` ending at `interval_time`, judged by its
+    /// start in the network's local time.
+    pub fn period_at(&self, interval_time: &str, minutes: i64) -> Period {
+        let local = match timezone::to_local(interval_time, timezone::region_tz(self.region)) {
+            Some(t) => t - chrono::Duration::minutes(minutes),
+            None => return self.default_period,
+        };
+        let hour = local.hour();
+        let weekend = matches!(local.weekday(), Weekday::Sat | Weekday::Sun);
+        let period = self
+            .windows
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&hour))
+            .map(|(_, _, p)| *p)
+            .unwrap_or(self.default_period);
+        if period == Period::Peak && weekend && self.peak_weekdays_only {
+            Period::Shoulder
+        } else {
+            period
+        }
+    }
+
+    fn network_c(&self, period: Period) -> f64 {
+        match period {
+            Period::Peak => self.peak_c,
+            Period::Shoulder => self.shoulder_c,
+            Period::OffPeak => self.offpeak_c,
+        }
+    }
+
+    /// Effective retail prices for a wholesale price ($/MWh) over an interval of `minutes`
+    /// ending at `interval_time`.
+    pub fn retail_price(&self, wholesale_mwh: f64, interval_time: &str, minutes: i64) -> RetailPrice {
+        let period = self.period_at(interval_time, minutes);
+        let energy_c = wholesale_mwh / 10.0 * self.loss_factor;
+        RetailPrice {
+            import_c: (energy_c + self.network_c(period) + MARKET_CHARGES_C) * GST,
+            export_c: energy_c - self.export_fee_c,
+            period,
+        }
+    }
+    /// Hours of stored prices (interval_time, $/MWh; oldest first) whose retail import
+    /// price is above `cents`. Each interval counts the minutes since the one before, at
+    /// most 30 (5 for the first), as `Db::get_price_stats` weighs them.
+    pub fn hours_above(&self, prices: &[(String, f64)], cents: f64) -> f64 {
+        let mut prev: Option<chrono::DateTime<chrono_tz::Tz>> = None;
+        let mut minutes = 0;
+        for (time, price) in prices {
+            let at = timezone::parse_market(time);
+            let length = match (prev, at) {
+                (Some(prev), Some(at)) => (at - prev).num_minutes().min(PREDISPATCH_MINUTES),
+                _ => DISPATCH_MINUTES,
+            };
+            prev = at;
+            if self.retail_price(*price, time, length).import_c > cents {
+                minutes += length;
+            }
+        }
+        minutes as f64 / 60.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // July, so the eastern states are on standard time and market time is local time
+    // (16 July 2026 is a Thursday, the 18th a Saturday)
+
+    #[test]
+    fn periods_go_by_interval_start() {
+        let ausgrid = find("ausgrid_tou").unwrap();
+        let at = |t: &str, m| ausgrid.period_at(t, m).label();
+        assert_eq!(at("2026/07/16 15:00:00", DISPATCH_MINUTES), "shoulder");
+        assert_eq!(at("2026/07/16 15:05:00", DISPATCH_MINUTES), "peak");
+        assert_eq!(at("2026/07/16 21:00:00", DISPATCH_MINUTES), "peak");
+        assert_eq!(at("2026/07/16 21:05:00", DISPATCH_MINUTES), "shoulder");
+        assert_eq!(at("2026/07/16 22:05:00", DISPATCH_MINUTES), "off-peak");
+        // A pre-dispatch period ending 15:30 started at 15:00
+        assert_eq!(at("2026/07/16 15:30:00", PREDISPATCH_MINUTES), "peak");
+        assert_eq!(at("2026/07/16 15:00:00", PREDISPATCH_MINUTES), "shoulder");
+        assert_eq!(at("2026/07/16 07:30:00", PREDISPATCH_MINUTES), "shoulder");
+        // Ending at midnight Friday, so still Friday's off-peak
+        assert_eq!(at("2026/07/18 00:00:00", PREDISPATCH_MINUTES), "off-peak");
+    }
+
+    #[test]
+    fn hours_above_a_retail_threshold_follow_the_period() {
+        let ausgrid = find("ausgrid_tou").unwrap();
+        let shoulder = ausgrid.retail_price(300.0, "2026/07/16 15:00:00", DISPATCH_MINUTES).import_c;
+        let peak = ausgrid.retail_price(300.0, "2026/07/16 15:05:00", DISPATCH_MINUTES).import_c;
+        assert!(peak > shoulder);
+        let prices: Vec<(String, f64)> = ["14:55", "15:00", "15:05", "15:10", "16:00"]
+            .iter()
+            .map(|t| (format!("2026/07/16 {t}:00"), 300.0))
+            .collect();
+
+        // The same wholesale price is only above it at peak: 15:05, 15:10 and the
+        // half hour to 16:00 (the gap before it is capped at 30 minutes)
+        assert_eq!(ausgrid.hours_above(&prices, (shoulder + peak) / 2.0), 40.0 / 60.0);
+        assert_eq!(ausgrid.hours_above(&prices, shoulder - 1.0), 50.0 / 60.0);
+        assert_eq!(ausgrid.hours_above(&prices, peak), 0.0);
+    }
+
+    #[test]
+    fn weekday_only_peaks_fall_back_to_shoulder() {
+        let tas = find("tasnetworks_tou").unwrap();
+        assert_eq!(tas.period_at("2026/07/17 08:00:00", DISPATCH_MINUTES).label(), "peak");
+        assert_eq!(tas.period_at("2026/07/18 08:00:00", DISPATCH_MINUTES).label(), "shoulder");
+        assert_eq!(tas.period_at("2026/07/18 12:00:00", DISPATCH_MINUTES).label(), "off-peak");
+        // The Monday peak starts with the interval starting at 07:00
+        assert_eq!(tas.period_at("2026/07/20 07:00:00", DISPATCH_MINUTES).label(), "off-peak");
+        assert_eq!(tas.period_at("2026/07/20 07:30:00", PREDISPATCH_MINUTES).label(), "peak");
+
+        let endeavour = find("endeavour_tou").unwrap();
+        assert_eq!(endeavour.period_at("2026/07/18 17:00:00", DISPATCH_MINUTES).label(), "peak");
+    }
+
+    #[test]
+    fn outlook_lengths() {
+        let points = |times: &[&str]| times.iter().map(|t| (t.to_string(), 0.0)).collect::<Vec<_>>();
+        let mixed = points(&["2026/07/16 15:05:00", "2026/07/16 15:10:00", "2026/07/16 15:30:00", "2026/07/16 16:00:00"]);
+        assert_eq!(outlook_minutes(&mixed), vec![5, 5, 30, 30]);
+        assert_eq!(outlook_minutes(&points(&["2026/07/16 15:30:00", "2026/07/16 16:00:00"])), vec![30, 30]);
+        assert_eq!(outlook_minutes(&points(&["2026/07/16 15:30:00"])), vec![30]);
+        assert!(outlook_minutes(&[]).is_empty());
+    }
+}
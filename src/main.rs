@@ -3,6 +3,7 @@ mod config;
 mod data;
 mod db;
 mod engine;
+mod timezone;
 
 use std::sync::Arc;
 use teloxide::dispatching::UpdateFilterExt;
@@ -20,17 +21,42 @@ async fn main() -> anyhow::Result<()> {
 
     let cfg = config::Config::from_env()?;
     let db = Arc::new(db::Db::new(&cfg.database_url)?);
-    let bot = Bot::new(&cfg.teloxide_token);
+
+    // CLI subcommands run once and exit instead of starting the bot
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("backfill") => {
+            let backfill_args = engine::backfill::parse_args(&args[1..])?;
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(300))
+                .build()?;
+            return engine::backfill::run(&db, &client, &cfg.nemweb_base_url, &backfill_args).await;
+        }
+        Some("export") => return engine::export::run(&db, &engine::export::parse_args(&args[1..])?),
+        Some("registry") => {
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(60))
+                .build()?;
+            return data::generators::write_registry(&client, &cfg.nemweb_base_url, &args[1..]).await;
+        }
+        _ => {}
+    }
+
+    let bot = Bot::new(cfg.telegram_token()?);
+    let delivery = Arc::new(bot::notifier::Delivery::new(bot.clone(), &cfg)?);
+    let registry = Arc::new(data::generators::Registry::load(cfg.duid_registry_path.as_deref())?);
 
     tracing::info!("NEM Price Bot starting...");
 
     // Spawn background scheduler
     let sched_db = db.clone();
-    let sched_bot = bot.clone();
+    let sched_delivery = delivery.clone();
     let admin_id = cfg.admin_chat_id;
+    let nemweb_base = cfg.nemweb_base_url.clone();
     tokio::spawn(async move {
-        engine::scheduler::run(sched_db, sched_bot, admin_id).await;
+        engine::scheduler::run(sched_db, sched_delivery, admin_id, nemweb_base, registry).await;
     });
+    let cfg = Arc::new(cfg);
 
     // Bot dispatcher
     let handler = dptree::entry()
@@ -42,7 +68,7 @@ async fn main() -> anyhow::Result<()> {
         .branch(Update::filter_callback_query().endpoint(bot::callbacks::handle));
 
     Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![db])
+        .dependencies(dptree::deps![db, cfg, delivery])
         .enable_ctrlc_handler()
         .build()
         .dispatch()
@@ -0,0 +1,98 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// AEMO market time is AEST (UTC+10) all year round. Brisbane has no DST.
+pub const MARKET_TZ: Tz = chrono_tz::Australia::Brisbane;
+
+/// Timestamp format used by AEMO files and stored in the DB, e.g. "2026/02/27 14:35:00".
+pub const MARKET_FMT: &str = "%Y/%m/%d %H:%M:%S";
+
+/// Civil timezone for a NEM region's capital city.
+pub fn region_tz(region: &str) -> Tz {
+    match region {
+        "NSW1" => chrono_tz::Australia::Sydney,
+        "VIC1" => chrono_tz::Australia::Melbourne,
+        "QLD1" => chrono_tz::Australia::Brisbane,
+        "SA1" => chrono_tz::Australia::Adelaide,
+        "TAS1" => chrono_tz::Australia::Hobart,
+        _ => MARKET_TZ,
+    }
+}
+
+pub fn now_market() -> DateTime<Tz> {
+    Utc::now().with_timezone(&MARKET_TZ)
+}
+
+/// Format any instant as a market-time string comparable with stored interval times.
+pub fn market_str<T: TimeZone>(dt: &DateTime<T>) -> String {
+    dt.with_timezone(&MARKET_TZ).format(MARKET_FMT).to_string()
+}
+
+pub fn parse_market(s: &str) -> Option<DateTime<Tz>> {
+    NaiveDateTime::parse_from_str(s, MARKET_FMT)
+        .ok()
+        .and_then(|naive| naive.and_local_timezone(MARKET_TZ).single())
+}
+
+/// Convert an AEMO market-time string into the given civil timezone.
+pub fn to_local(interval_time: &str, tz: Tz) -> Option<DateTime<Tz>> {
+    parse_market(interval_time).map(|dt| dt.with_timezone(&tz))
+}
+
+/// "2026/02/27 14:35:00" (AEST) -> "15:35" in Sydney during daylight saving.
+pub fn format_local_time(interval_time: &str, tz: Tz) -> String {
+    match to_local(interval_time, tz) {
+        Some(dt) => dt.format("%H:%M").to_string(),
+        None if interval_time.len() >= 16 => interval_time[11..16].to_string(),
+        None => interval_time.to_string(),
+    }
+}
+
+/// Current abbreviation for a timezone, e.g. "AEDT" or "ACST".
+pub fn tz_abbrev(tz: Tz) -> String {
+    Utc::now().with_timezone(&tz).format("%Z").to_string()
+}
+
+/// Abbreviation in force at a market-time timestamp, so a time shown from across a
+/// daylight-saving change carries its own offset: "AEDT" for a summer interval in Sydney
+/// even when read in winter.
+pub fn tz_abbrev_at(interval_time: &str, tz: Tz) -> String {
+    match to_local(interval_time, tz) {
+        Some(dt) => dt.format("%Z").to_string(),
+        None => tz_abbrev(tz),
+    }
+}
+
+pub fn local_today(tz: Tz) -> NaiveDate {
+    Utc::now().with_timezone(&tz).date_naive()
+}
+
+/// Market-time bounds `[start, end)` covering one local calendar day in `tz`.
+pub fn local_day_bounds(tz: Tz, date: NaiveDate) -> (String, String) {
+    let start_of = |d: NaiveDate| {
+        // Australian DST changes happen at 2-3am, so local midnight always exists
+        tz.from_local_datetime(&d.and_time(chrono::NaiveTime::MIN))
+            .earliest()
+            .map(|dt| market_str(&dt))
+            .unwrap_or_else(|| format!("{} 00:00:00", d.format("%Y/%m/%d")))
+    };
+    let next = date.succ_opt().unwrap_or(date);
+    (start_of(date), start_of(next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviation_follows_the_timestamp() {
+        let sydney = chrono_tz::Australia::Sydney;
+        assert_eq!(tz_abbrev_at("2026/01/15 12:00:00", sydney), "AEDT");
+        assert_eq!(tz_abbrev_at("2026/07/15 12:00:00", sydney), "AEST");
+        // DST ends at 3am AEDT on 5 April 2026, which is 2am market time
+        assert_eq!(tz_abbrev_at("2026/04/05 01:55:00", sydney), "AEDT");
+        assert_eq!(tz_abbrev_at("2026/04/05 02:00:00", sydney), "AEST");
+        assert_eq!(tz_abbrev_at("2026/01/15 12:00:00", chrono_tz::Australia::Brisbane), "AEST");
+        assert_eq!(tz_abbrev_at("not a time", sydney), tz_abbrev(sydney));
+    }
+}